@@ -0,0 +1,197 @@
+//! Imports a directory of markdown posts with Hugo or Jekyll front matter.
+//!
+//! ```sh
+//! cargo run --bin import_markdown -- <dir> --author <email> [--dry-run]
+//! ```
+//!
+//! Uses the same environment as the api. Posts are keyed by their path under `<dir>`, so
+//! running the import again only saves the files that changed. `--dry-run` reports what
+//! would be imported without writing anything.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use nb_lib::{
+    import::markdown::{parse_markdown_post, MARKDOWN_EXTENSIONS},
+    models::{
+        import::{ImportAction, ImportError, ImportPostArgs},
+        locale::LocaleConfig,
+    },
+    services::{s_persons::PersonsService, s_posts::PostsService},
+    utils::{db_connection, locale_config},
+};
+use time::OffsetDateTime;
+use tracing::{error, info, warn};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+const USAGE: &str = "usage: import_markdown <dir> --author <email> [--dry-run]";
+
+struct ImportArgs {
+    dir: PathBuf,
+    /// Email of the person the imported drafts are saved as.
+    author: String,
+    dry_run: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let env_loaded = dotenvy::dotenv().is_ok();
+
+    // the api's RUST_LOG leaves out this binary's progress
+    let filter = EnvFilter::from_default_env()
+        .add_directive("import_markdown=info".parse().expect("valid log directive"));
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(filter)
+        .init();
+
+    if !env_loaded {
+        warn!("unable to load .env");
+    }
+
+    let Some(args) = parse_args() else {
+        error!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    let conn = db_connection();
+    let locales = locale_config();
+    let posts = PostsService::new(conn.clone(), locales.clone()).await;
+    let persons = PersonsService::new(conn).await;
+
+    let Some(author) = persons.get_person_by_email(args.author.clone()).await else {
+        error!("no person with email: {}", args.author);
+        return ExitCode::FAILURE;
+    };
+
+    let mut files = vec![];
+    if let Err(e) = markdown_files(&args.dir, &mut files) {
+        error!("unable to read {}: {}", args.dir.display(), e);
+        return ExitCode::FAILURE;
+    }
+    files.sort();
+
+    let (mut created, mut updated, mut unchanged, mut skipped) = (0, 0, 0, 0);
+
+    for path in files {
+        let relative = path.strip_prefix(&args.dir).unwrap_or(&path);
+
+        let result = match read_post(&path, relative, &locales) {
+            Ok(post) => {
+                posts
+                    .import_post(post, author.id.clone(), args.dry_run)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(result) => {
+                match result.action {
+                    ImportAction::Created => created += 1,
+                    ImportAction::Updated => updated += 1,
+                    ImportAction::Unchanged => unchanged += 1,
+                }
+                info!(
+                    post_id = result.post_id.as_deref().unwrap_or("-"),
+                    "{:?}: {}", result.action, result.source
+                );
+            }
+            Err(e) => {
+                skipped += 1;
+                warn!("skipped {}: {}", relative.display(), e);
+            }
+        }
+    }
+
+    if !args.dry_run && created + updated > 0 {
+        posts.refresh_related().await;
+    }
+
+    info!(
+        "{} created, {} updated, {} unchanged, {} skipped{}",
+        created,
+        updated,
+        unchanged,
+        skipped,
+        if args.dry_run {
+            " (dry run, nothing was written)"
+        } else {
+            ""
+        }
+    );
+
+    if skipped > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn parse_args() -> Option<ImportArgs> {
+    let mut dir = None;
+    let mut author = None;
+    let mut dry_run = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--author" => author = Some(args.next()?),
+            "--dry-run" => dry_run = true,
+            _ if dir.is_none() && !arg.starts_with("--") => dir = Some(PathBuf::from(arg)),
+            _ => return None,
+        }
+    }
+
+    Some(ImportArgs {
+        dir: dir?,
+        author: author?,
+        dry_run,
+    })
+}
+
+/// Markdown files under `dir`, leaving out hidden files and Hugo's `_index.md` section pages.
+fn markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+
+        if name.starts_with('.') || name.starts_with("_index.") {
+            continue;
+        }
+
+        if path.is_dir() {
+            markdown_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| MARKDOWN_EXTENSIONS.contains(&e))
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn read_post(
+    path: &Path,
+    relative: &Path,
+    locales: &LocaleConfig,
+) -> Result<ImportPostArgs, ImportError> {
+    let contents = fs::read_to_string(path).map_err(|e| ImportError::Unreadable(e.to_string()))?;
+
+    // the file's modified time stands in for posts without a date
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(OffsetDateTime::from)
+        .unwrap_or_else(|_| OffsetDateTime::now_utc());
+
+    parse_markdown_post(relative, &contents, modified, locales)
+}
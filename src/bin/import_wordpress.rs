@@ -0,0 +1,229 @@
+//! Imports the posts of a WordPress WXR export.
+//!
+//! ```sh
+//! cargo run --bin import_wordpress -- <export.xml> --author <email> [--dry-run] [--report <path>]
+//! ```
+//!
+//! Uses the same environment as the api. WordPress authors are matched to persons by
+//! email, and persons are created for the ones not found. Created persons have no password
+//! and can't log in. Authors without an email, and
+//! posts by authors missing from the export, are imported as the `--author` person. Posts
+//! are keyed by their WordPress guid, so running the import again only saves the posts
+//! that changed. Everything imported or skipped is written to the report, which defaults
+//! to `wordpress-import-report.json`.
+
+use std::{collections::HashMap, fs, path::PathBuf, process::ExitCode};
+
+use nb_lib::{
+    import::wordpress::read_wordpress_export,
+    models::import::{ImportAction, ImportReport, ImportReportEntry, ImportedAuthor},
+    services::{s_persons::PersonsService, s_posts::PostsService},
+    utils::{db_connection, locale_config},
+};
+use tracing::{error, info, warn};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+const USAGE: &str =
+    "usage: import_wordpress <export.xml> --author <email> [--dry-run] [--report <path>]";
+const DEFAULT_REPORT: &str = "wordpress-import-report.json";
+
+struct ImportArgs {
+    export: PathBuf,
+    /// Email of the person posts are saved as when their author can't be matched.
+    author: String,
+    dry_run: bool,
+    report: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let env_loaded = dotenvy::dotenv().is_ok();
+
+    // the api's RUST_LOG leaves out this binary's progress
+    let filter = EnvFilter::from_default_env().add_directive(
+        "import_wordpress=info"
+            .parse()
+            .expect("valid log directive"),
+    );
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(filter)
+        .init();
+
+    if !env_loaded {
+        warn!("unable to load .env");
+    }
+
+    let Some(args) = parse_args() else {
+        error!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    let export = match fs::read_to_string(&args.export)
+        .map_err(|e| e.to_string())
+        .and_then(|xml| read_wordpress_export(&xml).map_err(|e| e.to_string()))
+    {
+        Ok(export) => export,
+        Err(e) => {
+            error!("unable to read {}: {}", args.export.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let conn = db_connection();
+    let posts = PostsService::new(conn.clone(), locale_config()).await;
+    let persons = PersonsService::new(conn).await;
+
+    let Some(fallback) = persons.get_person_by_email(args.author.clone()).await else {
+        error!("no person with email: {}", args.author);
+        return ExitCode::FAILURE;
+    };
+
+    let mut report = ImportReport {
+        dry_run: args.dry_run,
+        skipped: export.skipped,
+        ..Default::default()
+    };
+
+    // person ids by WordPress login, `None` for persons a dry run would create
+    let mut authors: HashMap<String, Option<String>> = HashMap::new();
+    for author in export.authors {
+        let imported = if author.email.is_empty() {
+            warn!(
+                "author {} has no email, importing as {}",
+                author.login, args.author
+            );
+            ImportedAuthor {
+                login: author.login,
+                email: fallback.email.clone(),
+                person_id: Some(fallback.id.clone()),
+                created: false,
+            }
+        } else {
+            persons
+                .import_author(
+                    author.login,
+                    author.email,
+                    author.display_name,
+                    args.dry_run,
+                )
+                .await
+        };
+
+        info!(
+            person_id = imported.person_id.as_deref().unwrap_or("-"),
+            created = imported.created,
+            "author {}: {}",
+            imported.login,
+            imported.email
+        );
+        authors.insert(imported.login.clone(), imported.person_id.clone());
+        report.authors.push(imported);
+    }
+
+    let (mut created, mut updated, mut unchanged, mut failed) = (0, 0, 0, 0);
+
+    for post in export.posts {
+        let source = post.post.source.clone();
+        let title = post.post.draft.title.clone();
+        let entry = |reason: String| ImportReportEntry {
+            source: source.clone(),
+            title: title.clone(),
+            reason,
+        };
+
+        let author_id = match authors.get(&post.author_login) {
+            Some(Some(person_id)) => person_id.clone(),
+            // a dry run neither creates the person nor writes the post
+            Some(None) => fallback.id.clone(),
+            None => {
+                report.warnings.push(entry(format!(
+                    "Author {} not in the export, imported as {}",
+                    post.author_login, args.author
+                )));
+                fallback.id.clone()
+            }
+        };
+
+        match posts.import_post(post.post, author_id, args.dry_run).await {
+            Ok(result) => {
+                match result.action {
+                    ImportAction::Created => created += 1,
+                    ImportAction::Updated => updated += 1,
+                    ImportAction::Unchanged => unchanged += 1,
+                }
+                info!(
+                    post_id = result.post_id.as_deref().unwrap_or("-"),
+                    "{:?}: {}", result.action, result.source
+                );
+                report
+                    .warnings
+                    .extend(post.warnings.into_iter().map(&entry));
+                report.posts.push(result);
+            }
+            Err(e) => {
+                failed += 1;
+                warn!("skipped {}: {}", source, e);
+                report.skipped.push(entry(e.to_string()));
+            }
+        }
+    }
+
+    if !args.dry_run && created + updated > 0 {
+        posts.refresh_related().await;
+    }
+
+    let written = serde_json::to_string_pretty(&report)
+        .map_err(|e| e.to_string())
+        .and_then(|json| fs::write(&args.report, json).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        error!("unable to write report {}: {}", args.report.display(), e);
+        return ExitCode::FAILURE;
+    }
+
+    info!(
+        "{} created, {} updated, {} unchanged, {} skipped, {} warnings, report written to {}{}",
+        created,
+        updated,
+        unchanged,
+        report.skipped.len(),
+        report.warnings.len(),
+        args.report.display(),
+        if args.dry_run {
+            " (dry run, no posts were written)"
+        } else {
+            ""
+        }
+    );
+
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn parse_args() -> Option<ImportArgs> {
+    let mut export = None;
+    let mut author = None;
+    let mut dry_run = false;
+    let mut report = PathBuf::from(DEFAULT_REPORT);
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--author" => author = Some(args.next()?),
+            "--dry-run" => dry_run = true,
+            "--report" => report = PathBuf::from(args.next()?),
+            _ if export.is_none() && !arg.starts_with("--") => export = Some(PathBuf::from(arg)),
+            _ => return None,
+        }
+    }
+
+    Some(ImportArgs {
+        export: export?,
+        author: author?,
+        dry_run,
+        report,
+    })
+}
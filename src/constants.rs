@@ -2,12 +2,25 @@ pub const NB_REFRESH_KEY: &str = "nbRefresh";
 pub const NB_SECRET_KEY: &str = "NOVA_SECRET";
 pub const NB_REFRESH_DURATION: &str = "REFRESH_DURATION_MINUTES";
 pub const NB_JWT_DURATION: &str = "JWT_DURATION_MINUTES";
-pub const NB_DB_ADDRESS: &str = "DB_ADDRESS";
-pub const NB_DB_USER: &str = "DB_USER";
-pub const NB_DB_PSWD: &str = "DB_PASSWORD";
-pub const NB_DB_NAMESPACE: &str = "DB_NAMESPACE";
-pub const NB_DB_NAME: &str = "DB_NAME";
 pub const NB_SERVER_ADDRESS: &str = "SERVER_ADDRESS";
 pub const NB_ALLOWED_ORIGIN: &str = "ALLOWED_ORIGIN";
 pub const NB_TLS_CERT: &str = "TLS_CERT";
 pub const NB_TLS_KEY: &str = "TLS_KEY";
+pub const NB_SCHEDULER_INTERVAL: &str = "SCHEDULER_INTERVAL_SECONDS";
+pub const NB_VIEW_SALT: &str = "VIEW_HASH_SALT";
+pub const NB_TRUSTED_PROXIES: &str = "TRUSTED_PROXIES";
+pub const NB_RANKINGS_INTERVAL: &str = "RANKINGS_INTERVAL_SECONDS";
+pub const NB_REACTION_KINDS: &str = "REACTION_KINDS";
+pub const NB_SITE_URL: &str = "SITE_URL";
+pub const NB_SITE_TITLE: &str = "SITE_TITLE";
+pub const NB_SITE_DESCRIPTION: &str = "SITE_DESCRIPTION";
+pub const NB_MEDIA_STORE: &str = "MEDIA_STORE";
+pub const NB_MEDIA_ROOT: &str = "MEDIA_ROOT";
+pub const NB_MEDIA_PUBLIC_URL: &str = "MEDIA_PUBLIC_URL";
+pub const NB_MAX_UPLOAD_BYTES: &str = "MAX_UPLOAD_BYTES";
+pub const NB_IMAGE_WIDTHS: &str = "IMAGE_WIDTHS";
+pub const NB_S3_BUCKET: &str = "S3_BUCKET";
+pub const NB_S3_REGION: &str = "S3_REGION";
+pub const NB_S3_ENDPOINT: &str = "S3_ENDPOINT";
+pub const NB_S3_ACCESS_KEY: &str = "S3_ACCESS_KEY";
+pub const NB_S3_SECRET_KEY: &str = "S3_SECRET_KEY";
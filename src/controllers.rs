@@ -1,2 +1,9 @@
+pub mod c_analytics;
+pub mod c_comments;
+pub mod c_feeds;
+pub mod c_media;
 pub mod c_persons;
 pub mod c_posts;
+pub mod c_previews;
+pub mod c_reactions;
+pub mod c_sitemap;
@@ -0,0 +1,96 @@
+use std::net::SocketAddr;
+
+use nb_lib::models::{
+    analytics::{RankingArgs, ViewRangeArgs},
+    locale::LangArgs,
+};
+use nb_lib::utils::is_record_id;
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header::USER_AGENT, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use tracing::instrument;
+
+use crate::middleware::NbBlogServices;
+use crate::utils::locale_choice;
+
+/// POST endpoint to record a view of a published post.
+/// Repeat views from the same visitor on the same day are not counted, and a malformed
+/// post id is a 404.
+#[instrument(skip(services, headers))]
+pub async fn handle_record_view(
+    State(services): State<NbBlogServices>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(post_id): Path<String>,
+) -> impl IntoResponse {
+    if !is_record_id(&post_id, "post") {
+        return StatusCode::NOT_FOUND;
+    }
+
+    let forwarded_for = headers.get("x-forwarded-for").and_then(|v| v.to_str().ok());
+    let ip = services
+        .analytics
+        .client_ip(addr.ip(), forwarded_for)
+        .to_string();
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    services
+        .analytics
+        .record_view(post_id, &ip, user_agent)
+        .await;
+
+    StatusCode::NO_CONTENT
+}
+
+/// GET endpoint returning daily unique views of a post between `?from=` and `?to=`.
+#[instrument(skip(services))]
+pub async fn get_post_views(
+    State(services): State<NbBlogServices>,
+    Path(post_id): Path<String>,
+    Query(range): Query<ViewRangeArgs>,
+) -> impl IntoResponse {
+    Json(services.analytics.get_post_views(post_id, range).await)
+}
+
+/// GET endpoint returning daily unique views across the whole site between `?from=` and `?to=`.
+#[instrument(skip(services))]
+pub async fn get_site_views(
+    State(services): State<NbBlogServices>,
+    Query(range): Query<ViewRangeArgs>,
+) -> impl IntoResponse {
+    Json(services.analytics.get_site_views(range).await)
+}
+
+/// GET endpoint returning the most-read published posts.
+/// Pass `?window=week|month|year|all` to choose the period and `?limit=` for how many, and
+/// `?lang=` or `Accept-Language` to choose the language of each post.
+#[instrument(skip(services, headers))]
+pub async fn get_popular_posts(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<RankingArgs>,
+    Query(lang): Query<LangArgs>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let locale = locale_choice(services.posts.locales(), &lang, &headers);
+    Json(services.analytics.get_popular_posts(args, &locale))
+}
+
+/// GET endpoint returning published posts ranked by recent views, with older views
+/// counting for less. Pass `?lang=` or `Accept-Language` to choose the language of each post.
+#[instrument(skip(services, headers))]
+pub async fn get_trending_posts(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<RankingArgs>,
+    Query(lang): Query<LangArgs>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let locale = locale_choice(services.posts.locales(), &lang, &headers);
+    Json(services.analytics.get_trending_posts(args, &locale))
+}
@@ -0,0 +1,155 @@
+use nb_lib::models::{
+    comment::{
+        CommentError, CommentSettings, CreateCommentArgs, EditCommentArgs, ModerateCommentArgs,
+        ModerationQueueArgs,
+    },
+    page::PageArgs,
+    person::Person,
+};
+use nb_lib::utils::is_record_id;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use tracing::instrument;
+
+use crate::middleware::NbBlogServices;
+
+/// GET endpoint returning the approved comments on a post as a thread of nested replies.
+#[instrument(skip(services))]
+pub async fn get_post_comments(
+    State(services): State<NbBlogServices>,
+    Path(post_id): Path<String>,
+) -> impl IntoResponse {
+    if !is_record_id(&post_id, "post") {
+        return Err(comment_error_response(CommentError::NotFound));
+    }
+
+    services
+        .comments
+        .get_post_comments(post_id)
+        .await
+        .map(Json)
+        .map_err(comment_error_response)
+}
+
+/// POST endpoint to comment on a published post, or reply to a comment with `parent_id`.
+/// Sends the new comment in the response body.
+#[instrument(skip(services, args))]
+pub async fn handle_create_comment(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(post_id): Path<String>,
+    Json(args): Json<CreateCommentArgs>,
+) -> impl IntoResponse {
+    services
+        .comments
+        .create_comment(post_id, current_person.0, args)
+        .await
+        .map(Json)
+        .map_err(comment_error_response)
+}
+
+/// PUT endpoint for a person to edit their own comment shortly after posting it.
+#[instrument(skip(services, args))]
+pub async fn handle_edit_comment(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(comment_id): Path<String>,
+    Json(args): Json<EditCommentArgs>,
+) -> impl IntoResponse {
+    services
+        .comments
+        .edit_comment(comment_id, current_person.0, args.body)
+        .await
+        .map(Json)
+        .map_err(comment_error_response)
+}
+
+/// DELETE endpoint for a person to delete their own comment shortly after posting it.
+#[instrument(skip(services))]
+pub async fn handle_delete_comment(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(comment_id): Path<String>,
+) -> impl IntoResponse {
+    services
+        .comments
+        .delete_comment(comment_id, current_person.0)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(comment_error_response)
+}
+
+/// GET endpoint listing comments awaiting moderation, or in the state passed as `?status=`.
+#[instrument(skip(services))]
+pub async fn get_moderation_queue(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<ModerationQueueArgs>,
+    Query(page): Query<PageArgs>,
+) -> impl IntoResponse {
+    Json(
+        services
+            .comments
+            .get_moderation_queue(args.status, page)
+            .await,
+    )
+}
+
+/// PUT endpoint to approve a comment, or mark it as spam or removed.
+/// Sends the moderated comment in the response body.
+#[instrument(skip(services))]
+pub async fn handle_moderate_comment(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(comment_id): Path<String>,
+    Json(args): Json<ModerateCommentArgs>,
+) -> impl IntoResponse {
+    match services
+        .comments
+        .moderate_comment(comment_id.clone(), current_person.id.clone(), args.status)
+        .await
+    {
+        Some(comment) => Ok(Json(comment)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find comment: {}", comment_id),
+        )),
+    }
+}
+
+/// PUT endpoint to open or close a post for new comments.
+#[instrument(skip(services))]
+pub async fn handle_update_comment_settings(
+    State(services): State<NbBlogServices>,
+    Path(post_id): Path<String>,
+    Json(settings): Json<CommentSettings>,
+) -> impl IntoResponse {
+    if services
+        .comments
+        .set_comments_open(post_id.clone(), settings.open)
+        .await
+    {
+        Ok(Json(settings))
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find post: {}", post_id),
+        ))
+    }
+}
+
+fn comment_error_response(e: CommentError) -> (StatusCode, String) {
+    let status = match e {
+        CommentError::NotFound => StatusCode::NOT_FOUND,
+        CommentError::Closed => StatusCode::FORBIDDEN,
+        CommentError::NotAuthor => StatusCode::FORBIDDEN,
+        CommentError::WindowExpired => StatusCode::FORBIDDEN,
+        CommentError::Invalid(_) => StatusCode::BAD_REQUEST,
+    };
+
+    (status, e.to_string())
+}
@@ -0,0 +1,102 @@
+use nb_lib::{
+    feed::write_feed,
+    models::feed::{FeedArgs, FeedFormat},
+    utils::http_date,
+};
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use tracing::instrument;
+
+use crate::middleware::NbBlogServices;
+
+/// GET endpoint serving the newest published posts as RSS 2.0.
+/// Pass `?tag=` for a single tag's posts and `?full=true` for the full rendered posts.
+#[instrument(skip(services, headers))]
+pub async fn get_rss_feed(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<FeedArgs>,
+    headers: HeaderMap,
+) -> Response {
+    feed_response(services, FeedFormat::Rss, args, headers).await
+}
+
+/// GET endpoint serving the newest published posts as Atom.
+/// Pass `?tag=` for a single tag's posts and `?full=true` for the full rendered posts.
+#[instrument(skip(services, headers))]
+pub async fn get_atom_feed(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<FeedArgs>,
+    headers: HeaderMap,
+) -> Response {
+    feed_response(services, FeedFormat::Atom, args, headers).await
+}
+
+/// GET endpoint serving the newest published posts as a JSON Feed.
+/// Pass `?tag=` for a single tag's posts and `?full=true` for the full rendered posts.
+#[instrument(skip(services, headers))]
+pub async fn get_json_feed(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<FeedArgs>,
+    headers: HeaderMap,
+) -> Response {
+    feed_response(services, FeedFormat::Json, args, headers).await
+}
+
+/// Writes out the feed, or answers 304 when the reader's cached copy is still current.
+async fn feed_response(
+    services: NbBlogServices,
+    format: FeedFormat,
+    args: FeedArgs,
+    headers: HeaderMap,
+) -> Response {
+    let feed = services.feeds.get_feed(format, args).await;
+    let body = write_feed(&feed, format);
+
+    let etag = format!(
+        "\"{}\"",
+        &hex::encode(Sha256::digest(body.as_bytes()))[..32]
+    );
+    let last_modified = http_date(feed.updated);
+
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::LAST_MODIFIED, last_modified.clone()),
+        (header::CACHE_CONTROL, "public, max-age=300".to_string()),
+    ];
+
+    if is_not_modified(&headers, &etag, &last_modified) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+
+    (
+        cache_headers,
+        [(header::CONTENT_TYPE, format.content_type())],
+        body,
+    )
+        .into_response()
+}
+
+/// Whether the request's validators match the current feed. `If-None-Match` wins over
+/// `If-Modified-Since` when both are sent, as the HTTP spec asks.
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: &str) -> bool {
+    if let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+    {
+        return if_none_match
+            .split(',')
+            .map(|t| t.trim().trim_start_matches("W/"))
+            .any(|t| t == etag || t == "*");
+    }
+
+    // readers send back the Last-Modified they were given, so an exact match is enough
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|since| since == last_modified)
+}
@@ -0,0 +1,194 @@
+use nb_lib::{
+    constants::MEDIA_CACHE_CONTROL,
+    models::{
+        media::{DeleteMediaArgs, MediaDetailsArgs, MediaError, MediaListArgs, UploadedMedia},
+        page::PageArgs,
+        person::Person,
+    },
+};
+
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use tracing::{error, instrument};
+
+use crate::middleware::NbBlogServices;
+
+/// POST endpoint to upload a media file as the `file` field of a multipart body.
+/// Sends the new media in the response body with a 201, or the existing media with a 200
+/// when the same content was uploaded before.
+#[instrument(skip(services, multipart))]
+pub async fn handle_upload_media(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<UploadedMedia>), (StatusCode, String)> {
+    let max = services.media.max_upload_bytes();
+
+    let mut file = None;
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| media_error_response(MediaError::Malformed(e.body_text())))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let name = field.file_name().map(String::from);
+        let mut bytes = vec![];
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|e| media_error_response(MediaError::Malformed(e.body_text())))?
+        {
+            // stop reading as soon as the limit is passed instead of buffering the rest
+            if bytes.len() + chunk.len() > max {
+                return Err(media_error_response(MediaError::TooLarge(max)));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        file = Some((name, bytes));
+        break;
+    }
+
+    let (name, bytes) = file.ok_or_else(|| media_error_response(MediaError::MissingFile))?;
+
+    let uploaded = services
+        .media
+        .upload(current_person.id.clone(), name, bytes)
+        .await
+        .map_err(media_error_response)?;
+
+    let status = if uploaded.duplicate {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+
+    Ok((status, Json(uploaded)))
+}
+
+/// GET endpoint serving an uploaded file. Keys come from the content hash, so responses
+/// can be cached for good.
+#[instrument(skip(services, headers))]
+pub async fn get_media_file(
+    State(services): State<NbBlogServices>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(file) = services.media.get_file(key.clone()).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Unable to find media: {}", key),
+        )
+            .into_response();
+    };
+
+    let etag = format!("\"{}\"", file.etag);
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, MEDIA_CACHE_CONTROL.to_string()),
+    ];
+
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|t| t.trim() == etag));
+
+    if cached {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+
+    (
+        cache_headers,
+        [
+            (header::CONTENT_TYPE, file.content_type),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        file.bytes,
+    )
+        .into_response()
+}
+
+/// GET endpoint listing the media library, filtered by `?type=` and `?q=`.
+#[instrument(skip(services))]
+pub async fn get_media_library(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<MediaListArgs>,
+    Query(page): Query<PageArgs>,
+) -> impl IntoResponse {
+    Json(services.media.list_media(args, page).await)
+}
+
+/// PUT endpoint to set the alt text and caption of media.
+/// Sends the updated media in the response body.
+#[instrument(skip(services))]
+pub async fn handle_update_media(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(key): Path<String>,
+    Json(args): Json<MediaDetailsArgs>,
+) -> impl IntoResponse {
+    services
+        .media
+        .update_details(key, current_person.id.clone(), args)
+        .await
+        .map(Json)
+        .map_err(media_error_response)
+}
+
+/// GET endpoint listing the drafts that use media as their image or in their markdown.
+#[instrument(skip(services))]
+pub async fn get_media_references(
+    State(services): State<NbBlogServices>,
+    Path(key): Path<String>,
+) -> impl IntoResponse {
+    services
+        .media
+        .get_references(key)
+        .await
+        .map(Json)
+        .map_err(media_error_response)
+}
+
+/// DELETE endpoint to trash media. Sends a 409 while published posts use it, unless
+/// `?force=true` is passed.
+#[instrument(skip(services))]
+pub async fn handle_delete_media(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(key): Path<String>,
+    Query(args): Query<DeleteMediaArgs>,
+) -> impl IntoResponse {
+    services
+        .media
+        .delete_media(key, current_person.id.clone(), args.force)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(media_error_response)
+}
+
+fn media_error_response(e: MediaError) -> (StatusCode, String) {
+    let status = match &e {
+        MediaError::MissingFile
+        | MediaError::Empty
+        | MediaError::Malformed(_)
+        | MediaError::Invalid(_) => StatusCode::BAD_REQUEST,
+        MediaError::NotFound => StatusCode::NOT_FOUND,
+        MediaError::InUse(_) => StatusCode::CONFLICT,
+        MediaError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+        MediaError::Undecodable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        MediaError::UnsupportedType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        MediaError::Store(message) => {
+            error!("{}", message);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+
+    (status, e.to_string())
+}
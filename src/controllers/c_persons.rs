@@ -18,10 +18,13 @@ use jwt_simple::{
 };
 use nb_lib::models::{
     custom_claims::CustomClaims,
+    page::PageArgs,
     person::{
         LogInCreds, LoginResponse, Person, PersonCheck, RefreshResponse, SignUpCreds, SignUpState,
+        UpdateProfileArgs,
     },
 };
+use nb_lib::utils::is_record_id;
 use time::{Duration, OffsetDateTime};
 use tracing::{error, info, instrument, warn};
 
@@ -228,11 +231,73 @@ pub async fn handle_get_person(
     ))
 }
 
+/// PUT endpoint for a person to replace their own profile.
+/// Sends the updated person in the response body.
 #[instrument(skip(services))]
-pub async fn get_persons(State(services): State<NbBlogServices>) -> impl IntoResponse {
+pub async fn handle_update_profile(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(person_id): Path<String>,
+    Json(profile): Json<UpdateProfileArgs>,
+) -> impl IntoResponse {
+    if current_person.id != person_id {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(NovaWebError {
+                id: NovaWebErrorId::Forbidden,
+                message: "You can only update your own profile.".into(),
+                context: Some(NovaWebErrorContext::Profile),
+            }),
+        ));
+    }
+
+    if let Err(message) = profile.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(NovaWebError {
+                id: NovaWebErrorId::InvalidInput,
+                message,
+                context: Some(NovaWebErrorContext::Profile),
+            }),
+        ));
+    }
+
+    Ok(Json(
+        services.persons.update_profile(person_id, profile).await,
+    ))
+}
+
+/// GET endpoint for the public profile of an author. Never includes their email or
+/// admin status, and people without a published post are not found.
+#[instrument(skip(services))]
+pub async fn handle_get_author(
+    State(services): State<NbBlogServices>,
+    Path(author_id): Path<String>,
+) -> impl IntoResponse {
+    if !is_record_id(&author_id, "person") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Invalid author id: {}", author_id),
+        ));
+    }
+
+    match services.persons.get_author(author_id.clone()).await {
+        Some(author) => Ok(Json(author)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find author with id: {}", author_id),
+        )),
+    }
+}
+
+#[instrument(skip(services))]
+pub async fn get_persons(
+    State(services): State<NbBlogServices>,
+    Query(page): Query<PageArgs>,
+) -> impl IntoResponse {
     info!("c: get persons");
 
-    let persons = services.persons.get_persons().await;
+    let persons = services.persons.get_persons(page).await;
 
     Json(persons)
 }
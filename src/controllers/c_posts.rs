@@ -1,70 +1,201 @@
-use nb_lib::models::{person::Person, post::DraftPostArgs};
+use nb_lib::models::{
+    diff::DiffArgs,
+    locale::LangArgs,
+    page::PageArgs,
+    person::Person,
+    post::{
+        DraftError, DraftPostArgs, PurgeArgs, RelatedArgs, RenderArgs, RevertArgs, ScheduleArgs,
+    },
+    search::SearchArgs,
+};
+use nb_lib::utils::is_record_id;
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Extension, Json,
 };
 use tracing::{info, instrument};
 
 use crate::middleware::NbBlogServices;
+use crate::utils::locale_choice;
 
-#[instrument(skip(services))]
-pub async fn handle_get_random_post(State(services): State<NbBlogServices>) -> impl IntoResponse {
-    let post = services.posts.get_random_post().await;
-    Json(post)
+/// GET endpoint to get a random published post, with its reactions.
+/// Pass `?format=html` to receive the post rendered instead of its markdown, and `?lang=` or
+/// `Accept-Language` to choose its language.
+#[instrument(skip(services, headers))]
+pub async fn handle_get_random_post(
+    State(services): State<NbBlogServices>,
+    current_person: Option<Extension<Person>>,
+    Query(render): Query<RenderArgs>,
+    Query(lang): Query<LangArgs>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let locale = locale_choice(services.posts.locales(), &lang, &headers);
+    let post = services.posts.get_random_post(locale).await;
+    let post = services
+        .reactions
+        .with_reactions(post, current_person.map(|p| p.0.id))
+        .await;
+
+    Json(post.render(render.format))
 }
 
 #[instrument(skip(services))]
-pub async fn get_posts(State(services): State<NbBlogServices>) -> impl IntoResponse + 'static {
-    let posts = services.posts.get_posts().await;
+pub async fn get_posts(
+    State(services): State<NbBlogServices>,
+    Query(page): Query<PageArgs>,
+) -> impl IntoResponse + 'static {
+    let posts = services.posts.get_posts(page).await;
     Json(posts)
 }
 
+/// GET endpoint listing the drafts of a post, rendered instead of markdown
+/// when `?format=html`.
 #[instrument(skip(services))]
 pub async fn get_post_drafts(
     State(services): State<NbBlogServices>,
     Path(post_id): Path<String>,
+    Query(render): Query<RenderArgs>,
+) -> impl IntoResponse {
+    let drafts = services.posts.get_post_drafts(post_id).await;
+
+    Json(
+        drafts
+            .into_iter()
+            .map(|d| d.render(render.format))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// GET endpoint to diff two versions of a post, by default the published version against the
+/// latest draft. Sends the diff in the response body.
+#[instrument(skip(services))]
+pub async fn handle_diff_drafts(
+    State(services): State<NbBlogServices>,
+    Path(post_id): Path<String>,
+    Query(args): Query<DiffArgs>,
 ) -> impl IntoResponse {
-    Json(services.posts.get_post_drafts(post_id).await)
+    match services.posts.diff_drafts(post_id.clone(), args).await {
+        Some(diff) => Ok(Json(diff)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!(
+                "Unable to find both versions to compare for post: {}",
+                post_id
+            ),
+        )),
+    }
 }
 
 /// GET endpoint to handle getting a draft based on the draft_id passed in the request url.
-/// Sends the retrieved draft in the response body.
+/// Sends the retrieved draft in the response body, rendered instead of markdown when
+/// `?format=html`.
+/// Published drafts include their reactions.
 #[instrument(skip(services))]
 pub async fn get_draft(
     State(services): State<NbBlogServices>,
+    current_person: Option<Extension<Person>>,
     Path(draft_id): Path<String>,
+    Query(render): Query<RenderArgs>,
 ) -> impl IntoResponse {
-    let draft = services.posts.get_draft(draft_id).await;
+    match services.posts.get_draft(draft_id.clone()).await {
+        Some(draft) => {
+            let draft = services
+                .reactions
+                .with_reactions(draft, current_person.map(|p| p.0.id))
+                .await;
 
-    Json(draft)
+            Ok(Json(draft.render(render.format)))
+        }
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find draft: {}", draft_id),
+        )),
+    }
 }
 
-/// POST endpoint to handle the creation of a draft.
-/// Sends the newly created draft in the response body.
+/// POST endpoint to handle the creation of a draft. With `autosave` set, the caller's
+/// working copy of the post is updated in place instead of adding a version to its history.
+/// Sends the newly created draft in the response body, or a 409 with the newer draft when
+/// `base_draft_id` is out of date and `force` is not set. Sends a 400 when the excerpt or
+/// an SEO field is invalid.
 #[instrument(skip(services))]
 pub async fn handle_create_draft(
     State(services): State<NbBlogServices>,
     current_person: Extension<Person>,
     draft_post: Json<DraftPostArgs>,
+) -> Response {
+    match services
+        .posts
+        .create_draft(draft_post.0.clone(), current_person.id.clone())
+        .await
+    {
+        Ok(new_draft) => Json(new_draft).into_response(),
+        Err(DraftError::Conflict(conflict)) => {
+            (StatusCode::CONFLICT, Json(conflict)).into_response()
+        }
+        Err(DraftError::Invalid(message)) => (StatusCode::BAD_REQUEST, message).into_response(),
+        Err(DraftError::NotFound) => {
+            (StatusCode::NOT_FOUND, "Unable to find draft.").into_response()
+        }
+    }
+}
+
+/// POST endpoint to freeze the caller's autosaved working copy of a post into a checkpoint,
+/// in the translation passed as `?lang=`. Sends the checkpointed draft in the response body.
+#[instrument(skip(services))]
+pub async fn handle_checkpoint_draft(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(post_id): Path<String>,
+    Query(lang): Query<LangArgs>,
+) -> impl IntoResponse {
+    match services
+        .posts
+        .checkpoint_draft(post_id.clone(), lang.lang, current_person.id.clone())
+        .await
+    {
+        Some(draft) => Ok(Json(draft)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("No working draft to checkpoint for post: {}", post_id),
+        )),
+    }
+}
+
+/// POST endpoint to create a new draft from an earlier version of a post.
+/// Pass `?publish=true` to publish the restored draft straight away.
+/// Sends the newly created draft in the response body.
+#[instrument(skip(services))]
+pub async fn handle_revert_draft(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(draft_id): Path<String>,
+    Query(args): Query<RevertArgs>,
 ) -> impl IntoResponse {
     let new_draft = services
         .posts
-        .create_draft(draft_post.0.clone(), current_person.id.clone())
+        .revert_draft(draft_id, current_person.id.clone(), args.publish)
         .await;
 
     Json(new_draft)
 }
 
+/// GET endpoint listing the current draft of each unpublished post, rendered instead of
+/// markdown when `?format=html`.
 #[instrument(skip(services))]
-pub async fn get_drafted_posts(State(services): State<NbBlogServices>) -> impl IntoResponse {
+pub async fn get_drafted_posts(
+    State(services): State<NbBlogServices>,
+    Query(page): Query<PageArgs>,
+    Query(render): Query<RenderArgs>,
+) -> impl IntoResponse {
     info!("c: get drafted posts");
 
-    let posts = services.posts.get_drafted_posts().await;
+    let posts = services.posts.get_drafted_posts(page).await;
 
-    Json(posts)
+    Json(posts.map(|p| p.render(render.format)))
 }
 
 #[instrument]
@@ -80,9 +211,55 @@ pub async fn publish_draft(
     StatusCode::NO_CONTENT
 }
 
+/// POST endpoint to set or clear when a draft is automatically published and unpublished.
+/// Sends the updated draft in the response body, or a 404 when the draft doesn't exist or
+/// is in the trash.
+#[instrument(skip(services))]
+pub async fn handle_schedule_draft(
+    State(services): State<NbBlogServices>,
+    Path(draft_id): Path<String>,
+    Json(schedule): Json<ScheduleArgs>,
+) -> impl IntoResponse {
+    match services
+        .posts
+        .schedule_draft(draft_id.clone(), schedule)
+        .await
+    {
+        Ok(draft) => Json(draft).into_response(),
+        Err(DraftError::Conflict(conflict)) => {
+            (StatusCode::CONFLICT, Json(conflict)).into_response()
+        }
+        Err(DraftError::Invalid(message)) => (StatusCode::BAD_REQUEST, message).into_response(),
+        Err(DraftError::NotFound) => (
+            StatusCode::NOT_FOUND,
+            format!("Unable to find draft: {}", draft_id),
+        )
+            .into_response(),
+    }
+}
+
+/// GET endpoint listing upcoming scheduled publishes and unpublishes, soonest first.
 #[instrument(skip(services))]
-pub async fn get_published_posts(State(services): State<NbBlogServices>) -> impl IntoResponse {
-    let posts = services.posts.get_published_posts().await;
+pub async fn get_scheduled_changes(State(services): State<NbBlogServices>) -> impl IntoResponse {
+    Json(services.posts.get_scheduled_changes().await)
+}
+
+/// GET endpoint listing published posts, each in the language chosen by `?lang=` or
+/// `Accept-Language` when translated into it, and in the fallback language otherwise.
+#[instrument(skip(services, headers))]
+pub async fn get_published_posts(
+    State(services): State<NbBlogServices>,
+    current_person: Option<Extension<Person>>,
+    Query(page): Query<PageArgs>,
+    Query(lang): Query<LangArgs>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let locale = locale_choice(services.posts.locales(), &lang, &headers);
+    let posts = services.posts.get_published_posts(page, locale).await;
+    let posts = services
+        .reactions
+        .with_page_reactions(posts, current_person.map(|p| p.0.id))
+        .await;
 
     Json(posts)
 }
@@ -96,3 +273,174 @@ pub async fn unpublish_post(
 
     StatusCode::NO_CONTENT
 }
+
+/// GET endpoint listing published posts related to a post, most related first.
+/// Pass `?limit=` to choose how many.
+#[instrument(skip(services, headers))]
+pub async fn get_related_posts(
+    State(services): State<NbBlogServices>,
+    Path(post_id): Path<String>,
+    Query(args): Query<RelatedArgs>,
+    Query(lang): Query<LangArgs>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !is_record_id(&post_id, "post") {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find post: {}", post_id),
+        ));
+    }
+
+    let locale = locale_choice(services.posts.locales(), &lang, &headers);
+    Ok(Json(
+        services
+            .posts
+            .get_related_posts(post_id, args.limit, locale)
+            .await,
+    ))
+}
+
+/// GET endpoint to search published posts by title and content.
+/// Sends a page of ranked results with highlighted snippets in the response body.
+#[instrument(skip(services, headers))]
+pub async fn handle_search_posts(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<SearchArgs>,
+    Query(lang): Query<LangArgs>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let locale = locale_choice(services.posts.locales(), &lang, &headers);
+    Json(services.posts.search_posts(args, locale).await)
+}
+
+/// GET endpoint to get the published version of a post in the language chosen by `?lang=`
+/// or `Accept-Language`, falling back to the configured locale and then to any published
+/// translation. Lists every published translation for `hreflang` links.
+/// Pass `?format=html` to receive the post rendered instead of its markdown.
+#[instrument(skip(services, headers))]
+pub async fn get_published_post(
+    State(services): State<NbBlogServices>,
+    current_person: Option<Extension<Person>>,
+    Path(post_id): Path<String>,
+    Query(render): Query<RenderArgs>,
+    Query(lang): Query<LangArgs>,
+    headers: HeaderMap,
+) -> Response {
+    let locale = locale_choice(services.posts.locales(), &lang, &headers);
+
+    let Some(post) = services
+        .posts
+        .get_published_post(post_id.clone(), locale)
+        .await
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Unable to find published post: {}", post_id),
+        )
+            .into_response();
+    };
+
+    let post = services
+        .reactions
+        .with_reactions(post, current_person.map(|p| p.0.id))
+        .await;
+
+    (
+        [
+            (header::CONTENT_LANGUAGE, post.lang.clone()),
+            (header::VARY, header::ACCEPT_LANGUAGE.to_string()),
+        ],
+        Json(post.render(render.format)),
+    )
+        .into_response()
+}
+
+/// DELETE endpoint to move a post and all of its drafts to the trash.
+#[instrument(skip(services))]
+pub async fn handle_trash_post(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(post_id): Path<String>,
+) -> impl IntoResponse {
+    if services
+        .posts
+        .trash_post(post_id.clone(), current_person.id.clone())
+        .await
+    {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find post: {}", post_id),
+        ))
+    }
+}
+
+/// POST endpoint to take a post and its drafts back out of the trash.
+#[instrument(skip(services))]
+pub async fn handle_restore_post(
+    State(services): State<NbBlogServices>,
+    Path(post_id): Path<String>,
+) -> impl IntoResponse {
+    if services.posts.restore_post(post_id.clone()).await {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find post: {}", post_id),
+        ))
+    }
+}
+
+/// DELETE endpoint to move a single draft to the trash.
+#[instrument(skip(services))]
+pub async fn handle_trash_draft(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(draft_id): Path<String>,
+) -> impl IntoResponse {
+    if services
+        .posts
+        .trash_draft(draft_id.clone(), current_person.id.clone())
+        .await
+    {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find draft: {}", draft_id),
+        ))
+    }
+}
+
+/// POST endpoint to take a single draft back out of the trash.
+#[instrument(skip(services))]
+pub async fn handle_restore_draft(
+    State(services): State<NbBlogServices>,
+    Path(draft_id): Path<String>,
+) -> impl IntoResponse {
+    if services.posts.restore_draft(draft_id.clone()).await {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find draft: {}", draft_id),
+        ))
+    }
+}
+
+/// GET endpoint listing trashed posts and drafts.
+#[instrument(skip(services))]
+pub async fn get_trash(State(services): State<NbBlogServices>) -> impl IntoResponse {
+    Json(services.posts.get_trash().await)
+}
+
+/// DELETE endpoint to permanently remove everything trashed more than `?older_than_days=N` ago.
+/// Sends the number of purged posts and drafts in the response body.
+#[instrument(skip(services))]
+pub async fn handle_purge_trash(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<PurgeArgs>,
+) -> impl IntoResponse {
+    Json(services.posts.purge_trash(args).await)
+}
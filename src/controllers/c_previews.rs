@@ -0,0 +1,68 @@
+use nb_lib::models::{
+    head::{OEmbedArgs, OEmbedError},
+    locale::LangArgs,
+};
+use nb_lib::utils::is_record_id;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use tracing::instrument;
+
+use crate::middleware::NbBlogServices;
+use crate::utils::locale_choice;
+
+/// GET endpoint returning the head metadata of a published post for link previews:
+/// Open Graph and Twitter card tags, a JSON-LD `BlogPosting`, and the same as markup.
+/// Pass `?lang=` or `Accept-Language` to choose the translation.
+#[instrument(skip(services, headers))]
+pub async fn get_post_head(
+    State(services): State<NbBlogServices>,
+    Path(post_id): Path<String>,
+    Query(lang): Query<LangArgs>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !is_record_id(&post_id, "post") {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find published post: {}", post_id),
+        ));
+    }
+
+    let locale = locale_choice(services.previews.locales(), &lang, &headers);
+
+    match services
+        .previews
+        .get_head_metadata(post_id.clone(), locale)
+        .await
+    {
+        Some(head) => Ok(Json(head)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find published post: {}", post_id),
+        )),
+    }
+}
+
+/// GET oEmbed provider endpoint. Pass the public post URL as `?url=`.
+#[instrument(skip(services))]
+pub async fn get_oembed(
+    State(services): State<NbBlogServices>,
+    Query(args): Query<OEmbedArgs>,
+) -> impl IntoResponse {
+    services
+        .previews
+        .get_oembed(args)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            let status = match e {
+                OEmbedError::NotFound => StatusCode::NOT_FOUND,
+                OEmbedError::UnsupportedFormat => StatusCode::NOT_IMPLEMENTED,
+            };
+            (status, e.to_string())
+        })
+}
@@ -0,0 +1,84 @@
+use nb_lib::models::{
+    person::Person,
+    reaction::{ReactionArgs, ReactionError},
+};
+use nb_lib::utils::is_record_id;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use tracing::instrument;
+
+use crate::middleware::NbBlogServices;
+
+/// GET endpoint listing the reaction kinds persons can choose from.
+#[instrument(skip(services))]
+pub async fn get_reaction_kinds(State(services): State<NbBlogServices>) -> impl IntoResponse {
+    Json(services.reactions.kinds().to_vec())
+}
+
+/// GET endpoint returning the reaction counts on a post, along with the current person's
+/// own reactions when the request is authenticated.
+#[instrument(skip(services))]
+pub async fn get_post_reactions(
+    State(services): State<NbBlogServices>,
+    current_person: Option<Extension<Person>>,
+    Path(post_id): Path<String>,
+) -> impl IntoResponse {
+    if !is_record_id(&post_id, "post") {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("Unable to find post: {}", post_id),
+        ));
+    }
+
+    Ok(Json(
+        services
+            .reactions
+            .get_post_reactions(post_id, current_person.map(|p| p.0.id))
+            .await,
+    ))
+}
+
+/// POST endpoint to react to a published post. Sends the post's reactions in the response body.
+#[instrument(skip(services))]
+pub async fn handle_add_reaction(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path(post_id): Path<String>,
+    Json(args): Json<ReactionArgs>,
+) -> impl IntoResponse {
+    services
+        .reactions
+        .add_reaction(post_id, current_person.0.id, args.kind)
+        .await
+        .map(Json)
+        .map_err(reaction_error_response)
+}
+
+/// DELETE endpoint to take back a reaction. Sends the post's reactions in the response body.
+#[instrument(skip(services))]
+pub async fn handle_remove_reaction(
+    State(services): State<NbBlogServices>,
+    current_person: Extension<Person>,
+    Path((post_id, kind)): Path<(String, String)>,
+) -> impl IntoResponse {
+    services
+        .reactions
+        .remove_reaction(post_id, current_person.0.id, kind)
+        .await
+        .map(Json)
+        .map_err(reaction_error_response)
+}
+
+fn reaction_error_response(e: ReactionError) -> (StatusCode, String) {
+    let status = match e {
+        ReactionError::NotFound => StatusCode::NOT_FOUND,
+        ReactionError::UnknownKind(_) => StatusCode::BAD_REQUEST,
+    };
+
+    (status, e.to_string())
+}
@@ -0,0 +1,45 @@
+use nb_lib::models::sitemap::Sitemap;
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use tracing::instrument;
+
+use crate::middleware::NbBlogServices;
+
+const XML_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+
+/// GET endpoint serving the sitemap of every public page, or a sitemap index when there
+/// are more pages than fit in one sitemap.
+#[instrument(skip(services))]
+pub async fn get_sitemap(State(services): State<NbBlogServices>) -> impl IntoResponse {
+    let xml = match services.sitemap.get_sitemap().await {
+        Sitemap::Urlset(xml) => xml,
+        Sitemap::Index(xml) => xml,
+    };
+
+    ([(header::CONTENT_TYPE, XML_CONTENT_TYPE)], xml)
+}
+
+/// GET endpoint serving one page of a sitemap index, e.g. `/sitemaps/2.xml`.
+#[instrument(skip(services))]
+pub async fn get_sitemap_page(
+    State(services): State<NbBlogServices>,
+    Path(page): Path<String>,
+) -> impl IntoResponse {
+    let page = page
+        .strip_suffix(".xml")
+        .and_then(|p| p.parse::<usize>().ok());
+
+    let xml = match page {
+        Some(page) => services.sitemap.get_sitemap_page(page).await,
+        None => None,
+    };
+
+    match xml {
+        Some(xml) => Ok(([(header::CONTENT_TYPE, XML_CONTENT_TYPE)], xml)),
+        None => Err((StatusCode::NOT_FOUND, "Unable to find sitemap page.")),
+    }
+}
@@ -10,6 +10,7 @@ use serde::Serialize;
 pub enum NovaWebErrorContext {
     Authentication,
     Refresh,
+    Profile,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -20,6 +21,8 @@ pub enum NovaWebErrorId {
     TokenExpired,
     NotFound,
     MissingRefreshToken,
+    InvalidInput,
+    Forbidden,
 }
 
 impl Display for NovaWebErrorId {
@@ -0,0 +1,119 @@
+use std::{env, future::Future, time::Duration};
+
+use nb_lib::services::{s_analytics::AnalyticsService, s_posts::PostsService};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tracing::{error, info, instrument};
+
+use crate::constants::{NB_RANKINGS_INTERVAL, NB_SCHEDULER_INTERVAL};
+
+/// How often the publish scheduler checks for due changes when
+/// `SCHEDULER_INTERVAL_SECONDS` is not set, or is not a positive number.
+const DEFAULT_SCHEDULER_INTERVAL_SECONDS: u64 = 30;
+
+/// Spawns the background task that applies scheduled publishes and unpublishes.
+///
+/// Schedules live on the drafts themselves, so anything that came due while the server
+/// was down is applied on the first tick after startup.
+#[instrument(skip(posts))]
+pub fn spawn_publish_scheduler(posts: PostsService) -> JoinHandle<()> {
+    let period = env::var(NB_SCHEDULER_INTERVAL)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|s| *s > 0)
+        .unwrap_or(DEFAULT_SCHEDULER_INTERVAL_SECONDS);
+
+    info!("publish scheduler running every {}s", period);
+
+    spawn_every(
+        Duration::from_secs(period),
+        "publish scheduler",
+        move || {
+            let posts = posts.clone();
+            async move {
+                posts.apply_scheduled_changes().await;
+            }
+        },
+    )
+}
+
+/// How often the popular and trending rankings are recomputed when
+/// `RANKINGS_INTERVAL_SECONDS` is not set, or is not a positive number.
+const DEFAULT_RANKINGS_INTERVAL_SECONDS: u64 = 300;
+
+/// How often visitor hashes from previous days are deleted.
+const VISITOR_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Spawns the background task that deletes visitor hashes once their day has passed.
+///
+/// Hashes are only kept to dedupe views within a day, so nothing is lost by pruning them.
+#[instrument(skip(analytics))]
+pub fn spawn_visitor_pruner(analytics: AnalyticsService) -> JoinHandle<()> {
+    spawn_every(VISITOR_PRUNE_INTERVAL, "visitor pruner", move || {
+        let analytics = analytics.clone();
+        async move {
+            analytics.prune_visitors().await;
+        }
+    })
+}
+
+/// Spawns the background task that recomputes the popular and trending post rankings,
+/// so public requests only ever read the cached result.
+#[instrument(skip(analytics))]
+pub fn spawn_rankings_refresher(analytics: AnalyticsService) -> JoinHandle<()> {
+    let period = env::var(NB_RANKINGS_INTERVAL)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|s| *s > 0)
+        .unwrap_or(DEFAULT_RANKINGS_INTERVAL_SECONDS);
+
+    info!("rankings refresher running every {}s", period);
+
+    spawn_every(
+        Duration::from_secs(period),
+        "rankings refresher",
+        move || {
+            let analytics = analytics.clone();
+            async move {
+                analytics.refresh_rankings().await;
+            }
+        },
+    )
+}
+
+/// How often the related posts are recomputed, when a post was published since the last run.
+const RELATED_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Spawns the background task that recomputes related posts after posts are published, so
+/// publishing never waits on ranking every post.
+#[instrument(skip(posts))]
+pub fn spawn_related_refresher(posts: PostsService) -> JoinHandle<()> {
+    spawn_every(RELATED_REFRESH_INTERVAL, "related refresher", move || {
+        let posts = posts.clone();
+        async move {
+            posts.refresh_related_if_stale().await;
+        }
+    })
+}
+
+/// Runs `job` on a fixed interval for the life of the server.
+///
+/// Each run gets its own task so a panic inside a service call is logged and the next
+/// tick still fires.
+fn spawn_every<F, Fut>(period: Duration, name: &'static str, job: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = tokio::spawn(job()).await {
+                error!("{} run failed: {:#?}", name, e);
+            }
+        }
+    })
+}
@@ -1 +1,61 @@
 pub const SYSTEM_ID: &str = "person:01J72MQD8NS5NBYVTVKWHRT18D";
+
+// Environment variables shared by the api and the import binaries.
+pub const NB_DB_ADDRESS: &str = "DB_ADDRESS";
+pub const NB_DB_USER: &str = "DB_USER";
+pub const NB_DB_PSWD: &str = "DB_PASSWORD";
+pub const NB_DB_NAMESPACE: &str = "DB_NAMESPACE";
+pub const NB_DB_NAME: &str = "DB_NAME";
+pub const NB_LOCALES: &str = "LOCALES";
+pub const NB_DEFAULT_LOCALE: &str = "DEFAULT_LOCALE";
+
+/// Page size used by list endpoints when the caller does not pass `limit`.
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+/// Largest page size a caller can request from a list endpoint.
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// Minutes after posting during which a person can edit their comment.
+pub const COMMENT_EDIT_WINDOW_MINUTES: i64 = 15;
+/// Minutes after posting during which a person can delete their comment.
+pub const COMMENT_DELETE_WINDOW_MINUTES: i64 = 60;
+/// Longest comment body, in characters.
+pub const MAX_COMMENT_LENGTH: usize = 5000;
+/// Deepest a reply can be nested, counting top level comments as depth 1.
+pub const MAX_COMMENT_DEPTH: usize = 5;
+
+/// Reaction kinds offered when none are configured.
+pub const DEFAULT_REACTION_KINDS: &[&str] = &["👍", "❤️", "🎉", "😂", "🤔"];
+
+/// Most entries in a syndication feed.
+pub const FEED_SIZE: u32 = 20;
+
+/// Most URLs the sitemap protocol allows in a single sitemap file.
+pub const SITEMAP_MAX_URLS: usize = 50_000;
+/// Largest uncompressed sitemap file the sitemap protocol allows, in bytes.
+pub const SITEMAP_MAX_BYTES: usize = 50 * 1024 * 1024;
+
+/// Largest media upload accepted when none is configured, in bytes.
+pub const DEFAULT_MAX_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+/// `Cache-Control` for media files. Their keys come from their content hash, so the bytes
+/// behind a URL never change.
+pub const MEDIA_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Language posts are written in when no locales are configured.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Widths uploaded images are resized to when none are configured.
+pub const DEFAULT_IMAGE_WIDTHS: &[u32] = &[320, 640, 960, 1280, 1920];
+
+/// Longest alt text for media, in characters.
+pub const MAX_ALT_TEXT_LENGTH: usize = 1000;
+/// Longest media caption, in characters.
+pub const MAX_CAPTION_LENGTH: usize = 2000;
+
+/// Longest hand written post excerpt, in characters.
+pub const MAX_EXCERPT_LENGTH: usize = 500;
+/// Longest SEO title, in characters. Search engines cut titles off at around 60.
+pub const MAX_SEO_TITLE_LENGTH: usize = 70;
+/// Longest meta description, in characters. Search engines cut them off at around 155.
+pub const MAX_META_DESCRIPTION_LENGTH: usize = 160;
+/// Longest canonical URL, in characters.
+pub const MAX_CANONICAL_URL_LENGTH: usize = 2048;
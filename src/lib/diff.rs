@@ -0,0 +1,36 @@
+use similar::{ChangeTag, TextDiff};
+
+use crate::models::diff::{DiffChange, DiffOp, FieldDiff};
+
+/// Line-level and word-level diff of one field between two versions.
+pub fn diff_field(old: &str, new: &str) -> FieldDiff {
+    FieldDiff {
+        changed: old != new,
+        lines: collect_changes(&TextDiff::from_lines(old, new)),
+        words: collect_changes(&TextDiff::from_words(old, new)),
+    }
+}
+
+/// Flattens a diff into runs, merging neighbouring changes with the same op so a
+/// word diff of a long post does not send one entry per word.
+fn collect_changes<'a>(diff: &TextDiff<'a, 'a, 'a, str>) -> Vec<DiffChange> {
+    let mut changes: Vec<DiffChange> = vec![];
+
+    for change in diff.iter_all_changes() {
+        let op = match change.tag() {
+            ChangeTag::Equal => DiffOp::Equal,
+            ChangeTag::Insert => DiffOp::Insert,
+            ChangeTag::Delete => DiffOp::Delete,
+        };
+
+        match changes.last_mut() {
+            Some(last) if last.op == op => last.value.push_str(change.value()),
+            _ => changes.push(DiffChange {
+                op,
+                value: change.value().to_string(),
+            }),
+        }
+    }
+
+    changes
+}
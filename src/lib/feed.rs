@@ -0,0 +1,164 @@
+use serde_json::{json, Map, Value};
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::OffsetDateTime;
+
+use crate::models::feed::{Feed, FeedEntry, FeedFormat};
+use crate::utils::xml_escape;
+
+/// Writes out a feed as RSS 2.0, Atom 1.0 or JSON Feed 1.1.
+pub fn write_feed(feed: &Feed, format: FeedFormat) -> String {
+    match format {
+        FeedFormat::Rss => write_rss(feed),
+        FeedFormat::Atom => write_atom(feed),
+        FeedFormat::Json => write_json(feed),
+    }
+}
+
+fn write_rss(feed: &Feed) -> String {
+    let mut out = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    out.push('\n');
+    out.push_str(
+        r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/">"#,
+    );
+    out.push_str("<channel>");
+    element(&mut out, "title", &feed.title);
+    element(&mut out, "link", &feed.home_url);
+    element(&mut out, "description", &feed.description);
+    element(&mut out, "lastBuildDate", &rfc2822(feed.updated));
+    out.push_str(&format!(
+        r#"<atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+        xml_escape(&feed.feed_url)
+    ));
+
+    for entry in &feed.entries {
+        out.push_str("<item>");
+        element(&mut out, "title", &entry.title);
+        element(&mut out, "link", &entry.url);
+        out.push_str(&format!(
+            r#"<guid isPermaLink="false">{}</guid>"#,
+            xml_escape(&entry.id)
+        ));
+        element(&mut out, "pubDate", &rfc2822(entry.published));
+        element(&mut out, "dc:creator", &entry.author_name);
+        for tag in &entry.tags {
+            element(&mut out, "category", tag);
+        }
+        element(&mut out, "description", &entry.summary);
+        if let Some(html) = &entry.content_html {
+            element(&mut out, "content:encoded", html);
+        }
+        out.push_str("</item>");
+    }
+
+    out.push_str("</channel></rss>");
+    out
+}
+
+fn write_atom(feed: &Feed) -> String {
+    let mut out = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    out.push('\n');
+    out.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    element(&mut out, "id", &feed.feed_url);
+    element(&mut out, "title", &feed.title);
+    element(&mut out, "subtitle", &feed.description);
+    element(&mut out, "updated", &rfc3339(feed.updated));
+    out.push_str(&format!(
+        r#"<link href="{}" rel="alternate" type="text/html"/>"#,
+        xml_escape(&feed.home_url)
+    ));
+    out.push_str(&format!(
+        r#"<link href="{}" rel="self" type="application/atom+xml"/>"#,
+        xml_escape(&feed.feed_url)
+    ));
+
+    for entry in &feed.entries {
+        out.push_str("<entry>");
+        element(&mut out, "id", &atom_id(entry));
+        element(&mut out, "title", &entry.title);
+        out.push_str(&format!(
+            r#"<link href="{}" rel="alternate" type="text/html"/>"#,
+            xml_escape(&entry.url)
+        ));
+        element(&mut out, "published", &rfc3339(entry.published));
+        element(&mut out, "updated", &rfc3339(entry.updated));
+        out.push_str("<author>");
+        element(&mut out, "name", &entry.author_name);
+        element(&mut out, "uri", &entry.author_url);
+        out.push_str("</author>");
+        for tag in &entry.tags {
+            out.push_str(&format!(r#"<category term="{}"/>"#, xml_escape(tag)));
+        }
+        element(&mut out, "summary", &entry.summary);
+        if let Some(html) = &entry.content_html {
+            out.push_str(&format!(
+                r#"<content type="html">{}</content>"#,
+                xml_escape(html)
+            ));
+        }
+        out.push_str("</entry>");
+    }
+
+    out.push_str("</feed>");
+    out
+}
+
+fn write_json(feed: &Feed) -> String {
+    let items: Vec<Value> = feed
+        .entries
+        .iter()
+        .map(|entry| {
+            let mut item = Map::new();
+            item.insert("id".into(), json!(entry.id));
+            item.insert("url".into(), json!(entry.url));
+            item.insert("title".into(), json!(entry.title));
+            item.insert("summary".into(), json!(entry.summary));
+            match &entry.content_html {
+                Some(html) => item.insert("content_html".into(), json!(html)),
+                None => item.insert("content_text".into(), json!(entry.summary)),
+            };
+            if let Some(image) = &entry.image {
+                item.insert("image".into(), json!(image));
+            }
+            item.insert("date_published".into(), json!(rfc3339(entry.published)));
+            item.insert("date_modified".into(), json!(rfc3339(entry.updated)));
+            item.insert(
+                "authors".into(),
+                json!([{ "name": entry.author_name, "url": entry.author_url }]),
+            );
+            if !entry.tags.is_empty() {
+                item.insert("tags".into(), json!(entry.tags));
+            }
+            Value::Object(item)
+        })
+        .collect();
+
+    json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": feed.title,
+        "home_page_url": feed.home_url,
+        "feed_url": feed.feed_url,
+        "description": feed.description,
+        "items": items,
+    })
+    .to_string()
+}
+
+/// A globally unique Atom id for a post that does not change with the site URL.
+fn atom_id(entry: &FeedEntry) -> String {
+    match entry.id.split_once(':') {
+        Some((_, ulid)) => format!("urn:ulid:{}", ulid),
+        None => format!("urn:ulid:{}", entry.id),
+    }
+}
+
+fn element(out: &mut String, name: &str, text: &str) {
+    out.push_str(&format!("<{name}>{}</{name}>", xml_escape(text)));
+}
+
+fn rfc2822(datetime: OffsetDateTime) -> String {
+    datetime.format(&Rfc2822).unwrap_or_default()
+}
+
+fn rfc3339(datetime: OffsetDateTime) -> String {
+    datetime.format(&Rfc3339).unwrap_or_default()
+}
@@ -0,0 +1,192 @@
+use serde_json::{json, Value};
+use time::format_description::well_known::Rfc3339;
+
+use crate::models::feed::SiteConfig;
+use crate::models::head::{HeadMetadata, MetaTag, OEmbed};
+use crate::models::post::PostVersion;
+use crate::render::render_markdown;
+use crate::utils::xml_escape;
+
+/// Seconds oEmbed consumers may cache a response for.
+const OEMBED_CACHE_AGE: u32 = 3600;
+
+/// Head metadata for the published version of a post.
+pub fn head_metadata(post: &PostVersion, site: &SiteConfig) -> HeadMetadata {
+    let title = post.seo_title.clone().unwrap_or_else(|| post.title.clone());
+    let description = post
+        .meta_description
+        .clone()
+        .or_else(|| post.excerpt.clone())
+        .unwrap_or_else(|| render_markdown(post.markdown()).excerpt);
+    let canonical_url = post
+        .canonical_url
+        .clone()
+        .unwrap_or_else(|| site.post_url(&post.id));
+    let image = Some(&post.image)
+        .filter(|i| !i.is_empty())
+        .map(|i| site.absolute_url(i));
+    let published_time = post
+        .published_on
+        .unwrap_or(post.at)
+        .format(&Rfc3339)
+        .unwrap_or_default();
+    let modified_time = post.at.format(&Rfc3339).unwrap_or_default();
+    let (author_name, author_url) = author(post, site);
+
+    let mut open_graph = vec![
+        tag("og:type", "article"),
+        tag("og:site_name", &site.title),
+        tag("og:title", &title),
+        tag("og:description", &description),
+        tag("og:url", &canonical_url),
+        tag("og:locale", &post.lang),
+    ];
+    for t in post.translations.iter().filter(|t| t.lang != post.lang) {
+        open_graph.push(tag("og:locale:alternate", &t.lang));
+    }
+    if let Some(image) = &image {
+        open_graph.push(tag("og:image", image));
+        if let Some(alt) = &post.cover_alt {
+            open_graph.push(tag("og:image:alt", alt));
+        }
+    }
+    open_graph.push(tag("article:published_time", &published_time));
+    open_graph.push(tag("article:modified_time", &modified_time));
+    open_graph.push(tag("article:author", &author_url));
+    for t in &post.tags {
+        open_graph.push(tag("article:tag", t));
+    }
+
+    let mut twitter = vec![
+        tag(
+            "twitter:card",
+            if image.is_some() {
+                "summary_large_image"
+            } else {
+                "summary"
+            },
+        ),
+        tag("twitter:title", &title),
+        tag("twitter:description", &description),
+    ];
+    if let Some(image) = &image {
+        twitter.push(tag("twitter:image", image));
+        if let Some(alt) = &post.cover_alt {
+            twitter.push(tag("twitter:image:alt", alt));
+        }
+    }
+
+    let json_ld = json!({
+        "@context": "https://schema.org",
+        "@type": "BlogPosting",
+        "headline": post.title,
+        "description": description,
+        "inLanguage": post.lang,
+        "url": canonical_url,
+        "mainEntityOfPage": { "@type": "WebPage", "@id": canonical_url },
+        "image": image,
+        "datePublished": published_time,
+        "dateModified": modified_time,
+        "author": { "@type": "Person", "name": author_name, "url": author_url },
+        "publisher": { "@type": "Organization", "name": site.title, "url": site.url },
+        "keywords": post.tags,
+    });
+
+    let html = head_html(
+        &title,
+        &description,
+        &canonical_url,
+        &open_graph,
+        &twitter,
+        &json_ld,
+    );
+
+    HeadMetadata {
+        title,
+        description,
+        canonical_url,
+        image,
+        published_time,
+        modified_time,
+        open_graph,
+        twitter,
+        json_ld,
+        html,
+    }
+}
+
+/// An oEmbed `link` response for the published version of a post.
+pub fn oembed(post: &PostVersion, site: &SiteConfig) -> OEmbed {
+    let (author_name, author_url) = author(post, site);
+
+    OEmbed {
+        kind: "link".into(),
+        version: "1.0".into(),
+        title: post.title.clone(),
+        author_name,
+        author_url,
+        provider_name: site.title.clone(),
+        provider_url: site.url.clone(),
+        cache_age: OEMBED_CACHE_AGE,
+        thumbnail_url: Some(&post.image)
+            .filter(|i| !i.is_empty())
+            .map(|i| site.absolute_url(i)),
+    }
+}
+
+/// Display name and profile URL of a post's author.
+fn author(post: &PostVersion, site: &SiteConfig) -> (String, String) {
+    let name = post
+        .author_summary
+        .as_ref()
+        .map(|a| a.display_name.clone().unwrap_or_else(|| a.username.clone()))
+        .unwrap_or_else(|| post.author.clone());
+
+    (name, site.author_url(&post.author))
+}
+
+fn tag(key: &str, content: &str) -> MetaTag {
+    MetaTag {
+        key: key.to_string(),
+        content: content.to_string(),
+    }
+}
+
+fn head_html(
+    title: &str,
+    description: &str,
+    canonical_url: &str,
+    open_graph: &[MetaTag],
+    twitter: &[MetaTag],
+    json_ld: &Value,
+) -> String {
+    let mut out = format!("<title>{}</title>\n", xml_escape(title));
+    out.push_str(&format!(
+        "<meta name=\"description\" content=\"{}\">\n",
+        xml_escape(description)
+    ));
+    out.push_str(&format!(
+        "<link rel=\"canonical\" href=\"{}\">\n",
+        xml_escape(canonical_url)
+    ));
+    for t in open_graph {
+        out.push_str(&format!(
+            "<meta property=\"{}\" content=\"{}\">\n",
+            xml_escape(&t.key),
+            xml_escape(&t.content)
+        ));
+    }
+    for t in twitter {
+        out.push_str(&format!(
+            "<meta name=\"{}\" content=\"{}\">\n",
+            xml_escape(&t.key),
+            xml_escape(&t.content)
+        ));
+    }
+    // keep post text from closing the script element early
+    out.push_str(&format!(
+        "<script type=\"application/ld+json\">{}</script>",
+        json_ld.to_string().replace("</", "<\\/")
+    ));
+    out
+}
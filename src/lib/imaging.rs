@@ -0,0 +1,230 @@
+use std::io::Cursor;
+
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::error::{EncodingError, ImageFormatHint, LimitError, LimitErrorKind};
+use image::imageops::FilterType;
+use image::{
+    AnimationDecoder, DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader, Limits,
+};
+
+/// Image types decoded and resized on upload. Other images are stored as uploaded.
+pub const PROCESSED_IMAGE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp"];
+
+/// Quality JPEGs are re-encoded at.
+const JPEG_QUALITY: u8 = 85;
+/// Quality WebPs of photographic sources are encoded at, out of 100.
+const WEBP_QUALITY: f32 = 80.0;
+/// Widest and tallest image decoded, in pixels.
+const MAX_IMAGE_DIMENSION: u32 = 10_000;
+/// Most memory decoding an image may take, in bytes, counting every frame of a GIF.
+const MAX_IMAGE_ALLOC: u64 = 256 * 1024 * 1024;
+/// Blurhash components across and down. More components keep more detail in the placeholder.
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+/// Images are shrunk to this width before hashing, which is plenty for a placeholder.
+const BLURHASH_WIDTH: u32 = 32;
+
+/// An uploaded image after decoding, with its metadata dropped and its variants encoded.
+#[derive(Debug)]
+pub struct ProcessedImage {
+    /// Width after applying the EXIF orientation.
+    pub width: u32,
+    pub height: u32,
+    pub blurhash: Option<String>,
+    /// The image re-encoded in its own format, without EXIF, GPS or other metadata.
+    pub original: Vec<u8>,
+    /// Smaller copies in the original format, and every size (including full size) as WebP.
+    pub variants: Vec<EncodedVariant>,
+}
+
+#[derive(Debug)]
+pub struct EncodedVariant {
+    pub width: u32,
+    pub height: u32,
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// Decodes an uploaded image, turns it upright from its EXIF orientation and re-encodes it
+/// without metadata. Variants are made for each of `widths` narrower than the image itself.
+///
+/// WebPs of JPEG and lossy WebP uploads, which are mostly photographs, are lossy. PNG and
+/// lossless WebP uploads are mostly screenshots and drawings, so their WebPs stay lossless.
+/// This is CPU bound, so run it on a blocking thread.
+pub fn process_image(
+    bytes: &[u8],
+    content_type: &str,
+    widths: &[u32],
+) -> Result<ProcessedImage, ImageError> {
+    let format = ImageFormat::from_mime_type(content_type).ok_or_else(|| {
+        ImageError::Unsupported(image::error::UnsupportedError::from(ImageFormatHint::Name(
+            content_type.to_string(),
+        )))
+    })?;
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(decode_limits());
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let (width, height) = (image.width(), image.height());
+    let lossy =
+        format == ImageFormat::Jpeg || (format == ImageFormat::WebP && is_lossy_webp(bytes));
+    let original = encode(&image, format, lossy)?;
+
+    let mut sizes: Vec<u32> = widths.iter().copied().filter(|w| *w < width).collect();
+    sizes.sort_unstable();
+    sizes.dedup();
+
+    let mut variants = Vec::new();
+    for size in sizes {
+        let resized_height = ((height as u64 * size as u64) / width as u64).max(1) as u32;
+        let resized = image.resize_exact(size, resized_height, FilterType::Lanczos3);
+
+        variants.push(variant(&resized, format, lossy)?);
+        if format != ImageFormat::WebP {
+            variants.push(variant(&resized, ImageFormat::WebP, lossy)?);
+        }
+    }
+    if format != ImageFormat::WebP {
+        variants.push(variant(&image, ImageFormat::WebP, lossy)?);
+    }
+
+    Ok(ProcessedImage {
+        width,
+        height,
+        blurhash: blurhash(&image),
+        original,
+        variants,
+    })
+}
+
+/// Re-encodes a GIF frame by frame, dropping its comments, XMP and other extensions.
+/// Animations keep their frame delays and loop forever. This is CPU bound, so run it on a
+/// blocking thread.
+pub fn strip_gif(bytes: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let mut decoder = GifDecoder::new(Cursor::new(bytes))?;
+    decoder.set_limits(decode_limits())?;
+
+    // the limits cover one frame at a time, so the frames kept are counted here
+    let mut frames = Vec::new();
+    let mut allocated: u64 = 0;
+    for frame in decoder.into_frames() {
+        let frame = frame?;
+        allocated += frame.buffer().as_raw().len() as u64;
+        if allocated > MAX_IMAGE_ALLOC {
+            return Err(ImageError::Limits(LimitError::from_kind(
+                LimitErrorKind::InsufficientMemory,
+            )));
+        }
+        frames.push(frame);
+    }
+
+    let mut stripped = Vec::new();
+    let mut encoder = GifEncoder::new(&mut stripped);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(frames)?;
+    drop(encoder);
+
+    Ok(stripped)
+}
+
+/// Limits keeping a small upload from decoding into a huge image.
+fn decode_limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    limits.max_alloc = Some(MAX_IMAGE_ALLOC);
+    limits
+}
+
+fn variant(
+    image: &DynamicImage,
+    format: ImageFormat,
+    lossy: bool,
+) -> Result<EncodedVariant, ImageError> {
+    Ok(EncodedVariant {
+        width: image.width(),
+        height: image.height(),
+        content_type: format.to_mime_type(),
+        extension: format.extensions_str()[0],
+        bytes: encode(image, format, lossy)?,
+    })
+}
+
+/// Encodes `image` as `format`, as lossy WebP when `lossy`. Nothing from the source file
+/// is carried over, so the output has no EXIF, GPS or other metadata.
+fn encode(image: &DynamicImage, format: ImageFormat, lossy: bool) -> Result<Vec<u8>, ImageError> {
+    let mut bytes = Vec::new();
+
+    match format {
+        ImageFormat::Jpeg => DynamicImage::from(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY))?,
+        ImageFormat::Png => image.write_with_encoder(PngEncoder::new(&mut bytes))?,
+        _ if lossy => return encode_lossy_webp(image),
+        _ if image.color().has_alpha() => DynamicImage::from(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut bytes))?,
+        _ => DynamicImage::from(image.to_rgb8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut bytes))?,
+    }
+
+    Ok(bytes)
+}
+
+fn encode_lossy_webp(image: &DynamicImage) -> Result<Vec<u8>, ImageError> {
+    let (width, height) = (image.width(), image.height());
+    let encoded = if image.color().has_alpha() {
+        webp::Encoder::from_rgba(image.to_rgba8().as_raw(), width, height)
+            .encode_simple(false, WEBP_QUALITY)
+    } else {
+        webp::Encoder::from_rgb(image.to_rgb8().as_raw(), width, height)
+            .encode_simple(false, WEBP_QUALITY)
+    };
+
+    encoded.map(|webp| webp.to_vec()).map_err(|e| {
+        ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Exact(ImageFormat::WebP),
+            format!("{:?}", e),
+        ))
+    })
+}
+
+/// Whether a WebP file holds lossy (`VP8 `) rather than lossless (`VP8L`) image data.
+fn is_lossy_webp(bytes: &[u8]) -> bool {
+    // RIFF header, then chunks of a fourcc, a little endian size and padded data
+    let mut chunks = bytes.get(12..).unwrap_or_default();
+    while let Some((fourcc, rest)) = chunks.split_first_chunk::<4>() {
+        match fourcc {
+            b"VP8 " => return true,
+            b"VP8L" => return false,
+            _ => {}
+        }
+        let Some((size, rest)) = rest.split_first_chunk::<4>() else {
+            break;
+        };
+        let size = u32::from_le_bytes(*size) as usize;
+        chunks = rest.get(size + size % 2..).unwrap_or_default();
+    }
+    false
+}
+
+fn blurhash(image: &DynamicImage) -> Option<String> {
+    let height = ((image.height() as u64 * BLURHASH_WIDTH as u64) / image.width() as u64).max(1);
+    let small = image
+        .resize_exact(BLURHASH_WIDTH, height as u32, FilterType::Triangle)
+        .to_rgba8();
+
+    blurhash::encode(
+        BLURHASH_COMPONENTS.0,
+        BLURHASH_COMPONENTS.1,
+        small.width(),
+        small.height(),
+        small.as_raw(),
+    )
+    .ok()
+}
@@ -0,0 +1,98 @@
+pub mod markdown;
+pub mod wordpress;
+
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// Reads the dates static site generators and exports write: RFC 3339, a bare
+/// `2019-03-01`, and `2019-03-01 10:00:00` with or without a `+0200` style offset.
+/// Dates without an offset are taken as UTC.
+pub fn parse_date(text: &str) -> Option<OffsetDateTime> {
+    let text = text.trim();
+    if text.len() < 10 || !text.is_char_boundary(10) {
+        return None;
+    }
+
+    let (date, time) = text.split_at(10);
+    let time = time.trim_start_matches(['T', 't', ' ']).replace(' ', "");
+
+    let rfc3339 = if time.is_empty() {
+        format!("{}T00:00:00Z", date)
+    } else if time.ends_with(['Z', 'z']) {
+        format!("{}T{}", date, time)
+    } else {
+        // the offset, if any, starts at the last sign after the seconds
+        let (clock, offset) = match time.rfind(['+', '-']) {
+            Some(idx) => time.split_at(idx),
+            None => (time.as_str(), ""),
+        };
+        let clock = if clock.len() == 5 {
+            format!("{}:00", clock)
+        } else {
+            clock.to_string()
+        };
+        let offset = match offset.len() {
+            0 => "Z".to_string(),
+            5 if !offset.contains(':') => format!("{}:{}", &offset[..3], &offset[3..]),
+            3 => format!("{}:00", offset),
+            _ => offset.to_string(),
+        };
+        format!("{}T{}{}", date, clock, offset)
+    };
+
+    OffsetDateTime::parse(&rfc3339, &Rfc3339).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn parses_rfc3339() {
+        assert_eq!(
+            parse_date("2019-03-01T10:00:00+02:00"),
+            Some(datetime!(2019-03-01 08:00:00 UTC))
+        );
+        assert_eq!(
+            parse_date("1979-05-27T07:32:00.5Z"),
+            Some(datetime!(1979-05-27 07:32:00.5 UTC))
+        );
+    }
+
+    #[test]
+    fn parses_bare_dates_as_utc_midnight() {
+        assert_eq!(
+            parse_date(" 2019-03-01 "),
+            Some(datetime!(2019-03-01 00:00:00 UTC))
+        );
+    }
+
+    #[test]
+    fn parses_space_separated_times_with_and_without_offset() {
+        assert_eq!(
+            parse_date("2019-03-01 10:00:00"),
+            Some(datetime!(2019-03-01 10:00:00 UTC))
+        );
+        assert_eq!(
+            parse_date("2019-03-01 10:00:00 +0200"),
+            Some(datetime!(2019-03-01 08:00:00 UTC))
+        );
+        assert_eq!(
+            parse_date("2019-03-01 10:00:00 -05"),
+            Some(datetime!(2019-03-01 15:00:00 UTC))
+        );
+        assert_eq!(
+            parse_date("2019-03-01T10:00"),
+            Some(datetime!(2019-03-01 10:00:00 UTC))
+        );
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_date() {
+        assert_eq!(parse_date(""), None);
+        assert_eq!(parse_date("yesterday"), None);
+        assert_eq!(parse_date("2019-13-01"), None);
+        assert_eq!(parse_date("0000-00-00 00:00:00"), None);
+    }
+}
@@ -0,0 +1,363 @@
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use super::parse_date;
+use crate::models::import::{ImportError, ImportPostArgs};
+use crate::models::locale::LocaleConfig;
+use crate::models::post::DraftPostArgs;
+
+/// File extensions read as markdown posts.
+pub const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown"];
+
+/// The front matter fields the importer reads, by their Hugo and Jekyll names.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct FrontMatter {
+    #[serde(deserialize_with = "text")]
+    title: Option<String>,
+    #[serde(deserialize_with = "text")]
+    date: Option<String>,
+    /// Hugo's last modified date.
+    #[serde(deserialize_with = "text")]
+    lastmod: Option<String>,
+    /// Jekyll's last modified date, from the `jekyll-last-modified-at` plugin.
+    #[serde(deserialize_with = "text")]
+    last_modified_at: Option<String>,
+    /// Hugo leaves drafts out of the site.
+    draft: bool,
+    /// Jekyll leaves `published: false` posts out of the site.
+    published: Option<bool>,
+    /// Hugo themes' cover image.
+    cover: Option<Cover>,
+    /// Jekyll themes' cover image.
+    image: Option<Cover>,
+    tags: Terms,
+    categories: Terms,
+    #[serde(deserialize_with = "text")]
+    summary: Option<String>,
+    #[serde(deserialize_with = "text")]
+    excerpt: Option<String>,
+    #[serde(deserialize_with = "text")]
+    description: Option<String>,
+    #[serde(deserialize_with = "text", alias = "language")]
+    lang: Option<String>,
+}
+
+/// A cover image path, or a table with the path and its alt text.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Cover {
+    Path(String),
+    Table {
+        #[serde(alias = "path", alias = "src")]
+        image: Option<String>,
+        alt: Option<String>,
+    },
+}
+
+/// Tags or categories as a list, or as Jekyll's space separated string.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Terms {
+    List(Vec<Value>),
+    Words(String),
+}
+
+impl Default for Terms {
+    fn default() -> Self {
+        Self::List(vec![])
+    }
+}
+
+impl Terms {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::List(terms) => terms.iter().filter_map(scalar).collect(),
+            Self::Words(words) => words.split_whitespace().map(String::from).collect(),
+        }
+    }
+}
+
+/// Reads a markdown file with YAML (`---`) or TOML (`+++`) front matter, as written for
+/// Hugo and Jekyll, into a post to import. `path` is the file's path relative to the
+/// imported directory, and `modified` is used when the post has no date of its own.
+///
+/// The language comes from the front matter, or a `.de.md` style suffix naming one of
+/// `locales`. Translations share a source with the suffix left out, so they are imported
+/// as drafts of the same post. Posts in Jekyll's `_drafts` directory are not published.
+pub fn parse_markdown_post(
+    path: &Path,
+    contents: &str,
+    modified: OffsetDateTime,
+    locales: &LocaleConfig,
+) -> Result<ImportPostArgs, ImportError> {
+    let (delimiter, front_matter, body) =
+        split_front_matter(contents).ok_or(ImportError::NoFrontMatter)?;
+
+    let front_matter: FrontMatter = match delimiter {
+        "+++" => toml::from_str::<toml::Table>(front_matter)
+            .map(|table| toml_to_json(toml::Value::Table(table)))
+            .map_err(|e| ImportError::FrontMatter(e.to_string())),
+        _ => serde_yaml_ng::from_str::<Value>(front_matter)
+            .map_err(|e| ImportError::FrontMatter(e.to_string())),
+    }
+    // empty front matter reads as null
+    .map(|value| match value {
+        Value::Null => Value::Object(Default::default()),
+        value => value,
+    })
+    .and_then(|value| {
+        serde_json::from_value(value).map_err(|e| ImportError::FrontMatter(e.to_string()))
+    })?;
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let (stem, suffix_lang) = match stem.rsplit_once('.') {
+        Some((name, suffix)) if locales.supported(suffix).is_some() => (name, Some(suffix)),
+        _ => (stem, None),
+    };
+    let source = path
+        .with_file_name(format!("{}.{}", stem, extension))
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let date = |text: Option<String>| -> Result<Option<OffsetDateTime>, ImportError> {
+        text.filter(|t| !t.trim().is_empty())
+            .map(|t| parse_date(&t).ok_or(ImportError::InvalidDate(t)))
+            .transpose()
+    };
+    // Jekyll posts are named like `2019-03-01-title.md`
+    let created_on = date(front_matter.date)?
+        .or_else(|| stem.get(..10).and_then(parse_date))
+        .unwrap_or(modified);
+    let updated_on = date(front_matter.lastmod.or(front_matter.last_modified_at))?
+        .filter(|updated| *updated > created_on)
+        .unwrap_or(created_on);
+
+    let title = front_matter
+        .title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .ok_or(ImportError::MissingTitle)?;
+
+    let (image, cover_alt) = match front_matter.cover.or(front_matter.image) {
+        Some(Cover::Path(image)) => (image, None),
+        Some(Cover::Table { image, alt }) => (image.unwrap_or_default(), alt),
+        None => (String::new(), None),
+    };
+
+    Ok(ImportPostArgs {
+        source,
+        draft: DraftPostArgs {
+            lang: front_matter.lang.or(suffix_lang.map(String::from)),
+            title,
+            markdown: body.trim().to_string(),
+            published: !front_matter.draft
+                && front_matter.published.unwrap_or(true)
+                && !path.components().any(|c| c.as_os_str() == "_drafts"),
+            image,
+            cover_alt,
+            tags: front_matter.tags.into_vec(),
+            excerpt: front_matter
+                .summary
+                .or(front_matter.excerpt)
+                .or(front_matter.description),
+            ..Default::default()
+        },
+        categories: front_matter.categories.into_vec(),
+        created_on,
+        updated_on,
+    })
+}
+
+/// Splits a file into its front matter delimiter, the front matter, and the body after it.
+fn split_front_matter(contents: &str) -> Option<(&'static str, &str, &str)> {
+    let contents = contents.trim_start_matches('\u{feff}');
+    let delimiter = ["---", "+++"]
+        .into_iter()
+        .find(|d| contents.starts_with(d))?;
+
+    let rest = &contents[delimiter.len()..];
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            return Some((delimiter, &rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+/// TOML front matter as JSON, with dates written out as text like YAML front matter has them.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(values) => values.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(k, v)| (k, toml_to_json(v)))
+            .collect(),
+    }
+}
+
+/// A scalar as text, so `title: 1984` is read as a title instead of failing.
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(scalar(&Value::deserialize(deserializer)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    const MODIFIED: OffsetDateTime = datetime!(2024-01-01 00:00:00 UTC);
+
+    fn locales() -> LocaleConfig {
+        LocaleConfig::new(vec!["en".into(), "de".into()], "en".into())
+    }
+
+    fn parse(path: &str, contents: &str) -> Result<ImportPostArgs, ImportError> {
+        parse_markdown_post(Path::new(path), contents, MODIFIED, &locales())
+    }
+
+    #[test]
+    fn splits_yaml_and_toml_front_matter() {
+        assert_eq!(
+            split_front_matter("---\ntitle: A\n---\nBody\n"),
+            Some(("---", "title: A\n", "Body\n"))
+        );
+        assert_eq!(
+            split_front_matter("\u{feff}+++\r\ntitle = \"A\"\r\n+++\r\nBody"),
+            Some(("+++", "title = \"A\"\r\n", "Body"))
+        );
+        assert_eq!(split_front_matter("---\n---\n"), Some(("---", "", "")));
+    }
+
+    #[test]
+    fn needs_closed_front_matter_at_the_start() {
+        assert_eq!(split_front_matter("Body\n---\ntitle: A\n---\n"), None);
+        assert_eq!(split_front_matter("---\ntitle: A\nBody"), None);
+        assert_eq!(split_front_matter("--- title: A\n---\n"), None);
+    }
+
+    #[test]
+    fn reads_hugo_yaml_front_matter() {
+        let post = parse(
+            "posts/hello.de.md",
+            "---\ntitle: Hallo\ndate: 2019-03-01\nlastmod: 2019-04-01 10:00:00 +0200\n\
+             draft: true\ncover:\n  image: /img/a.png\n  alt: An A\ntags: [rust, 2019]\n\
+             categories: [news]\nsummary: Short\n---\n\nBody here\n",
+        )
+        .unwrap();
+
+        assert_eq!(post.source, "posts/hello.md");
+        assert_eq!(post.draft.lang.as_deref(), Some("de"));
+        assert_eq!(post.draft.title, "Hallo");
+        assert_eq!(post.draft.markdown, "Body here");
+        assert!(!post.draft.published);
+        assert_eq!(post.draft.image, "/img/a.png");
+        assert_eq!(post.draft.cover_alt.as_deref(), Some("An A"));
+        assert_eq!(post.draft.tags, vec!["rust", "2019"]);
+        assert_eq!(post.draft.excerpt.as_deref(), Some("Short"));
+        assert_eq!(post.categories, vec!["news"]);
+        assert_eq!(post.created_on, datetime!(2019-03-01 00:00:00 UTC));
+        assert_eq!(post.updated_on, datetime!(2019-04-01 08:00:00 UTC));
+    }
+
+    #[test]
+    fn reads_toml_front_matter() {
+        let post = parse(
+            "toml.md",
+            "+++\ntitle = \"Toml\"\ndate = 2020-05-06T07:08:09Z\ntags = [\"x\"]\n\
+             [cover]\nimage = \"c.png\"\n+++\nBody",
+        )
+        .unwrap();
+
+        assert_eq!(post.source, "toml.md");
+        assert_eq!(post.draft.lang, None);
+        assert_eq!(post.draft.title, "Toml");
+        assert!(post.draft.published);
+        assert_eq!(post.draft.image, "c.png");
+        assert_eq!(post.draft.tags, vec!["x"]);
+        assert_eq!(post.created_on, datetime!(2020-05-06 07:08:09 UTC));
+        assert_eq!(post.updated_on, post.created_on);
+    }
+
+    #[test]
+    fn reads_jekyll_names_and_dates() {
+        let post = parse(
+            "_posts/2018-02-03-jekyll.markdown",
+            "---\r\ntitle: 1984\r\ntags: a b c\r\nimage: /x.jpg\r\n---\r\nJekyll body",
+        )
+        .unwrap();
+
+        assert_eq!(post.source, "_posts/2018-02-03-jekyll.markdown");
+        assert_eq!(post.draft.title, "1984");
+        assert_eq!(post.draft.tags, vec!["a", "b", "c"]);
+        assert_eq!(post.draft.image, "/x.jpg");
+        assert!(post.draft.published);
+        assert_eq!(post.created_on, datetime!(2018-02-03 00:00:00 UTC));
+
+        let unpublished = parse("a.md", "---\ntitle: A\npublished: false\n---\n").unwrap();
+        assert!(!unpublished.draft.published);
+
+        let draft = parse("_drafts/a.md", "---\ntitle: A\n---\n").unwrap();
+        assert!(!draft.draft.published);
+        assert_eq!(draft.created_on, MODIFIED);
+    }
+
+    #[test]
+    fn keeps_unsupported_language_suffixes_in_the_source() {
+        let post = parse("notes.fr.md", "---\ntitle: A\n---\n").unwrap();
+
+        assert_eq!(post.source, "notes.fr.md");
+        assert_eq!(post.draft.lang, None);
+    }
+
+    #[test]
+    fn reports_what_can_not_be_imported() {
+        let error = |path, contents| parse(path, contents).unwrap_err().to_string();
+
+        assert_eq!(error("a.md", "just text"), "No front matter found.");
+        assert_eq!(
+            error("a.md", "---\n---\nno title"),
+            "Front matter has no title."
+        );
+        assert!(error("a.md", "---\ntitle: [unclosed\n---\n")
+            .starts_with("Unable to read front matter"));
+        assert_eq!(
+            error("a.md", "---\ntitle: A\ndate: yesterday\n---\n"),
+            "Unable to read date: yesterday"
+        );
+    }
+}
@@ -0,0 +1,629 @@
+use std::collections::HashMap;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use super::parse_date;
+use crate::constants::MAX_EXCERPT_LENGTH;
+use crate::models::import::{ImportError, ImportPostArgs, ImportReportEntry};
+use crate::models::post::DraftPostArgs;
+
+/// Shortcodes WordPress expands when it renders a post. They are kept in the markdown as
+/// text, with a warning.
+const SHORTCODES: &[&str] = &["gallery", "audio", "video", "playlist", "embed"];
+
+/// Tags that start a block of their own, which WordPress does not wrap in a paragraph.
+const BLOCK_TAGS: &[&str] = &[
+    "<p",
+    "<h1",
+    "<h2",
+    "<h3",
+    "<h4",
+    "<h5",
+    "<h6",
+    "<ul",
+    "<ol",
+    "<li",
+    "<blockquote",
+    "<pre",
+    "<table",
+    "<div",
+    "<figure",
+    "<hr",
+    "<dl",
+    "<iframe",
+    "<!--",
+];
+
+/// An author listed in a WordPress export.
+#[derive(Debug, Clone, Default)]
+pub struct WordPressAuthor {
+    pub login: String,
+    pub email: String,
+    pub display_name: String,
+}
+
+/// A post read from a WordPress export.
+#[derive(Debug, Clone)]
+pub struct WordPressPost {
+    /// Login of the [`WordPressAuthor`] who wrote the post.
+    pub author_login: String,
+    pub post: ImportPostArgs,
+    /// Parts of the post that did not carry over.
+    pub warnings: Vec<String>,
+}
+
+/// The authors and posts of a WordPress export, and the items that are not imported.
+#[derive(Debug, Clone, Default)]
+pub struct WordPressExport {
+    pub authors: Vec<WordPressAuthor>,
+    pub posts: Vec<WordPressPost>,
+    pub skipped: Vec<ImportReportEntry>,
+}
+
+/// An `<item>` of the export, as written.
+#[derive(Debug, Default)]
+struct Item {
+    title: String,
+    link: String,
+    guid: String,
+    creator: String,
+    content: String,
+    excerpt: String,
+    post_id: String,
+    post_date: String,
+    post_date_gmt: String,
+    post_modified_gmt: String,
+    status: String,
+    post_type: String,
+    password: String,
+    attachment_url: String,
+    categories: Vec<String>,
+    tags: Vec<String>,
+    thumbnail_id: Option<String>,
+    comments: usize,
+}
+
+impl Item {
+    /// Identifies the post across exports of the same site.
+    fn source(&self) -> String {
+        let id = [&self.guid, &self.link, &self.post_id]
+            .into_iter()
+            .find(|id| !id.is_empty())
+            .cloned()
+            .unwrap_or_default();
+        format!("wordpress:{}", id)
+    }
+
+    fn skip(&self, reason: impl Into<String>) -> ImportReportEntry {
+        ImportReportEntry {
+            source: self.source(),
+            title: self.title.clone(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Reads a WordPress WXR export. Posts become [`ImportPostArgs`] with their HTML converted
+/// to markdown, their dates and publish state kept, their tags as tags and their
+/// categories as categories. Featured images are linked where they were uploaded.
+///
+/// Pages and other item types, trashed posts and auto drafts are skipped, as are comments.
+pub fn read_wordpress_export(xml: &str) -> Result<WordPressExport, ImportError> {
+    let (authors, items) = parse_items(xml)?;
+
+    let attachments: HashMap<&str, &str> = items
+        .iter()
+        .filter(|i| i.post_type == "attachment" && !i.attachment_url.is_empty())
+        .map(|i| (i.post_id.as_str(), i.attachment_url.as_str()))
+        .collect();
+
+    let mut export = WordPressExport {
+        authors,
+        ..Default::default()
+    };
+
+    // attachments only stand in for featured images
+    for item in items.iter().filter(|i| i.post_type != "attachment") {
+        if item.post_type != "post" {
+            export
+                .skipped
+                .push(item.skip(format!("Not a post: {}", item.post_type)));
+            continue;
+        }
+
+        let (published, publish_on) = match item.status.as_str() {
+            "publish" => (true, false),
+            "future" => (false, true),
+            "draft" | "pending" | "private" => (false, false),
+            status => {
+                export
+                    .skipped
+                    .push(item.skip(format!("Not imported in status: {}", status)));
+                continue;
+            }
+        };
+
+        match post_from_item(item, &attachments, published, publish_on) {
+            Ok(post) => export.posts.push(post),
+            Err(e) => export.skipped.push(item.skip(e.to_string())),
+        }
+
+        if item.comments > 0 {
+            export
+                .skipped
+                .push(item.skip(format!("{} comments not imported", item.comments)));
+        }
+    }
+
+    Ok(export)
+}
+
+fn post_from_item(
+    item: &Item,
+    attachments: &HashMap<&str, &str>,
+    published: bool,
+    publish_on: bool,
+) -> Result<WordPressPost, ImportError> {
+    let title = item.title.trim().to_string();
+    if title.is_empty() {
+        return Err(ImportError::UntitledItem);
+    }
+
+    // drafts have no GMT date, only the site's local one
+    let created_on = parse_date(&item.post_date_gmt)
+        .or_else(|| parse_date(&item.post_date))
+        .or_else(|| parse_date(&item.post_modified_gmt))
+        .ok_or_else(|| ImportError::InvalidDate(item.post_date.clone()))?;
+    let updated_on = parse_date(&item.post_modified_gmt)
+        .filter(|updated| *updated > created_on)
+        .unwrap_or(created_on);
+
+    let (markdown, mut warnings) = html_to_markdown(&item.content);
+
+    let excerpt = Some(html_to_markdown(&item.excerpt).0).filter(|e| !e.is_empty());
+    let excerpt = match excerpt {
+        Some(e) if e.chars().count() > MAX_EXCERPT_LENGTH => {
+            warnings.push(format!(
+                "Excerpt longer than {} characters left out",
+                MAX_EXCERPT_LENGTH
+            ));
+            None
+        }
+        excerpt => excerpt,
+    };
+
+    // the blog has no password protection, so those posts are kept from going live
+    let password_protected = !item.password.is_empty();
+    if password_protected {
+        warnings.push("Password protected, imported unpublished".into());
+    }
+
+    let image = item
+        .thumbnail_id
+        .as_deref()
+        .and_then(|id| attachments.get(id))
+        .map(|url| url.to_string())
+        .unwrap_or_default();
+
+    Ok(WordPressPost {
+        author_login: item.creator.clone(),
+        post: ImportPostArgs {
+            source: item.source(),
+            draft: DraftPostArgs {
+                title,
+                markdown,
+                published: published && !password_protected,
+                image,
+                tags: item.tags.clone(),
+                excerpt,
+                publish_at: (publish_on && !password_protected).then_some(created_on),
+                ..Default::default()
+            },
+            categories: item.categories.clone(),
+            created_on,
+            updated_on,
+        },
+        warnings,
+    })
+}
+
+/// Reads the authors and items out of the export, element by element. Prefixed names like
+/// `content:encoded` and `excerpt:encoded` differ only by prefix, so they are matched whole.
+fn parse_items(xml: &str) -> Result<(Vec<WordPressAuthor>, Vec<Item>), ImportError> {
+    let invalid = |e: quick_xml::Error| ImportError::Unreadable(e.to_string());
+
+    let mut reader = Reader::from_str(xml);
+    let mut path: Vec<String> = vec![];
+    let mut text = String::new();
+
+    let mut authors = vec![];
+    let mut items = vec![];
+    let mut author: Option<WordPressAuthor> = None;
+    let mut item: Option<Item> = None;
+    let mut category_domain = String::new();
+    let mut meta_key = String::new();
+
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                match name.as_str() {
+                    "wp:author" if item.is_none() => author = Some(WordPressAuthor::default()),
+                    "item" => item = Some(Item::default()),
+                    "category" => {
+                        category_domain = e
+                            .try_get_attribute("domain")
+                            .ok()
+                            .flatten()
+                            .and_then(|a| a.unescape_value().ok())
+                            .map(|v| v.into_owned())
+                            .unwrap_or_default();
+                    }
+                    _ => {}
+                }
+                path.push(name);
+                text.clear();
+            }
+            Event::Text(e) => text.push_str(&e.unescape().map_err(invalid)?),
+            Event::CData(e) => text.push_str(&String::from_utf8_lossy(&e.into_inner())),
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                let parent = path.last().map(String::as_str).unwrap_or_default();
+                let value = std::mem::take(&mut text);
+
+                if let Some(author) = author.as_mut() {
+                    match name.as_str() {
+                        "wp:author_login" => author.login = value.trim().to_string(),
+                        "wp:author_email" => author.email = value.trim().to_string(),
+                        "wp:author_display_name" => author.display_name = value.trim().to_string(),
+                        _ => {}
+                    }
+                }
+
+                if let Some(item) = item.as_mut() {
+                    match (parent, name.as_str()) {
+                        ("item", "title") => item.title = value,
+                        ("item", "link") => item.link = value.trim().to_string(),
+                        ("item", "guid") => item.guid = value.trim().to_string(),
+                        ("item", "dc:creator") => item.creator = value.trim().to_string(),
+                        ("item", "content:encoded") => item.content = value,
+                        ("item", "excerpt:encoded") => item.excerpt = value,
+                        ("item", "wp:post_id") => item.post_id = value.trim().to_string(),
+                        ("item", "wp:post_date") => item.post_date = value,
+                        ("item", "wp:post_date_gmt") => item.post_date_gmt = value,
+                        ("item", "wp:post_modified_gmt") => item.post_modified_gmt = value,
+                        ("item", "wp:status") => item.status = value.trim().to_string(),
+                        ("item", "wp:post_type") => item.post_type = value.trim().to_string(),
+                        ("item", "wp:post_password") => item.password = value,
+                        ("item", "wp:attachment_url") => {
+                            item.attachment_url = value.trim().to_string()
+                        }
+                        ("item", "category") => {
+                            let term = value.trim().to_string();
+                            match category_domain.as_str() {
+                                "category" => item.categories.push(term),
+                                "post_tag" => item.tags.push(term),
+                                _ => {}
+                            }
+                        }
+                        ("item", "wp:comment") => item.comments += 1,
+                        ("wp:postmeta", "wp:meta_key") => meta_key = value,
+                        ("wp:postmeta", "wp:meta_value") if meta_key == "_thumbnail_id" => {
+                            item.thumbnail_id = Some(value.trim().to_string())
+                        }
+                        _ => {}
+                    }
+                }
+
+                match name.as_str() {
+                    "wp:author" => authors.extend(author.take()),
+                    "item" => items.extend(item.take()),
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok((authors, items))
+}
+
+/// Converts the HTML of a WordPress post to markdown, with a warning for each kind of
+/// shortcode left in it.
+///
+/// Posts from the classic editor separate paragraphs with blank lines instead of `<p>`
+/// tags, so those are added first the way WordPress does when it renders them.
+pub fn html_to_markdown(html: &str) -> (String, Vec<String>) {
+    let html = strip_caption_shortcodes(&html.replace("\r\n", "\n"));
+
+    let warnings = SHORTCODES
+        .iter()
+        .filter(|code| {
+            html.contains(&format!("[{}]", code)) || html.contains(&format!("[{} ", code))
+        })
+        .map(|code| format!("[{}] shortcode kept as text", code))
+        .collect();
+
+    let html = if html.contains("<p>") || html.contains("<p ") {
+        html
+    } else {
+        add_paragraphs(&html)
+    };
+
+    (tidy_markdown(&html2md::parse_html(&html)), warnings)
+}
+
+/// Removes `[caption]` shortcodes, keeping the image and caption text they wrap.
+fn strip_caption_shortcodes(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find("[caption") {
+        out.push_str(&rest[..start]);
+        rest = match rest[start..].find(']') {
+            Some(end) => &rest[start + end + 1..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+
+    out.replace("[/caption]", "\n")
+}
+
+/// Wraps each blank line separated block that is not already a block element in `<p>`,
+/// keeping single line breaks inside it.
+fn add_paragraphs(html: &str) -> String {
+    html.split("\n\n")
+        .map(str::trim)
+        .filter(|block| !block.is_empty())
+        .map(|block| {
+            if BLOCK_TAGS.iter().any(|tag| block.starts_with(tag)) {
+                block.to_string()
+            } else {
+                format!("<p>{}</p>", block.replace('\n', "<br>\n"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Drops the empty `>` lines html2md leaves around quoted paragraphs, runs of blank lines
+/// and trailing spaces, except the two that end a line in a hard break.
+fn tidy_markdown(markdown: &str) -> String {
+    let mut lines: Vec<&str> = vec![];
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_end();
+        let line = if line.ends_with("  ") && !trimmed.is_empty() {
+            &line[..trimmed.len() + 2]
+        } else {
+            trimmed
+        };
+
+        let previous = lines.last().copied();
+        let empty_quote = line == ">";
+
+        if empty_quote && previous.is_none_or(|p| p == ">" || !p.starts_with('>')) {
+            continue;
+        }
+        if !line.starts_with('>') && previous == Some(">") {
+            lines.pop();
+        }
+        if line.is_empty() && previous.is_none_or(str::is_empty) {
+            continue;
+        }
+        lines.push(line);
+    }
+
+    while lines.last().is_some_and(|l| l.is_empty() || *l == ">") {
+        lines.pop();
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A WXR export around `items`, with one author.
+    fn export(items: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0"
+    xmlns:excerpt="http://wordpress.org/export/1.2/excerpt/"
+    xmlns:content="http://purl.org/rss/1.0/modules/content/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+    <title>Blog</title>
+    <wp:author>
+        <wp:author_id>1</wp:author_id>
+        <wp:author_login><![CDATA[ada]]></wp:author_login>
+        <wp:author_email><![CDATA[ada@example.com]]></wp:author_email>
+        <wp:author_display_name><![CDATA[Ada Lovelace]]></wp:author_display_name>
+    </wp:author>
+    {items}
+</channel>
+</rss>"#
+        )
+    }
+
+    /// A post item by `ada` in `status`, with `extra` elements added to it.
+    fn post(id: u32, status: &str, content: &str, extra: &str) -> String {
+        format!(
+            r#"<item>
+        <title>Post {id}</title>
+        <link>https://example.com/?p={id}</link>
+        <guid isPermaLink="false">https://example.com/?p={id}</guid>
+        <dc:creator><![CDATA[ada]]></dc:creator>
+        <content:encoded><![CDATA[{content}]]></content:encoded>
+        <excerpt:encoded><![CDATA[]]></excerpt:encoded>
+        <wp:post_id>{id}</wp:post_id>
+        <wp:post_date><![CDATA[2019-03-01 12:00:00]]></wp:post_date>
+        <wp:post_date_gmt><![CDATA[2019-03-01 10:00:00]]></wp:post_date_gmt>
+        <wp:post_modified_gmt><![CDATA[2019-04-01 10:00:00]]></wp:post_modified_gmt>
+        <wp:status><![CDATA[{status}]]></wp:status>
+        <wp:post_type><![CDATA[post]]></wp:post_type>
+        <wp:post_password><![CDATA[]]></wp:post_password>
+        {extra}
+    </item>"#
+        )
+    }
+
+    fn read(items: &str) -> WordPressExport {
+        read_wordpress_export(&export(items)).expect("export reads")
+    }
+
+    #[test]
+    fn reads_authors_and_cdata_content() {
+        let export = read(&post(
+            1,
+            "publish",
+            "<p>Hello <strong>world</strong> &amp; all</p>",
+            r#"<category domain="category" nicename="news"><![CDATA[News]]></category>
+        <category domain="post_tag" nicename="rust"><![CDATA[rust]]></category>"#,
+        ));
+
+        assert_eq!(export.authors.len(), 1);
+        assert_eq!(export.authors[0].login, "ada");
+        assert_eq!(export.authors[0].email, "ada@example.com");
+        assert_eq!(export.authors[0].display_name, "Ada Lovelace");
+
+        let post = &export.posts[0];
+        assert_eq!(post.author_login, "ada");
+        assert_eq!(post.post.source, "wordpress:https://example.com/?p=1");
+        assert_eq!(post.post.draft.title, "Post 1");
+        assert_eq!(post.post.draft.markdown, "Hello **world** & all");
+        assert_eq!(post.post.draft.tags, vec!["rust"]);
+        assert_eq!(post.post.categories, vec!["News"]);
+        assert_eq!(
+            post.post.created_on,
+            parse_date("2019-03-01 10:00:00").unwrap()
+        );
+        assert_eq!(
+            post.post.updated_on,
+            parse_date("2019-04-01 10:00:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn maps_status_to_publish_state() {
+        let export = read(
+            &[
+                post(1, "publish", "Live", ""),
+                post(2, "future", "Later", ""),
+                post(3, "draft", "Unfinished", ""),
+                post(4, "trash", "Gone", ""),
+                post(
+                    5,
+                    "publish",
+                    "Secret",
+                    "<wp:post_password><![CDATA[hunter2]]></wp:post_password>",
+                ),
+            ]
+            .concat(),
+        );
+
+        let drafts: Vec<_> = export.posts.iter().map(|p| &p.post.draft).collect();
+        assert_eq!(drafts.len(), 4);
+
+        assert!(drafts[0].published);
+        assert_eq!(drafts[0].publish_at, None);
+
+        assert!(!drafts[1].published);
+        assert_eq!(drafts[1].publish_at, Some(export.posts[1].post.created_on));
+
+        assert!(!drafts[2].published);
+        assert_eq!(drafts[2].publish_at, None);
+
+        assert!(!drafts[3].published);
+        assert_eq!(
+            export.posts[3].warnings,
+            vec!["Password protected, imported unpublished"]
+        );
+
+        assert_eq!(export.skipped.len(), 1);
+        assert_eq!(export.skipped[0].reason, "Not imported in status: trash");
+    }
+
+    #[test]
+    fn links_featured_image_from_postmeta() {
+        let export = read(
+            &[
+                post(
+                    1,
+                    "publish",
+                    "With a cover",
+                    r#"<wp:postmeta>
+            <wp:meta_key><![CDATA[_edit_last]]></wp:meta_key>
+            <wp:meta_value><![CDATA[1]]></wp:meta_value>
+        </wp:postmeta>
+        <wp:postmeta>
+            <wp:meta_key><![CDATA[_thumbnail_id]]></wp:meta_key>
+            <wp:meta_value><![CDATA[7]]></wp:meta_value>
+        </wp:postmeta>"#,
+                ),
+                r#"<item>
+        <title>cover</title>
+        <wp:post_id>7</wp:post_id>
+        <wp:post_type><![CDATA[attachment]]></wp:post_type>
+        <wp:attachment_url><![CDATA[https://example.com/uploads/cover.jpg]]></wp:attachment_url>
+    </item>"#
+                    .to_string(),
+            ]
+            .concat(),
+        );
+
+        assert_eq!(export.posts.len(), 1);
+        assert_eq!(
+            export.posts[0].post.draft.image,
+            "https://example.com/uploads/cover.jpg"
+        );
+        assert!(export.skipped.is_empty());
+    }
+
+    #[test]
+    fn adds_paragraphs_to_classic_editor_posts() {
+        let (markdown, warnings) =
+            html_to_markdown("First line\nsecond line\r\n\r\nNext paragraph\n\n<h2>Heading</h2>");
+
+        assert_eq!(
+            markdown,
+            "First line  \nsecond line\n\nNext paragraph\n\nHeading\n----------"
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn keeps_image_and_text_of_captions() {
+        let (markdown, _) = html_to_markdown(
+            r#"[caption id="attachment_7" align="aligncenter" width="300"]<img src="https://example.com/cat.jpg" alt="A cat" /> Sleeping cat[/caption]
+
+After the picture"#,
+        );
+
+        assert!(!markdown.contains("caption"));
+        assert!(markdown.contains("![A cat](https://example.com/cat.jpg)"));
+        assert!(markdown.contains("Sleeping cat"));
+        assert!(markdown.ends_with("After the picture"));
+    }
+
+    #[test]
+    fn warns_about_shortcodes_kept_as_text() {
+        let (markdown, warnings) = html_to_markdown("<p>Look</p>\n[gallery ids=\"1,2\"]");
+
+        assert!(markdown.contains("[gallery ids=\"1,2\"]"));
+        assert_eq!(warnings, vec!["[gallery] shortcode kept as text"]);
+    }
+
+    #[test]
+    fn skips_untitled_posts() {
+        let export = read(&post(1, "publish", "Body", "").replace("Post 1", " "));
+
+        assert!(export.posts.is_empty());
+        assert_eq!(export.skipped[0].reason, "WordPress post has no title.");
+    }
+}
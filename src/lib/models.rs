@@ -1,5 +1,17 @@
+pub mod analytics;
+pub mod comment;
 pub mod custom_claims;
+pub mod diff;
+pub mod feed;
+pub mod head;
+pub mod import;
+pub mod locale;
+pub mod media;
 pub mod meta;
+pub mod page;
 pub mod person;
 pub mod post;
+pub mod reaction;
+pub mod search;
+pub mod sitemap;
 pub mod token;
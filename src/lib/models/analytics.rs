@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::post::PostSummary;
+
+/// Query string arguments for the view analytics endpoints.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ViewRangeArgs {
+    /// First day to include (ISO 8601). Defaults to 30 days before `to`.
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub from: Option<OffsetDateTime>,
+    /// Last day to include (ISO 8601). Defaults to today.
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub to: Option<OffsetDateTime>,
+}
+
+/// Unique visitors on a single UTC day.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DailyViews {
+    #[serde(with = "time::serde::iso8601")]
+    pub day: OffsetDateTime,
+    pub views: u64,
+}
+
+/// Daily unique views over a date range, for one post or the whole site.
+#[derive(Debug, Serialize, Clone)]
+pub struct ViewSeries {
+    /// The post the views are for, `None` for site-wide views.
+    pub post: Option<String>,
+    #[serde(with = "time::serde::iso8601")]
+    pub from: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    pub to: OffsetDateTime,
+    pub total: u64,
+    /// Only days with at least one view are included, oldest first.
+    pub days: Vec<DailyViews>,
+}
+
+/// Period the most-read posts are counted over.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PopularWindow {
+    Week,
+    Month,
+    Year,
+    #[default]
+    All,
+}
+
+impl PopularWindow {
+    pub const ALL: [PopularWindow; 4] = [Self::Week, Self::Month, Self::Year, Self::All];
+
+    /// Number of days counted, `None` for all time.
+    pub fn days(&self) -> Option<i64> {
+        match self {
+            Self::Week => Some(7),
+            Self::Month => Some(30),
+            Self::Year => Some(365),
+            Self::All => None,
+        }
+    }
+}
+
+/// Query string arguments for the popular and trending endpoints.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RankingArgs {
+    /// Only used by the popular endpoint.
+    #[serde(default)]
+    pub window: PopularWindow,
+    pub limit: Option<u32>,
+}
+
+/// Views of a post, summed per post by the database.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PostViewTotal {
+    pub post: String,
+    pub views: u64,
+}
+
+/// Views of a post on one day.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PostDailyViews {
+    pub post: String,
+    #[serde(with = "time::serde::iso8601")]
+    pub day: OffsetDateTime,
+    pub views: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RankedPost {
+    pub post: PostSummary,
+    /// Unique views counted in the ranking's period.
+    pub views: u64,
+    /// What the ranking is ordered by: the view count for popular posts and the
+    /// time-decayed view count for trending posts.
+    pub score: f64,
+}
+
+/// A cached ranking of posts.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct RankedPosts {
+    pub items: Vec<RankedPost>,
+    /// When the ranking was last recomputed, `None` until the first run.
+    #[serde(with = "time::serde::iso8601::option")]
+    pub computed_at: Option<OffsetDateTime>,
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::meta::Meta;
+use super::person::AuthorSummary;
+
+/// Where a comment is in moderation. Only approved comments are shown to readers.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentStatus {
+    #[default]
+    Pending,
+    Approved,
+    Spam,
+    Removed,
+}
+
+impl CommentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Spam => "spam",
+            Self::Removed => "removed",
+        }
+    }
+}
+
+/// A comment as stored on a `commented` relation from a person to a post.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Comment {
+    pub id: String,
+    pub post: String,
+    pub author: String,
+    #[serde(default)]
+    pub author_summary: Option<AuthorSummary>,
+    /// The comment this one replies to, `None` for top level comments.
+    #[serde(default)]
+    pub parent: Option<String>,
+    pub body: String,
+    pub status: CommentStatus,
+    #[serde(with = "time::serde::iso8601")]
+    pub at: OffsetDateTime,
+    /// When the author last edited the comment.
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub edited_on: Option<OffsetDateTime>,
+    pub meta: Meta<()>,
+}
+
+/// A comment in a post's thread, with its replies nested beneath it.
+#[derive(Debug, Serialize, Clone)]
+pub struct CommentNode {
+    pub id: String,
+    pub author_summary: Option<AuthorSummary>,
+    /// `None` when the comment was deleted or moderated but still has visible replies.
+    pub body: Option<String>,
+    #[serde(with = "time::serde::iso8601")]
+    pub at: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601::option")]
+    pub edited_on: Option<OffsetDateTime>,
+    pub replies: Vec<CommentNode>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CreateCommentArgs {
+    pub body: String,
+    /// Reply to this comment instead of commenting on the post directly.
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EditCommentArgs {
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModerateCommentArgs {
+    pub status: CommentStatus,
+}
+
+/// Query string arguments for the moderation queue.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ModerationQueueArgs {
+    #[serde(default)]
+    pub status: CommentStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommentSettings {
+    /// Whether readers can add new comments to the post.
+    pub open: bool,
+}
+
+/// Whether a post can be commented on.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PostCommentState {
+    pub found: bool,
+    pub published: bool,
+    pub open: bool,
+}
+
+/// The comment a reply is written under.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReplyParent {
+    pub status: CommentStatus,
+    pub deleted: bool,
+    /// How deeply the comment is nested, with top level comments at depth 1.
+    pub depth: usize,
+}
+
+/// Why a comment could not be written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommentError {
+    NotFound,
+    /// The post is unpublished or not accepting comments.
+    Closed,
+    /// Only the comment's author can edit or delete it.
+    NotAuthor,
+    /// The comment is too old to be edited or deleted by its author.
+    WindowExpired,
+    Invalid(String),
+}
+
+impl std::fmt::Display for CommentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => f.write_str("Unable to find comment or post."),
+            Self::Closed => f.write_str("Comments are closed on this post."),
+            Self::NotAuthor => f.write_str("You can only change your own comments."),
+            Self::WindowExpired => f.write_str("This comment can no longer be changed."),
+            Self::Invalid(message) => f.write_str(message),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use super::post::PostVersion;
+
+/// Query string for the draft diff endpoint.
+///
+/// Each side takes a draft id, `published` for the currently published version, or
+/// `latest` for the newest draft. Defaults compare the published version to the latest.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DiffArgs {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Translation `published` and `latest` refer to. Defaults to the fallback locale.
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A run of text that was kept, added or removed.
+#[derive(Debug, Serialize, Clone)]
+pub struct DiffChange {
+    pub op: DiffOp,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FieldDiff {
+    pub changed: bool,
+    pub lines: Vec<DiffChange>,
+    pub words: Vec<DiffChange>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DraftDiff {
+    pub from: PostVersion,
+    pub to: PostVersion,
+    pub title: FieldDiff,
+    pub markdown: FieldDiff,
+    pub image: FieldDiff,
+}
@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use time::OffsetDateTime;
+use ulid::Ulid;
+
+/// Where the blog is served from and how it describes itself to feed readers and crawlers.
+#[derive(Debug, Clone)]
+pub struct SiteConfig {
+    /// Public base URL of the blog, without a trailing slash.
+    pub url: String,
+    pub title: String,
+    pub description: String,
+}
+
+impl SiteConfig {
+    /// Public URL of a post, from its `"post:ulid"` id.
+    pub fn post_url(&self, post_id: &str) -> String {
+        format!("{}/posts/{}", self.url, record_key(post_id))
+    }
+
+    /// Public URL of an author's profile, from their `"person:ulid"` id.
+    pub fn author_url(&self, person_id: &str) -> String {
+        format!("{}/authors/{}", self.url, record_key(person_id))
+    }
+
+    /// The `"post:ulid"` id of the post at a public post URL, as made by
+    /// [`SiteConfig::post_url`].
+    pub fn post_id_from_url(&self, url: &str) -> Option<String> {
+        let key = url
+            .strip_prefix(&self.url)?
+            .strip_prefix("/posts/")?
+            .split(['/', '?', '#'])
+            .next()?;
+
+        Ulid::from_string(key)
+            .ok()
+            .map(|ulid| format!("post:{}", ulid))
+    }
+
+    /// Makes a site relative URL, like an uploaded image path, absolute.
+    pub fn absolute_url(&self, url: &str) -> String {
+        if url.starts_with('/') {
+            format!("{}{}", self.url, url)
+        } else {
+            url.to_string()
+        }
+    }
+}
+
+/// The key part of a `"table:key"` id.
+fn record_key(id: &str) -> &str {
+    id.split_once(':').map(|(_, key)| key).unwrap_or(id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+impl FeedFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Rss => "application/rss+xml; charset=utf-8",
+            Self::Atom => "application/atom+xml; charset=utf-8",
+            Self::Json => "application/feed+json; charset=utf-8",
+        }
+    }
+
+    /// Path the feed is served at, relative to the site URL.
+    pub fn path(&self) -> &'static str {
+        match self {
+            Self::Rss => "/feed.xml",
+            Self::Atom => "/atom.xml",
+            Self::Json => "/feed.json",
+        }
+    }
+}
+
+/// Query string arguments shared by the feed endpoints.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct FeedArgs {
+    /// Only include posts with this tag.
+    pub tag: Option<String>,
+    /// Include the full rendered post in each entry instead of just the excerpt.
+    #[serde(default)]
+    pub full: bool,
+    /// Language to read posts in. Posts not translated into it appear in the fallback language.
+    pub lang: Option<String>,
+}
+
+/// A feed ready to be written out in any [`FeedFormat`].
+#[derive(Debug, Clone)]
+pub struct Feed {
+    pub title: String,
+    pub description: String,
+    /// The page the feed is for.
+    pub home_url: String,
+    /// Where the feed itself is served.
+    pub feed_url: String,
+    /// When the newest entry was last changed, or now when there are no entries.
+    pub updated: OffsetDateTime,
+    pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    /// The post id, which stays the same across every version of the post.
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    pub summary: String,
+    /// Rendered html of the post, only when full content was requested.
+    pub content_html: Option<String>,
+    pub author_name: String,
+    pub author_url: String,
+    pub image: Option<String>,
+    pub tags: Vec<String>,
+    /// When the post was first created.
+    pub published: OffsetDateTime,
+    /// When the published version was drafted.
+    pub updated: OffsetDateTime,
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Everything a page needs in its `<head>` for link previews and search engines.
+#[derive(Debug, Serialize, Clone)]
+pub struct HeadMetadata {
+    pub title: String,
+    pub description: String,
+    pub canonical_url: String,
+    /// Absolute URL of the post's image, `None` when it has none.
+    pub image: Option<String>,
+    pub published_time: String,
+    pub modified_time: String,
+    /// `og:` properties, in the order they should be written.
+    pub open_graph: Vec<MetaTag>,
+    /// `twitter:` card names, in the order they should be written.
+    pub twitter: Vec<MetaTag>,
+    /// A schema.org `BlogPosting`.
+    pub json_ld: Value,
+    /// All of the above as ready to inject `<head>` markup.
+    pub html: String,
+}
+
+/// A single `<meta>` tag. Open Graph tags use `property`, Twitter cards use `name`.
+#[derive(Debug, Serialize, Clone)]
+pub struct MetaTag {
+    pub key: String,
+    pub content: String,
+}
+
+/// Query string arguments of the oEmbed endpoint.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OEmbedArgs {
+    /// Public URL of the post to embed.
+    pub url: String,
+    /// Only `json` is supported.
+    pub format: Option<String>,
+    pub maxwidth: Option<u32>,
+    pub maxheight: Option<u32>,
+}
+
+/// An oEmbed 1.0 `link` response.
+#[derive(Debug, Serialize, Clone)]
+pub struct OEmbed {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub version: String,
+    pub title: String,
+    pub author_name: String,
+    pub author_url: String,
+    pub provider_name: String,
+    pub provider_url: String,
+    /// Seconds consumers may cache the response for.
+    pub cache_age: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+}
+
+/// Why an oEmbed response could not be made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OEmbedError {
+    /// The URL is not a published post on this site.
+    NotFound,
+    /// A format other than `json` was asked for.
+    UnsupportedFormat,
+}
+
+impl std::fmt::Display for OEmbedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => f.write_str("No published post at this URL."),
+            Self::UnsupportedFormat => f.write_str("Only the json format is supported."),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use super::post::DraftPostArgs;
+
+/// A post read from outside the blog, saved through
+/// [`PostsService::import_post`](crate::services::s_posts::PostsService::import_post).
+#[derive(Debug, Clone)]
+pub struct ImportPostArgs {
+    /// Where the post came from, like its path in the imported directory. Importing the
+    /// same source again updates the post made the first time instead of adding another.
+    pub source: String,
+    pub draft: DraftPostArgs,
+    /// Categories the post was filed under, kept on the post as they were written.
+    pub categories: Vec<String>,
+    /// When the post was first written. Kept as the post's `meta.created_on`.
+    pub created_on: OffsetDateTime,
+    /// When the post was last changed. Kept as the imported draft's `at`.
+    pub updated_on: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    /// A new post, or a new translation of the source's post, was made for the source.
+    Created,
+    /// The source changed since it was last imported, so a new draft was saved.
+    Updated,
+    /// The source's post already has a draft with the same content.
+    Unchanged,
+}
+
+/// What importing a source did, or would do on a dry run.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResult {
+    pub source: String,
+    pub action: ImportAction,
+    /// `None` when a dry run would create the post.
+    pub post_id: Option<String>,
+    /// `None` when a dry run would save a draft.
+    pub draft_id: Option<String>,
+}
+
+/// Something left out of an import, or imported with a caveat.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReportEntry {
+    pub source: String,
+    pub title: String,
+    pub reason: String,
+}
+
+/// Everything an import did, written out once it finishes.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub authors: Vec<ImportedAuthor>,
+    pub posts: Vec<ImportResult>,
+    pub skipped: Vec<ImportReportEntry>,
+    /// Posts imported with something that did not carry over.
+    pub warnings: Vec<ImportReportEntry>,
+}
+
+/// A person imported posts are written by, matched by email or created for the import.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedAuthor {
+    /// The author's name in the source, like a WordPress login.
+    pub login: String,
+    pub email: String,
+    /// `None` when a dry run would create the person.
+    pub person_id: Option<String>,
+    pub created: bool,
+}
+
+/// Why a source was not imported.
+#[derive(Debug, Clone)]
+pub enum ImportError {
+    /// The source could not be read.
+    Unreadable(String),
+    /// The file does not start with YAML (`---`) or TOML (`+++`) front matter.
+    NoFrontMatter,
+    /// The front matter could not be read.
+    FrontMatter(String),
+    /// The front matter has no title.
+    MissingTitle,
+    /// A WordPress item has an empty title.
+    UntitledItem,
+    InvalidDate(String),
+    /// A field failed draft validation.
+    Invalid(String),
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreadable(message) => write!(f, "Unable to read source: {}", message),
+            Self::NoFrontMatter => f.write_str("No front matter found."),
+            Self::FrontMatter(message) => write!(f, "Unable to read front matter: {}", message),
+            Self::MissingTitle => f.write_str("Front matter has no title."),
+            Self::UntitledItem => f.write_str("WordPress post has no title."),
+            Self::InvalidDate(date) => write!(f, "Unable to read date: {}", date),
+            Self::Invalid(message) => f.write_str(message),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+/// Languages posts are written in, as lowercase language tags like `en` or `de`.
+#[derive(Debug, Clone)]
+pub struct LocaleConfig {
+    pub locales: Vec<String>,
+    /// Used when a reader asks for no supported language. Always one of `locales`.
+    pub fallback: String,
+}
+
+impl LocaleConfig {
+    pub fn new(locales: Vec<String>, fallback: String) -> Self {
+        let fallback = normalize_tag(&fallback);
+
+        let mut unique = vec![fallback.clone()];
+        for locale in locales.iter().map(|l| normalize_tag(l)) {
+            if !locale.is_empty() && !unique.contains(&locale) {
+                unique.push(locale);
+            }
+        }
+
+        Self {
+            locales: unique,
+            fallback,
+        }
+    }
+
+    /// The supported locale matching `tag`, exactly or by its primary language, so `de-AT`
+    /// matches `de`.
+    pub fn supported(&self, tag: &str) -> Option<String> {
+        let tag = normalize_tag(tag);
+        let primary = tag.split('-').next().unwrap_or_default();
+
+        self.locales
+            .iter()
+            .find(|l| **l == tag)
+            .or_else(|| self.locales.iter().find(|l| *l == primary))
+            .cloned()
+    }
+
+    /// Chooses the language to read posts in: `lang` when supported, then the best supported
+    /// language of an `Accept-Language` header, then the fallback.
+    pub fn negotiate(&self, lang: Option<&str>, accept_language: Option<&str>) -> LocaleChoice {
+        let lang = lang
+            .and_then(|l| self.supported(l))
+            .or_else(|| accept_language.and_then(|h| self.preferred_by_accept_language(h)))
+            .unwrap_or_else(|| self.fallback.clone());
+
+        LocaleChoice {
+            lang,
+            fallback: self.fallback.clone(),
+        }
+    }
+
+    /// Only the fallback language, for listings that are not read by a particular reader.
+    pub fn fallback_choice(&self) -> LocaleChoice {
+        LocaleChoice {
+            lang: self.fallback.clone(),
+            fallback: self.fallback.clone(),
+        }
+    }
+
+    /// The supported language an `Accept-Language` header prefers most, skipping `q=0`.
+    fn preferred_by_accept_language(&self, header: &str) -> Option<String> {
+        let mut ranges: Vec<(&str, f32)> = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let q = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (!tag.is_empty() && tag != "*" && q > 0.0).then_some((tag, q))
+            })
+            .collect();
+
+        // stable, so equally weighted ranges keep the order they were sent in
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        ranges.into_iter().find_map(|(tag, _)| self.supported(tag))
+    }
+}
+
+/// The language published posts are read in, falling back for posts not translated into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocaleChoice {
+    pub lang: String,
+    pub fallback: String,
+}
+
+impl LocaleChoice {
+    /// The translation in `lang`, else the one in the fallback language, else the first by
+    /// language code, matching the translation listings pick for each post.
+    pub fn choose<T>(&self, translations: Vec<T>, lang_of: impl Fn(&T) -> &str) -> Option<T> {
+        let rank = |t: &T| {
+            let lang = lang_of(t);
+            let preference = if lang == self.lang {
+                0
+            } else if lang == self.fallback {
+                1
+            } else {
+                2
+            };
+            (preference, lang.to_string())
+        };
+
+        translations.into_iter().min_by_key(rank)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LangArgs {
+    /// Language to read posts in. Takes precedence over `Accept-Language`.
+    pub lang: Option<String>,
+}
+
+/// A published translation of a post, for `hreflang` links.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Translation {
+    pub lang: String,
+    pub draft_id: String,
+    pub title: String,
+}
+
+/// Lowercases a language tag and uses `-` between its subtags, so `en_US` becomes `en-us`.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().replace('_', "-").to_lowercase()
+}
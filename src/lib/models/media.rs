@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::meta::Meta;
+
+/// An uploaded file, stored once per distinct content.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Media {
+    pub id: String,
+    /// Hex SHA-256 of the file's bytes.
+    pub hash: String,
+    /// Where the file is kept in the media store, `{hash}.{extension}`.
+    pub key: String,
+    /// Public URL the file is served from.
+    #[serde(default)]
+    pub url: String,
+    /// Sniffed from the file's bytes, not taken from the upload.
+    pub content_type: String,
+    pub size: u64,
+    /// File name the media was first uploaded with.
+    #[serde(default)]
+    pub original_name: Option<String>,
+    /// Describes the media for screen readers, used as the `alt` of images.
+    #[serde(default)]
+    pub alt_text: Option<String>,
+    #[serde(default)]
+    pub caption: Option<String>,
+    #[serde(with = "time::serde::iso8601")]
+    pub at: OffsetDateTime,
+    /// Pixel size of images, after turning them upright.
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub blurhash: Option<String>,
+    /// Resized and WebP copies of images, including the original itself.
+    #[serde(default)]
+    pub variants: Vec<ImageVariant>,
+    pub meta: Meta<()>,
+}
+
+/// One size and format an image is available in, a single `srcset` candidate.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageVariant {
+    pub key: String,
+    /// Public URL the variant is served from, fixed when the image is uploaded.
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    pub content_type: String,
+}
+
+/// What a client needs for a responsive image. Variants are narrowest first, so those of
+/// one `content_type` join into a `srcset`, with a blurhash to show while they load.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageSet {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub blurhash: Option<String>,
+    pub variants: Vec<ImageVariant>,
+    #[serde(default)]
+    pub alt_text: Option<String>,
+    #[serde(default)]
+    pub caption: Option<String>,
+}
+
+/// Query string filters for the media library, used alongside [`PageArgs`].
+///
+/// [`PageArgs`]: super::page::PageArgs
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct MediaListArgs {
+    /// A full content type such as `image/png`, or just its top level such as `image`.
+    #[serde(default, rename = "type")]
+    pub content_type: Option<String>,
+    /// Case insensitive search of the file name, alt text and caption.
+    #[serde(default)]
+    pub q: Option<String>,
+}
+
+/// Replaces the descriptive text of media. Missing or blank fields are cleared.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct MediaDetailsArgs {
+    #[serde(default)]
+    pub alt_text: Option<String>,
+    #[serde(default)]
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DeleteMediaArgs {
+    /// Delete even when published posts still use the media.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// A draft that uses media, as its cover image or in its markdown.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MediaReference {
+    pub draft_id: String,
+    pub post_id: String,
+    pub title: String,
+    /// The draft is the live version of its post.
+    pub published: bool,
+    #[serde(with = "time::serde::iso8601")]
+    pub at: OffsetDateTime,
+    pub in_image: bool,
+    pub in_markdown: bool,
+}
+
+/// A new upload, or the existing media with the same content.
+#[derive(Debug, Serialize, Clone)]
+pub struct UploadedMedia {
+    pub media: Media,
+    /// The same content had already been uploaded, so nothing new was stored.
+    pub duplicate: bool,
+}
+
+/// The bytes of a media file, ready to be served.
+#[derive(Debug, Clone)]
+pub struct MediaFile {
+    pub bytes: Vec<u8>,
+    pub content_type: String,
+    /// Changes whenever the bytes do: the content hash, or the key of an image variant.
+    pub etag: String,
+}
+
+/// Why a media request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaError {
+    /// No `file` field in the multipart body.
+    MissingFile,
+    Empty,
+    /// Larger than the configured limit, in bytes.
+    TooLarge(usize),
+    /// The sniffed type is not one we accept, or could not be recognised.
+    UnsupportedType(Option<String>),
+    /// The multipart body could not be read.
+    Malformed(String),
+    NotFound,
+    /// Alt text or caption failed validation.
+    Invalid(String),
+    /// Published posts still use the media, by post id.
+    InUse(Vec<String>),
+    /// The file looked like an image but could not be decoded.
+    Undecodable(String),
+    Store(String),
+}
+
+impl std::fmt::Display for MediaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingFile => f.write_str("Missing file field in upload."),
+            Self::Empty => f.write_str("Uploaded file is empty."),
+            Self::TooLarge(max) => write!(f, "Uploaded file is larger than {} bytes.", max),
+            Self::UnsupportedType(Some(t)) => write!(f, "Unsupported file type: {}", t),
+            Self::UnsupportedType(None) => f.write_str("Unable to recognise the file type."),
+            Self::Malformed(message) => write!(f, "Unable to read upload: {}", message),
+            Self::NotFound => f.write_str("Unable to find media."),
+            Self::Invalid(message) => f.write_str(message),
+            Self::InUse(post_ids) => write!(
+                f,
+                "Media is used by published posts: {}. Pass force=true to delete it anyway.",
+                post_ids.join(", ")
+            ),
+            Self::Undecodable(message) => write!(f, "Unable to decode image: {}", message),
+            Self::Store(message) => write!(f, "Unable to store upload: {}", message),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+use surrealdb::types::RecordId;
+use time::OffsetDateTime;
+use ulid::Ulid;
+
+use crate::constants::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::utils::datetime_string;
+
+/// Direction a listing is sorted in. Ordering follows the ULID of the listed records,
+/// so `Newest` and `Oldest` also match creation order.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Newest,
+    Oldest,
+}
+
+/// Query string arguments shared by every paginated list endpoint.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PageArgs {
+    /// Opaque cursor taken from the `next_cursor` of a previous page. An unreadable cursor
+    /// fails extraction, so the request is rejected with 400 instead of restarting the listing.
+    #[serde(default, deserialize_with = "deserialize_cursor")]
+    pub cursor: Option<Ulid>,
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub sort: SortOrder,
+    /// Only include records at or after this date (ISO 8601).
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub from: Option<OffsetDateTime>,
+    /// Only include records at or before this date (ISO 8601).
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub to: Option<OffsetDateTime>,
+}
+
+impl PageArgs {
+    /// Requested page size clamped to `1..=MAX_PAGE_SIZE`.
+    pub fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    /// The ULID behind the opaque cursor.
+    pub fn cursor_ulid(&self) -> Option<Ulid> {
+        self.cursor
+    }
+
+    /// Turns the opaque cursor back into a [`RecordId`] on `table`.
+    pub fn cursor_record(&self, table: &str) -> Option<RecordId> {
+        self.cursor_ulid()
+            .map(|u| RecordId::new(table, u.to_string()))
+    }
+
+    pub fn from_rfc3339(&self) -> Option<String> {
+        datetime_string(self.from)
+    }
+
+    pub fn to_rfc3339(&self) -> Option<String> {
+        datetime_string(self.to)
+    }
+}
+
+/// Common envelope returned by paginated list endpoints.
+#[derive(Debug, Serialize, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass as `cursor` to fetch the next page. `None` on the last page.
+    pub next_cursor: Option<String>,
+    /// Number of records matching the filters, across all pages.
+    pub total: u64,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from `items`, which should hold up to `limit + 1` rows so we can
+    /// tell whether another page follows. `cursor_of` returns the `"table:ulid"` id
+    /// the listing is ordered by.
+    pub fn new(mut items: Vec<T>, limit: u32, total: u64, cursor_of: impl Fn(&T) -> &str) -> Self {
+        let has_more = items.len() > limit as usize;
+        items.truncate(limit as usize);
+
+        let next_cursor = if has_more {
+            items.last().map(|i| encode_cursor(cursor_of(i)))
+        } else {
+            None
+        };
+
+        Self {
+            items,
+            next_cursor,
+            total,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            total: self.total,
+        }
+    }
+}
+
+fn deserialize_cursor<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Ulid>, D::Error> {
+    Option::<String>::deserialize(d)?
+        .filter(|c| !c.is_empty())
+        .map(|c| Ulid::from_str(&c).map_err(|_| de::Error::custom("invalid cursor")))
+        .transpose()
+}
+
+/// Strips the table from a `"table:ulid"` id so only the ULID is exposed as the cursor.
+fn encode_cursor(id: &str) -> String {
+    id.split_once(':')
+        .map(|(_, key)| key)
+        .unwrap_or(id)
+        .to_string()
+}
@@ -3,15 +3,120 @@ use surrealdb::types::RecordId;
 
 use super::meta::Meta;
 
+/// Longest display name a person can set.
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 100;
+/// Longest bio a person can set.
+pub const MAX_BIO_LENGTH: usize = 2000;
+/// Most links a person can list on their profile.
+pub const MAX_PROFILE_LINKS: usize = 10;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Person {
     pub id: String,
     pub username: String,
     pub email: String,
     pub is_admin: bool,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub bio: Option<String>,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub links: Vec<ProfileLink>,
     pub meta: Meta<()>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileLink {
+    pub label: String,
+    pub url: String,
+}
+
+/// Request body replacing a person's profile. Omitted fields are cleared.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateProfileArgs {
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub links: Vec<ProfileLink>,
+}
+
+impl UpdateProfileArgs {
+    /// Checks lengths and that every url is http or https, so a profile can't link to
+    /// `javascript:` urls.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.display_name {
+            if name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+                return Err(format!(
+                    "Display name must be at most {} characters.",
+                    MAX_DISPLAY_NAME_LENGTH
+                ));
+            }
+        }
+        if let Some(bio) = &self.bio {
+            if bio.chars().count() > MAX_BIO_LENGTH {
+                return Err(format!(
+                    "Bio must be at most {} characters.",
+                    MAX_BIO_LENGTH
+                ));
+            }
+        }
+        if let Some(url) = &self.avatar_url {
+            if !is_web_url(url) {
+                return Err("Avatar url must start with http:// or https://.".into());
+            }
+        }
+        if self.links.len() > MAX_PROFILE_LINKS {
+            return Err(format!("At most {} links are allowed.", MAX_PROFILE_LINKS));
+        }
+        for link in &self.links {
+            if link.label.trim().is_empty() {
+                return Err("Every link needs a label.".into());
+            }
+            if !is_web_url(&link.url) {
+                return Err(format!(
+                    "Link url must start with http:// or https://: {}",
+                    link.url
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_web_url(url: &str) -> bool {
+    let lower = url.trim().to_lowercase();
+    lower.starts_with("https://") || lower.starts_with("http://")
+}
+
+/// Public view of a person. Never includes their email or admin status.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Author {
+    pub id: String,
+    pub username: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub bio: Option<String>,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub links: Vec<ProfileLink>,
+}
+
+/// The part of an [`Author`] embedded in post responses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorSummary {
+    pub id: String,
+    pub username: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LogInCreds {
     pub email: String,
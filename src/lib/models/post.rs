@@ -2,7 +2,16 @@
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
+use super::locale::Translation;
+use super::media::ImageSet;
 use super::meta::Meta;
+use super::person::AuthorSummary;
+use super::reaction::PostReactions;
+use crate::constants::{
+    MAX_ALT_TEXT_LENGTH, MAX_CANONICAL_URL_LENGTH, MAX_EXCERPT_LENGTH, MAX_META_DESCRIPTION_LENGTH,
+    MAX_SEO_TITLE_LENGTH,
+};
+use crate::render::render_markdown;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Post {
@@ -22,21 +31,409 @@ pub struct PostVersion {
     pub id: String,
     pub draft_id: String,
     pub title: String,
-    pub markdown: String,
+    /// Raw markdown, left out when the post is sent rendered to html instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markdown: Option<String>,
     pub author: String,
+    #[serde(default)]
+    pub author_summary: Option<AuthorSummary>,
+    /// Language the draft is written in. Each language has its own chain of drafts.
+    #[serde(default)]
+    pub lang: String,
+    /// Published translations of the post, including this one when it is published.
+    #[serde(default)]
+    pub translations: Vec<Translation>,
     pub published: Option<bool>,
     #[serde(with = "time::serde::iso8601")]
     pub at: OffsetDateTime,
     pub image: String,
+    /// Sizes and formats of `image` for `srcset`, when it is a processed upload.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_set: Option<ImageSet>,
     pub visits: u128,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Categories the post was filed under where it was imported from. Kept on the post,
+    /// so every draft of it has the same ones.
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Hand written summary. When missing, one is taken from the start of the markdown.
+    #[serde(default)]
+    pub excerpt: Option<String>,
+    /// Alt text of `image`.
+    #[serde(default)]
+    pub cover_alt: Option<String>,
+    /// Title for search results and link previews, when it should differ from `title`.
+    #[serde(default)]
+    pub seo_title: Option<String>,
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    /// Where the post was first published, when it is a copy of a post elsewhere.
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub publish_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub unpublish_at: Option<OffsetDateTime>,
+    /// When the post first went live in this language. Missing on drafts published before
+    /// it was recorded.
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub published_on: Option<OffsetDateTime>,
+    /// Draft this version was restored from, when it was created by a revert.
+    #[serde(default)]
+    pub restored_from: Option<String>,
+    /// When this draft was trashed on its own. Drafts of a trashed post use `meta.deleted_on`.
+    #[serde(
+        default,
+        with = "time::serde::iso8601::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub deleted_on: Option<OffsetDateTime>,
+    /// Its author's working copy of the post, updated in place by their autosaves until it
+    /// is checkpointed.
+    #[serde(default)]
+    pub working: bool,
     pub meta: Meta<()>,
+    /// Reaction counts, only filled in on published post responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reactions: Option<PostReactions>,
+    /// Sanitized html and reading metadata, only filled in when html is requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rendered: Option<RenderedMarkdown>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl PostVersion {
+    /// Swaps the markdown for [`PostVersion::rendered`] when `format` asks for html, so
+    /// only the requested format is sent.
+    pub fn render(mut self, format: ContentFormat) -> Self {
+        if format == ContentFormat::Html {
+            let markdown = self.markdown.take().unwrap_or_default();
+            self.rendered = Some(render_markdown(&markdown));
+        }
+        self
+    }
+
+    /// The raw markdown, empty once the post has been rendered.
+    pub fn markdown(&self) -> &str {
+        self.markdown.as_deref().unwrap_or_default()
+    }
+}
+
+/// A post version without its markdown, used by listings.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostSummary {
+    pub id: String,
+    pub draft_id: String,
+    pub title: String,
+    pub author: String,
+    #[serde(default)]
+    pub author_summary: Option<AuthorSummary>,
+    /// Language the draft is written in. Each language has its own chain of drafts.
+    #[serde(default)]
+    pub lang: String,
+    /// Published translations of the post, including this one when it is published.
+    #[serde(default)]
+    pub translations: Vec<Translation>,
+    pub published: Option<bool>,
+    #[serde(with = "time::serde::iso8601")]
+    pub at: OffsetDateTime,
+    pub image: String,
+    /// Sizes and formats of `image` for `srcset`, when it is a processed upload.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_set: Option<ImageSet>,
+    pub visits: u128,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Categories the post was filed under where it was imported from. Kept on the post,
+    /// so every draft of it has the same ones.
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Hand written summary, or else one taken from the start of the markdown once
+    /// [`PostSummary::summarize`] has run.
+    #[serde(default)]
+    pub excerpt: Option<String>,
+    #[serde(default)]
+    pub cover_alt: Option<String>,
+    /// Filled in by [`PostSummary::summarize`].
+    #[serde(default)]
+    pub reading_time_minutes: usize,
+    pub meta: Meta<()>,
+    /// Reaction counts, only filled in on published post responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reactions: Option<PostReactions>,
+    /// Raw markdown, only kept until [`PostSummary::summarize`] has read it.
+    #[serde(default, skip_serializing)]
+    markdown: Option<String>,
+}
+
+impl PostSummary {
+    /// Falls back to the auto excerpt and works out the reading time from the markdown,
+    /// then drops the markdown.
+    pub fn summarize(mut self) -> Self {
+        let rendered = render_markdown(&self.markdown.take().unwrap_or_default());
+        self.excerpt = self
+            .excerpt
+            .or_else(|| Some(rendered.excerpt).filter(|e| !e.is_empty()));
+        self.reading_time_minutes = rendered.reading_time_minutes;
+        self
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct DraftPostArgs {
     pub id: Option<String>,
+    /// Language of the draft, one of the configured locales. Defaults to the fallback locale.
+    #[serde(default)]
+    pub lang: Option<String>,
     pub title: String,
     pub markdown: String,
     pub published: bool,
     pub image: String,
+    /// Topics the post is filed under, e.g. for per-tag feeds.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub excerpt: Option<String>,
+    #[serde(default)]
+    pub cover_alt: Option<String>,
+    #[serde(default)]
+    pub seo_title: Option<String>,
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    /// Absolute `http` or `https` URL.
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+    /// When set, the draft is published automatically at this time.
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub publish_at: Option<OffsetDateTime>,
+    /// When set, the draft is unpublished automatically at this time.
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub unpublish_at: Option<OffsetDateTime>,
+    /// The draft this edit started from. When a newer draft of the post exists the write
+    /// is rejected as a conflict. An autosave keeps passing the draft it started from: the
+    /// caller's own working copy of that draft is not treated as newer.
+    #[serde(default)]
+    pub base_draft_id: Option<String>,
+    /// Write the draft even if it conflicts with a newer one.
+    #[serde(default)]
+    pub force: bool,
+    /// Update the caller's working copy of the post in place instead of saving a checkpoint.
+    #[serde(default)]
+    pub autosave: bool,
+}
+
+impl DraftPostArgs {
+    /// The draft's tags trimmed, lowercased and without duplicates or empty tags.
+    pub fn normalized_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in &self.tags {
+            let tag = tag.trim().to_lowercase();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+
+    /// Trims the excerpt and SEO fields, clearing blank ones, and checks their lengths, that
+    /// the canonical URL is absolute and that a scheduled unpublish comes after the publish.
+    pub fn validate_fields(&mut self) -> Result<(), DraftError> {
+        self.excerpt = checked_field(self.excerpt.take(), "Excerpt", MAX_EXCERPT_LENGTH)?;
+        self.cover_alt =
+            checked_field(self.cover_alt.take(), "Cover alt text", MAX_ALT_TEXT_LENGTH)?;
+        self.seo_title = checked_field(self.seo_title.take(), "SEO title", MAX_SEO_TITLE_LENGTH)?;
+        self.meta_description = checked_field(
+            self.meta_description.take(),
+            "Meta description",
+            MAX_META_DESCRIPTION_LENGTH,
+        )?;
+        self.canonical_url = checked_field(
+            self.canonical_url.take(),
+            "Canonical URL",
+            MAX_CANONICAL_URL_LENGTH,
+        )?;
+
+        if let Some(url) = &self.canonical_url {
+            let absolute = ["http://", "https://"]
+                .iter()
+                .any(|scheme| url.len() > scheme.len() && url.starts_with(scheme));
+            if !absolute || url.contains(char::is_whitespace) {
+                return Err(DraftError::Invalid(
+                    "Canonical URL must be an absolute http or https URL.".into(),
+                ));
+            }
+        }
+
+        check_schedule(self.publish_at, self.unpublish_at)
+    }
+}
+
+/// Rejects an unpublish time that is not after the publish time, when both are set.
+fn check_schedule(
+    publish_at: Option<OffsetDateTime>,
+    unpublish_at: Option<OffsetDateTime>,
+) -> Result<(), DraftError> {
+    match (publish_at, unpublish_at) {
+        (Some(publish_at), Some(unpublish_at)) if unpublish_at <= publish_at => Err(
+            DraftError::Invalid("Unpublish time must be after the publish time.".into()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Trimmed text, `None` when blank.
+fn checked_field(
+    text: Option<String>,
+    field: &str,
+    max_length: usize,
+) -> Result<Option<String>, DraftError> {
+    let text = text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+
+    match text {
+        Some(t) if t.chars().count() > max_length => Err(DraftError::Invalid(format!(
+            "{} must be at most {} characters.",
+            field, max_length
+        ))),
+        text => Ok(text),
+    }
+}
+
+/// Returned instead of a new draft when the post has changed since the draft an edit
+/// was based on.
+#[derive(Debug, Serialize, Clone)]
+pub struct DraftConflict {
+    pub base_draft_id: String,
+    /// The newest draft of the post, which the edit would have silently replaced.
+    pub latest: PostVersion,
+}
+
+/// Why a draft was not saved.
+#[derive(Debug, Clone)]
+pub enum DraftError {
+    /// No draft that is not in the trash has the id.
+    NotFound,
+    /// Boxed, as it holds a whole [`PostVersion`].
+    Conflict(Box<DraftConflict>),
+    /// A field failed validation.
+    Invalid(String),
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RevertArgs {
+    /// Publish the restored draft in the same transaction that creates it.
+    #[serde(default)]
+    pub publish: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RelatedArgs {
+    pub limit: Option<usize>,
+}
+
+/// The text of a published version, all related posts are ranked by.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RelatedSource {
+    /// Post id.
+    pub id: String,
+    pub title: String,
+    pub markdown: String,
+    #[serde(with = "time::serde::iso8601")]
+    pub at: OffsetDateTime,
+}
+
+/// Everything currently in the trash.
+#[derive(Debug, Serialize)]
+pub struct Trash {
+    pub posts: Vec<PostHydrated>,
+    /// Drafts trashed on their own, not along with their post.
+    pub drafts: Vec<PostVersion>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PurgeArgs {
+    /// Only purge items that have been in the trash for longer than this many days.
+    pub older_than_days: u32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PurgeResult {
+    pub posts: u64,
+    pub drafts: u64,
+}
+
+/// Request body for setting or clearing the schedule of a draft.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ScheduleArgs {
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub publish_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub unpublish_at: Option<OffsetDateTime>,
+}
+
+impl ScheduleArgs {
+    pub fn validate(&self) -> Result<(), DraftError> {
+        check_schedule(self.publish_at, self.unpublish_at)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduledAction {
+    Publish,
+    Unpublish,
+}
+
+/// An upcoming scheduled publish or unpublish of a draft.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScheduledChange {
+    pub id: String,
+    pub draft_id: String,
+    pub title: String,
+    pub action: ScheduledAction,
+    #[serde(with = "time::serde::iso8601")]
+    pub at: OffsetDateTime,
+}
+
+/// Row selected for drafts that have a publish or unpublish time set.
+#[derive(Debug, Deserialize)]
+pub struct ScheduledDraft {
+    pub id: String,
+    pub draft_id: String,
+    pub title: String,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub publish_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub unpublish_at: Option<OffsetDateTime>,
+}
+
+/// Whether a post body is sent as raw markdown or rendered to html.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RenderArgs {
+    #[serde(default)]
+    pub format: ContentFormat,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+    pub excerpt: String,
+    pub word_count: usize,
+    pub reading_time_minutes: usize,
+}
+
+/// A heading in a rendered post, linking to the heading's `id` attribute.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TocEntry {
+    pub level: u8,
+    pub id: String,
+    pub text: String,
 }
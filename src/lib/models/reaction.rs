@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReactionArgs {
+    /// One of the configured reaction kinds, e.g. `"👍"`.
+    pub kind: String,
+}
+
+/// Reactions on a published post, as shown to the person reading it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PostReactions {
+    /// How many people reacted with each kind. Kinds nobody used are left out.
+    pub counts: BTreeMap<String, u64>,
+    /// The kinds the current person reacted with, empty for anonymous readers.
+    pub mine: Vec<String>,
+}
+
+/// Number of reactions of one kind on a post.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReactionCount {
+    pub post: String,
+    pub kind: String,
+    pub count: u64,
+}
+
+/// A reaction the current person left on a post.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OwnReaction {
+    pub post: String,
+    pub kind: String,
+}
+
+/// Why a reaction could not be added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReactionError {
+    /// The post does not exist or is not published.
+    NotFound,
+    /// The kind is not in the configured set.
+    UnknownKind(String),
+}
+
+impl std::fmt::Display for ReactionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => f.write_str("Unable to find published post."),
+            Self::UnknownKind(kind) => write!(f, "Unknown reaction: {}", kind),
+        }
+    }
+}
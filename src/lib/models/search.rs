@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use super::post::PostSummary;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SearchArgs {
+    pub q: String,
+    /// Cursor taken from the `next_cursor` of a previous page. Results are ordered by score
+    /// rather than by id, so unlike other listings it is an offset into the ranking, not a
+    /// record id, and pages can shift if posts are published between requests. A cursor that
+    /// is not a number is rejected with 400.
+    pub cursor: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchResult {
+    pub post: PostSummary,
+    pub score: f64,
+    /// Html escaped title with matched terms wrapped in `<mark>` tags.
+    pub title: String,
+    /// Html escaped excerpt of the markdown around the first match, with matched terms
+    /// wrapped in `<mark>` tags.
+    pub snippet: String,
+}
+
+/// Ranking fields selected alongside a [`PostSummary`] by the search query.
+#[derive(Debug, Deserialize)]
+pub struct SearchRank {
+    pub score: Option<f64>,
+    pub title_highlight: Option<String>,
+    pub markdown_highlight: Option<String>,
+}
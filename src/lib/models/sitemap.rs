@@ -0,0 +1,27 @@
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+/// A page selected by a [`SitemapSource`] query.
+///
+/// [`SitemapSource`]: crate::sitemap::SitemapSource
+#[derive(Debug, Deserialize, Clone)]
+pub struct SitemapRow {
+    pub id: String,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub lastmod: Option<OffsetDateTime>,
+}
+
+/// A public URL listed in the sitemap.
+#[derive(Debug, Clone)]
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: Option<OffsetDateTime>,
+}
+
+/// What `/sitemap.xml` serves: every URL directly, or an index of sitemap pages once
+/// there are too many for one file.
+#[derive(Debug, Clone)]
+pub enum Sitemap {
+    Urlset(String),
+    Index(String),
+}
@@ -1,6 +1,15 @@
 pub mod constants;
 pub mod db;
+pub mod diff;
+pub mod feed;
+pub mod head;
+pub mod imaging;
+pub mod import;
 pub mod models;
+pub mod related;
+pub mod render;
 pub mod repos;
 pub mod services;
+pub mod sitemap;
+pub mod storage;
 pub mod utils;
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::models::post::RelatedSource;
+
+/// How many times more a term in the title counts than the same term in the markdown.
+const TITLE_WEIGHT: f64 = 3.0;
+
+/// Shortest word kept as a term.
+const MIN_TERM_LENGTH: usize = 3;
+
+/// Scores closer than this are treated as a tie and ordered by recency instead.
+const SCORE_EPSILON: f64 = 1e-9;
+
+/// Common English words that say nothing about what a post is about.
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "again", "all", "also", "and", "any", "are", "because", "been", "before",
+    "being", "but", "can", "could", "did", "does", "doing", "each", "for", "from", "had", "has",
+    "have", "her", "here", "him", "his", "how", "into", "its", "just", "more", "most", "not",
+    "now", "off", "once", "only", "other", "our", "out", "over", "own", "same", "she", "should",
+    "some", "such", "than", "that", "the", "their", "them", "then", "there", "these", "they",
+    "this", "those", "through", "too", "under", "until", "very", "was", "were", "what", "when",
+    "where", "which", "while", "who", "why", "will", "with", "would", "you", "your",
+];
+
+/// Ranks every published version against the others by the cosine similarity of their
+/// tf-idf term vectors, built from the title and markdown. Ties go to the more recent
+/// version.
+///
+/// Returns up to `limit` related post ids for each post id, most related first. Posts
+/// sharing no terms are never considered related.
+pub fn rank_related(posts: &[RelatedSource], limit: usize) -> HashMap<String, Vec<String>> {
+    let term_counts: Vec<HashMap<String, f64>> = posts
+        .iter()
+        .map(|p| count_terms(&p.title, &p.markdown))
+        .collect();
+
+    // number of posts each term appears in
+    let mut doc_freq: HashMap<&str, f64> = HashMap::new();
+    for counts in &term_counts {
+        for term in counts.keys() {
+            *doc_freq.entry(term.as_str()).or_default() += 1.0;
+        }
+    }
+
+    let total = posts.len() as f64;
+    let vectors: Vec<HashMap<&str, f64>> = term_counts
+        .iter()
+        .map(|counts| {
+            let mut vector: HashMap<&str, f64> = counts
+                .iter()
+                .map(|(term, count)| {
+                    let idf = ((1.0 + total) / (1.0 + doc_freq[term.as_str()])).ln() + 1.0;
+                    (term.as_str(), (1.0 + count.ln()) * idf)
+                })
+                .collect();
+            normalize(&mut vector);
+            vector
+        })
+        .collect();
+
+    let mut related = HashMap::new();
+    for (i, post) in posts.iter().enumerate() {
+        let mut scored: Vec<(f64, &RelatedSource)> = posts
+            .iter()
+            .enumerate()
+            .filter(|(j, other)| *j != i && other.id != post.id)
+            .map(|(j, other)| (dot(&vectors[i], &vectors[j]), other))
+            .filter(|(score, _)| *score > SCORE_EPSILON)
+            .collect();
+
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            if (a_score - b_score).abs() < SCORE_EPSILON {
+                b.at.cmp(&a.at)
+            } else {
+                b_score.total_cmp(a_score)
+            }
+        });
+
+        related.insert(
+            post.id.clone(),
+            scored
+                .into_iter()
+                .take(limit)
+                .map(|(_, other)| other.id.clone())
+                .collect(),
+        );
+    }
+
+    related
+}
+
+/// Weighted count of each term in a post, with title terms counting for more.
+fn count_terms(title: &str, markdown: &str) -> HashMap<String, f64> {
+    let mut counts = HashMap::new();
+    for term in terms(title) {
+        *counts.entry(term).or_default() += TITLE_WEIGHT;
+    }
+    for term in terms(markdown) {
+        *counts.entry(term).or_default() += 1.0;
+    }
+    counts
+}
+
+/// Lowercase words of `text`, skipping numbers, short words and stop words.
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= MIN_TERM_LENGTH)
+        .filter(|w| !w.chars().all(|c| c.is_numeric()))
+        .map(|w| w.to_lowercase())
+        .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+}
+
+fn normalize(vector: &mut HashMap<&str, f64>) {
+    let length = vector.values().map(|w| w * w).sum::<f64>().sqrt();
+    if length > 0.0 {
+        for weight in vector.values_mut() {
+            *weight /= length;
+        }
+    }
+}
+
+fn dot(a: &HashMap<&str, f64>, b: &HashMap<&str, f64>) -> f64 {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small
+        .iter()
+        .filter_map(|(term, weight)| large.get(term).map(|other| weight * other))
+        .sum()
+}
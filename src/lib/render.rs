@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+
+use crate::models::post::{RenderedMarkdown, TocEntry};
+
+/// Average adult silent reading speed used for the reading time estimate.
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Longest auto excerpt, in characters, before it is cut at a word boundary.
+const EXCERPT_LENGTH: usize = 280;
+
+/// Renders CommonMark with GitHub extensions to sanitized HTML, along with a table of
+/// contents, an excerpt, and word count and reading time estimates.
+///
+/// Headings are given slug ids so the table of contents can link to them. Raw HTML in
+/// the markdown is passed through the sanitizer, which strips scripts, event handlers
+/// and anything else not on its allow list.
+pub fn render_markdown(markdown: &str) -> RenderedMarkdown {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_GFM;
+
+    let mut events: Vec<Event> = Parser::new_ext(markdown, options).collect();
+
+    let mut toc = vec![];
+    let mut slugs: HashMap<String, usize> = HashMap::new();
+    let mut word_count = 0;
+    let mut excerpt: Option<String> = None;
+
+    let mut heading: Option<(usize, String)> = None;
+    let mut paragraph: Option<String> = None;
+
+    for idx in 0..events.len() {
+        match &events[idx] {
+            Event::Start(Tag::Heading { .. }) => heading = Some((idx, String::new())),
+            Event::End(TagEnd::Heading(level)) => {
+                let level = *level as u8;
+
+                if let Some((start, text)) = heading.take() {
+                    let id = unique_slug(&text, &mut slugs);
+
+                    // give the opening tag its slug so the toc can link to it
+                    if let Event::Start(Tag::Heading {
+                        level,
+                        classes,
+                        attrs,
+                        ..
+                    }) = &events[start]
+                    {
+                        let with_id = Event::Start(Tag::Heading {
+                            level: *level,
+                            id: Some(CowStr::from(id.clone())),
+                            classes: classes.clone(),
+                            attrs: attrs.clone(),
+                        });
+                        events[start] = with_id;
+                    }
+
+                    toc.push(TocEntry {
+                        level,
+                        id,
+                        text: text.trim().to_string(),
+                    });
+                }
+            }
+            Event::Start(Tag::Paragraph) if excerpt.is_none() => paragraph = Some(String::new()),
+            Event::End(TagEnd::Paragraph) => {
+                if let Some(text) = paragraph.take() {
+                    if !text.trim().is_empty() {
+                        excerpt = Some(truncate_words(text.trim(), EXCERPT_LENGTH));
+                    }
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                word_count += text.split_whitespace().count();
+
+                if let Some((_, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(text);
+                }
+                if let Some(paragraph_text) = paragraph.as_mut() {
+                    paragraph_text.push_str(text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(paragraph_text) = paragraph.as_mut() {
+                    paragraph_text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events.into_iter());
+
+    RenderedMarkdown {
+        html: sanitize_html(&unsafe_html),
+        toc,
+        excerpt: excerpt.unwrap_or_default(),
+        word_count,
+        reading_time_minutes: word_count.div_ceil(WORDS_PER_MINUTE).max(1),
+    }
+}
+
+/// Strips anything unsafe from rendered html while keeping heading anchors, code block
+/// language classes and task list checkboxes.
+fn sanitize_html(unsafe_html: &str) -> String {
+    ammonia::Builder::default()
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .add_tag_attributes("h1", ["id"])
+        .add_tag_attributes("h2", ["id"])
+        .add_tag_attributes("h3", ["id"])
+        .add_tag_attributes("h4", ["id"])
+        .add_tag_attributes("h5", ["id"])
+        .add_tag_attributes("h6", ["id"])
+        .add_tag_attributes("code", ["class"])
+        .clean(unsafe_html)
+        .to_string()
+}
+
+/// Lowercase, dash separated slug of `text`, suffixed with a counter when the same
+/// slug has already been handed out for this document.
+fn unique_slug(text: &str, seen: &mut HashMap<String, usize>) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.ends_with('-') && !slug.is_empty() {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-').to_string();
+    let slug = if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    };
+
+    let count = seen.entry(slug.clone()).or_insert(0);
+    *count += 1;
+
+    if *count == 1 {
+        slug
+    } else {
+        format!("{}-{}", slug, *count - 1)
+    }
+}
+
+/// Cuts `text` to at most `max_chars` characters at a word boundary, adding an ellipsis
+/// when anything was removed.
+pub fn truncate_words(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let mut out = String::new();
+    for word in text.split_whitespace() {
+        if out.chars().count() + word.chars().count() + 1 > max_chars {
+            break;
+        }
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    out.push('…');
+    out
+}
@@ -1,3 +1,8 @@
+pub mod r_analytics;
+pub mod r_comments;
+pub mod r_media;
 pub mod r_meta;
+pub mod r_page;
 pub mod r_persons;
 pub mod r_posts;
+pub mod r_reactions;
@@ -0,0 +1,186 @@
+use surrealdb::types::RecordId;
+
+use crate::db::nova_db::NovaQuery;
+use crate::utils::thing_from_string;
+
+use super::r_meta::MetaRepo;
+use super::r_posts::select_summary_string;
+
+/// Binds `$published` to the posts that currently have a published, untrashed version,
+/// so rankings are cut to size only after unpublished and trashed posts are dropped.
+const LET_PUBLISHED_POSTS: &str = r#"
+    LET $published = SELECT VALUE out FROM drafted
+        WHERE published = true
+            AND meta.deleted_on IS NONE
+            AND deleted_on IS NONE;
+"#;
+
+#[derive(Debug, Clone)]
+pub struct AnalyticsRepo {
+    pub select_summary_string: String,
+}
+
+impl Default for AnalyticsRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnalyticsRepo {
+    pub fn new() -> Self {
+        Self {
+            select_summary_string: select_summary_string(&MetaRepo::new().select_meta_string),
+        }
+    }
+
+    /// Query: record a view of a post's published draft by a visitor not yet seen today
+    /// (run in a transaction, returns whether the view was counted).
+    ///
+    /// `$visitor_id` is keyed on the post, day and visitor hash so a repeat visit on the
+    /// same day finds the existing record. `$view_id` is keyed on the post and day.
+    pub fn query_record_view(
+        &self,
+        post_id: &str,
+        visitor_id: RecordId,
+        view_id: RecordId,
+        day: String,
+    ) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            LET $draft_id = array::first(
+                SELECT VALUE id FROM drafted
+                WHERE out = $post_id
+                    AND published = true
+                    AND meta.deleted_on IS NONE
+                    AND deleted_on IS NONE
+                LIMIT 1
+            );
+            LET $new = $draft_id IS NOT NONE AND !record::exists($visitor_id);
+
+            IF $new {
+                CREATE $visitor_id SET post = $post_id, day = <datetime>$day;
+                UPSERT $view_id SET post = $post_id, day = <datetime>$day, views += 1;
+                UPDATE $draft_id SET visits += 1;
+            };
+
+            RETURN $new;
+            "#,
+        )
+        .bind("post_id", thing_from_string(post_id))
+        .bind("visitor_id", visitor_id)
+        .bind("view_id", view_id)
+        .bind("day", day)
+    }
+
+    /// Query: select daily views of a post between two days (returns Vec<DailyViews>).
+    pub fn query_select_post_views(&self, post_id: &str, from: String, to: String) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            SELECT day, views
+            FROM post_view
+            WHERE post = $post_id
+                AND day >= <datetime>$from
+                AND day <= <datetime>$to
+            ORDER BY day ASC;
+            "#,
+        )
+        .bind("post_id", thing_from_string(post_id))
+        .bind("from", from)
+        .bind("to", to)
+    }
+
+    /// Query: select daily views across every post between two days (returns Vec<DailyViews>).
+    pub fn query_select_site_views(&self, from: String, to: String) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            SELECT day, math::sum(views) as views
+            FROM post_view
+            WHERE day >= <datetime>$from AND day <= <datetime>$to
+            GROUP BY day
+            ORDER BY day ASC;
+            "#,
+        )
+        .bind("from", from)
+        .bind("to", to)
+    }
+
+    /// Query: delete visitor hashes from before `before` (returns nothing).
+    ///
+    /// Hashes are only needed to dedupe views within a day, the daily totals live on
+    /// `post_view`.
+    pub fn query_prune_visitors(&self, before: String) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            DELETE post_visitor WHERE day < <datetime>$before;
+            "#,
+        )
+        .bind("before", before)
+    }
+
+    /// Query: select the published posts with the most views, optionally only counting days
+    /// from `from` onwards (returns Vec<PostViewTotal> at index 1, most viewed first).
+    pub fn query_select_top_posts(&self, from: Option<String>, limit: u32) -> NovaQuery {
+        let since = if from.is_some() {
+            "AND day >= <datetime>$from"
+        } else {
+            ""
+        };
+
+        let sql = format!(
+            r#"
+            {LET_PUBLISHED_POSTS}
+            SELECT fn::string_id(post) as post, math::sum(views) as views
+            FROM post_view
+            WHERE post IN $published {since}
+            GROUP BY post
+            ORDER BY views DESC
+            LIMIT {limit};
+            "#
+        );
+
+        let mut q = NovaQuery::new(sql);
+        if let Some(from) = from {
+            q = q.bind("from", from);
+        }
+        q
+    }
+
+    /// Query: select each published post's daily views from `from` onwards
+    /// (returns Vec<PostDailyViews> at index 1).
+    pub fn query_select_daily_post_views(&self, from: String) -> NovaQuery {
+        let sql = format!(
+            r#"
+            {LET_PUBLISHED_POSTS}
+            SELECT fn::string_id(post) as post, day, views
+            FROM post_view
+            WHERE post IN $published AND day >= <datetime>$from;
+            "#
+        );
+        NovaQuery::new(sql).bind("from", from)
+    }
+
+    /// Query: select every published translation of each of `post_ids` (returns
+    /// Vec<PostSummary>, in no particular order, skipping posts that are unpublished or
+    /// trashed).
+    pub fn query_select_published_summaries(&self, post_ids: &[String]) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT
+                {}
+            FROM drafted
+            WHERE out IN $post_ids
+                AND published = true
+                AND meta.deleted_on IS NONE
+                AND deleted_on IS NONE;
+            "#,
+            self.select_summary_string
+        );
+        NovaQuery::new(sql).bind(
+            "post_ids",
+            post_ids
+                .iter()
+                .map(|id| thing_from_string(id))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
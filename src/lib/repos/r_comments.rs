@@ -0,0 +1,285 @@
+use crate::constants::MAX_COMMENT_DEPTH;
+use crate::db::nova_db::NovaQuery;
+use crate::models::comment::CommentStatus;
+use crate::models::page::PageArgs;
+use crate::utils::thing_from_string;
+
+use super::r_meta::MetaRepo;
+use super::r_page::{bind_page, PageSql};
+use super::r_persons::SELECT_AUTHOR_SUMMARY_STRING;
+
+#[derive(Debug, Clone)]
+pub struct CommentsRepo {
+    pub meta: MetaRepo,
+    pub select_comment_string: String,
+}
+
+/// Projection selecting a [`Comment`] from a `commented` record.
+///
+/// [`Comment`]: crate::models::comment::Comment
+pub fn select_comment_string(select_meta_string: &str) -> String {
+    format!(
+        r#"
+        fn::string_id(id) as id,
+        fn::string_id(out) as post,
+        fn::string_id(in) as author,
+        {SELECT_AUTHOR_SUMMARY_STRING},
+        (IF !type::is_none(parent) THEN fn::string_id(parent) END) as parent,
+        body,
+        status,
+        at,
+        edited_on,
+        {select_meta_string}
+    "#
+    )
+}
+
+impl Default for CommentsRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommentsRepo {
+    pub fn new() -> Self {
+        let meta = MetaRepo::new();
+
+        Self {
+            select_comment_string: select_comment_string(&meta.select_meta_string),
+            meta,
+        }
+    }
+
+    /// Query: whether a post exists, is published and accepts comments
+    /// (returns PostCommentState).
+    pub fn query_select_post_comment_state(&self, post_id: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            LET $post = (
+                SELECT comments_open
+                FROM ONLY post
+                WHERE id = $post_id AND meta.deleted_on IS NONE
+                LIMIT 1
+            );
+            LET $published = array::len(
+                SELECT VALUE id FROM drafted
+                WHERE out = $post_id
+                    AND published = true
+                    AND meta.deleted_on IS NONE
+                    AND deleted_on IS NONE
+            ) > 0;
+
+            RETURN {
+                found: $post IS NOT NONE,
+                published: $published,
+                open: $post.comments_open != false
+            };
+            "#,
+        )
+        .bind("post_id", thing_from_string(post_id))
+    }
+
+    /// Query: select a comment by id, including deleted ones (returns Vec<Comment>).
+    pub fn query_select_comment(&self, comment_id: &str) -> NovaQuery {
+        let sql = format!(
+            "SELECT {} FROM commented WHERE id = $comment_id;",
+            self.select_comment_string
+        );
+        NovaQuery::new(sql).bind("comment_id", thing_from_string(comment_id))
+    }
+
+    /// Query: select every comment on a post, in any state, oldest first (returns Vec<Comment>).
+    pub fn query_select_post_comments(&self, post_id: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT
+                {}
+            FROM commented
+            WHERE out = $post_id
+            ORDER BY id ASC;
+            "#,
+            self.select_comment_string
+        );
+        NovaQuery::new(sql).bind("post_id", thing_from_string(post_id))
+    }
+
+    /// Query: select the comment on a post a reply is written under, with how deeply it is
+    /// nested (returns Option<ReplyParent>).
+    pub fn query_select_reply_parent(&self, post_id: &str, parent_id: &str) -> NovaQuery {
+        // `parent`, `parent.parent`, ... as deep as replies can be nested
+        let ancestors = (1..MAX_COMMENT_DEPTH)
+            .map(|n| vec!["parent"; n].join("."))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let sql = format!(
+            r#"
+            SELECT
+                status,
+                meta.deleted_on IS NOT NONE AS deleted,
+                array::len(array::compact([{ancestors}])) + 1 AS depth
+            FROM ONLY commented
+            WHERE id = $parent_id AND out = $post_id
+            LIMIT 1;
+            "#
+        );
+        NovaQuery::new(sql)
+            .bind("post_id", thing_from_string(post_id))
+            .bind("parent_id", thing_from_string(parent_id))
+    }
+
+    /// Query: comment on a post as `$person_id` (run in a transaction, returns Comment).
+    pub fn query_create_comment(
+        &self,
+        post_id: &str,
+        person_id: &str,
+        body: String,
+        parent_id: Option<&str>,
+        status: CommentStatus,
+    ) -> NovaQuery {
+        let sql = format!(
+            r#"
+            {}
+            LET $comment_id = commented:ulid();
+
+            RELATE $person_id->commented->$post_id
+                SET
+                    id = $comment_id,
+                    body = $body,
+                    parent = $parent_id,
+                    status = $status,
+                    at = time::now(),
+                    meta = $meta_id;
+
+            SELECT
+                {}
+            FROM ONLY commented
+            WHERE id = $comment_id
+            LIMIT 1;
+            "#,
+            self.meta.sql_create_meta("$meta_id"),
+            self.select_comment_string
+        );
+        NovaQuery::new(sql)
+            .bind("created_by", thing_from_string(person_id))
+            .bind("person_id", thing_from_string(person_id))
+            .bind("post_id", thing_from_string(post_id))
+            .bind("body", body)
+            .bind("parent_id", parent_id.map(thing_from_string))
+            .bind("status", status.as_str())
+    }
+
+    /// Query: replace the body of a comment and move it to `$status` (returns Comment).
+    pub fn query_edit_comment(
+        &self,
+        comment_id: &str,
+        person_id: &str,
+        body: String,
+        status: CommentStatus,
+    ) -> NovaQuery {
+        let sql = format!(
+            r#"
+            LET $meta_id = (SELECT meta FROM ONLY commented WHERE id = $comment_id LIMIT 1).meta;
+            UPDATE $comment_id SET body = $body, status = $status, edited_on = time::now();
+            UPDATE $meta_id SET modified_by = $person_id, modified_on = time::now();
+
+            SELECT
+                {}
+            FROM ONLY commented
+            WHERE id = $comment_id
+            LIMIT 1;
+            "#,
+            self.select_comment_string
+        );
+        NovaQuery::new(sql)
+            .bind("comment_id", thing_from_string(comment_id))
+            .bind("person_id", thing_from_string(person_id))
+            .bind("body", body)
+            .bind("status", status.as_str())
+    }
+
+    /// Query: soft delete a comment through its meta record (returns nothing).
+    pub fn query_delete_comment(&self, comment_id: &str, person_id: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            LET $meta_id = (SELECT meta FROM ONLY commented WHERE id = $comment_id LIMIT 1).meta;
+            UPDATE $meta_id SET deleted_by = $person_id, deleted_on = time::now();
+            "#,
+        )
+        .bind("comment_id", thing_from_string(comment_id))
+        .bind("person_id", thing_from_string(person_id))
+    }
+
+    /// Query: move a comment to another moderation state (returns Vec<Comment>, empty when
+    /// no such comment exists).
+    pub fn query_set_comment_status(
+        &self,
+        comment_id: &str,
+        person_id: &str,
+        status: CommentStatus,
+    ) -> NovaQuery {
+        let sql = format!(
+            r#"
+            LET $meta_id = (SELECT meta FROM ONLY commented WHERE id = $comment_id LIMIT 1).meta;
+            IF $meta_id IS NOT NONE {{
+                UPDATE $comment_id SET status = $status;
+                UPDATE $meta_id SET modified_by = $person_id, modified_on = time::now();
+            }};
+
+            SELECT
+                {}
+            FROM commented
+            WHERE id = $comment_id;
+            "#,
+            self.select_comment_string
+        );
+        NovaQuery::new(sql)
+            .bind("comment_id", thing_from_string(comment_id))
+            .bind("person_id", thing_from_string(person_id))
+            .bind("status", status.as_str())
+    }
+
+    /// Query: select a page of comments in a moderation state across all posts
+    /// (returns Vec<Comment>, then the total count).
+    pub fn query_select_moderation_queue(
+        &self,
+        status: CommentStatus,
+        page: &PageArgs,
+    ) -> NovaQuery {
+        let PageSql {
+            filters,
+            after_cursor,
+            order_limit,
+        } = PageSql::new(page, "id", "at");
+
+        let sql = format!(
+            r#"
+            SELECT
+                {}
+            FROM commented
+            WHERE status = $status AND meta.deleted_on IS NONE{filters}{after_cursor}
+            {order_limit};
+
+            RETURN array::len((
+                SELECT VALUE id FROM commented
+                WHERE status = $status AND meta.deleted_on IS NONE{filters}
+            ));
+            "#,
+            self.select_comment_string
+        );
+        bind_page(NovaQuery::new(sql), page, "commented").bind("status", status.as_str())
+    }
+
+    /// Query: open or close a post for new comments (returns Vec<IdContainer>, empty when
+    /// no such post exists).
+    pub fn query_set_comments_open(&self, post_id: &str, open: bool) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            UPDATE post SET comments_open = $open WHERE id = $post_id
+            RETURN fn::string_id(id) as id;
+            "#,
+        )
+        .bind("post_id", thing_from_string(post_id))
+        .bind("open", open)
+    }
+}
@@ -0,0 +1,337 @@
+use crate::db::nova_db::NovaQuery;
+use crate::models::media::{ImageSet, MediaListArgs};
+use crate::models::page::PageArgs;
+use crate::utils::thing_from_string;
+
+use super::r_meta::MetaRepo;
+use super::r_page::{bind_page, PageSql};
+use super::r_posts::DRAFT_NOT_TRASHED;
+
+#[derive(Debug, Clone)]
+pub struct MediaRepo {
+    pub meta: MetaRepo,
+    pub select_media_string: String,
+}
+
+/// Projection selecting a [`Media`] from a `media` record, without its public URL.
+///
+/// [`Media`]: crate::models::media::Media
+pub fn select_media_string(select_meta_string: &str) -> String {
+    format!(
+        r#"
+        fn::string_id(id) as id,
+        hash,
+        key,
+        content_type,
+        size,
+        original_name,
+        alt_text,
+        caption,
+        at,
+        width,
+        height,
+        blurhash,
+        (variants OR []) as variants,
+        {select_meta_string}
+    "#
+    )
+}
+
+/// Projection selecting the [`ImageSet`] of a `drafted` record's image, when the image is
+/// live uploaded media that was processed. Matched on the last path segment of the URL,
+/// which for uploads is the media key.
+///
+/// [`ImageSet`]: crate::models::media::ImageSet
+pub const SELECT_IMAGE_SET_STRING: &str = r#"
+    (
+        SELECT
+            width,
+            height,
+            blurhash,
+            variants,
+            alt_text,
+            caption
+        FROM ONLY media
+        WHERE key = array::last(string::split($parent.image, "/"))
+            AND width IS NOT NONE
+            AND meta.deleted_on IS NONE
+        LIMIT 1
+    ) as image_set
+"#;
+
+impl Default for MediaRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MediaRepo {
+    pub fn new() -> Self {
+        let meta = MetaRepo::new();
+
+        Self {
+            select_media_string: select_media_string(&meta.select_meta_string),
+            meta,
+        }
+    }
+
+    /// Query: select the media with a content hash, including deleted media
+    /// (returns Option<Media>).
+    pub fn query_select_media_by_hash(&self, hash: &str) -> NovaQuery {
+        let sql = format!(
+            "SELECT {} FROM ONLY media WHERE hash = $hash LIMIT 1;",
+            self.select_media_string
+        );
+        NovaQuery::new(sql).bind("hash", hash.to_string())
+    }
+
+    /// Query: select live media with a content hash (returns Option<Media>).
+    pub fn query_select_live_media_by_hash(&self, hash: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT
+                {}
+            FROM ONLY media
+            WHERE hash = $hash AND meta.deleted_on IS NONE
+            LIMIT 1;
+            "#,
+            self.select_media_string
+        );
+        NovaQuery::new(sql).bind("hash", hash.to_string())
+    }
+
+    /// Query: select live media by its key, not matching image variants (returns Option<Media>).
+    pub fn query_select_media_by_key(&self, key: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT
+                {}
+            FROM ONLY media
+            WHERE key = $key AND meta.deleted_on IS NONE
+            LIMIT 1;
+            "#,
+            self.select_media_string
+        );
+        NovaQuery::new(sql).bind("key", key.to_string())
+    }
+
+    /// Query: a page of live media, newest first by default.
+    /// Multi-statement: 0=SELECT page (Vec<Media>), 1=RETURN total.
+    pub fn query_select_media_page(&self, args: &MediaListArgs, page: &PageArgs) -> NovaQuery {
+        let PageSql {
+            filters,
+            after_cursor,
+            order_limit,
+        } = PageSql::new(page, "id", "at");
+
+        let content_type = args
+            .content_type
+            .as_deref()
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty());
+        let search = args
+            .q
+            .as_deref()
+            .map(|q| q.trim().to_lowercase())
+            .filter(|q| !q.is_empty());
+
+        let mut conditions = String::from("meta.deleted_on IS NONE");
+        match &content_type {
+            Some(t) if t.contains('/') => conditions.push_str(" AND content_type = $content_type"),
+            Some(_) => {
+                conditions.push_str(" AND string::starts_with(content_type, $content_type + '/')")
+            }
+            None => {}
+        }
+        if search.is_some() {
+            conditions.push_str(
+                r#" AND (
+                    string::contains(string::lowercase(original_name OR ""), $q)
+                    OR string::contains(string::lowercase(alt_text OR ""), $q)
+                    OR string::contains(string::lowercase(caption OR ""), $q)
+                )"#,
+            );
+        }
+
+        let sql = format!(
+            r#"
+            SELECT
+                {}
+            FROM media
+            WHERE {conditions}{filters}{after_cursor}
+            {order_limit};
+
+            RETURN array::len((
+                SELECT VALUE id FROM media
+                WHERE {conditions}{filters}
+            ));
+            "#,
+            self.select_media_string
+        );
+
+        let mut q = bind_page(NovaQuery::new(sql), page, "media");
+        if let Some(content_type) = content_type {
+            q = q.bind("content_type", content_type);
+        }
+        if let Some(search) = search {
+            q = q.bind("q", search);
+        }
+        q
+    }
+
+    /// Query: drafts using media, by its content hash in their image URL or markdown, which
+    /// also finds image variants (returns Vec<MediaReference>).
+    pub fn query_select_media_references(&self, hash: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT
+                fn::string_id(id) as draft_id,
+                fn::string_id(out) as post_id,
+                title,
+                (published = true) as published,
+                at,
+                string::contains(image OR "", $hash) as in_image,
+                string::contains(markdown OR "", $hash) as in_markdown
+            FROM drafted
+            WHERE {DRAFT_NOT_TRASHED}
+                AND (
+                    string::contains(image OR "", $hash)
+                    OR string::contains(markdown OR "", $hash)
+                )
+            ORDER BY id DESC;
+            "#
+        );
+        NovaQuery::new(sql).bind("hash", hash.to_string())
+    }
+
+    /// Query: replace the alt text and caption of media.
+    /// Multi-statement: 0=LET $meta_id, 1=UPDATE media, 2=UPDATE meta, 3=SELECT Media.
+    pub fn query_update_media_details(
+        &self,
+        media_id: &str,
+        person_id: &str,
+        alt_text: Option<String>,
+        caption: Option<String>,
+    ) -> NovaQuery {
+        let sql = format!(
+            r#"
+            LET $meta_id = (SELECT meta FROM ONLY media WHERE id = $media_id LIMIT 1).meta;
+            UPDATE $media_id
+                SET
+                    alt_text = $alt_text,
+                    caption = $caption;
+            UPDATE $meta_id
+                SET
+                    modified_by = $person_id,
+                    modified_on = time::now();
+
+            SELECT
+                {}
+            FROM ONLY media
+            WHERE id = $media_id
+            LIMIT 1;
+            "#,
+            self.select_media_string
+        );
+        NovaQuery::new(sql)
+            .bind("media_id", thing_from_string(media_id))
+            .bind("person_id", thing_from_string(person_id))
+            .bind("alt_text", alt_text)
+            .bind("caption", caption)
+    }
+
+    /// Query: move media to the trash, keeping its files so an upload of the same content
+    /// can bring it back (returns nothing).
+    pub fn query_delete_media(&self, media_id: &str, person_id: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            LET $meta_id = (SELECT meta FROM ONLY media WHERE id = $media_id LIMIT 1).meta;
+            UPDATE $meta_id
+                SET
+                    deleted_by = $person_id,
+                    deleted_on = time::now();
+            "#,
+        )
+        .bind("media_id", thing_from_string(media_id))
+        .bind("person_id", thing_from_string(person_id))
+    }
+
+    /// Query: record an uploaded file (run in a transaction, returns Media).
+    ///
+    /// `image` is set for processed images. The file itself is described by `$created_by`,
+    /// `$hash`, `$key`, `$content_type`, `$size` and `$original_name`, bound by the caller.
+    pub fn query_create_media(&self, image: Option<&ImageSet>) -> NovaQuery {
+        let variants = image.map(|i| &i.variants[..]).unwrap_or_default();
+
+        // Each variant is bound field by field and put back together as an object.
+        let variant_objects = (0..variants.len())
+            .map(|i| {
+                format!(
+                    "{{ key: $variant_key_{i}, url: $variant_url_{i}, width: $variant_width_{i}, \
+                     height: $variant_height_{i}, content_type: $variant_type_{i} }}"
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let sql = format!(
+            r#"
+            {}
+            LET $media_id = media:ulid();
+
+            CREATE $media_id
+                SET
+                    hash = $hash,
+                    key = $key,
+                    content_type = $content_type,
+                    size = $size,
+                    original_name = $original_name,
+                    width = $width,
+                    height = $height,
+                    blurhash = $blurhash,
+                    variants = [{}],
+                    at = time::now(),
+                    meta = $meta_id;
+
+            SELECT
+                {}
+            FROM ONLY media
+            WHERE id = $media_id
+            LIMIT 1;
+            "#,
+            self.meta.sql_create_meta("$meta_id"),
+            variant_objects,
+            self.select_media_string
+        );
+        let mut q = NovaQuery::new(sql)
+            .bind("width", image.map(|i| i.width as i64))
+            .bind("height", image.map(|i| i.height as i64))
+            .bind("blurhash", image.and_then(|i| i.blurhash.clone()));
+        for (i, variant) in variants.iter().enumerate() {
+            q = q
+                .bind(&format!("variant_key_{i}"), variant.key.clone())
+                .bind(&format!("variant_url_{i}"), variant.url.clone())
+                .bind(&format!("variant_width_{i}"), variant.width as i64)
+                .bind(&format!("variant_height_{i}"), variant.height as i64)
+                .bind(&format!("variant_type_{i}"), variant.content_type.clone());
+        }
+        q
+    }
+
+    /// Query: bring back deleted media when its content is uploaded again (returns nothing).
+    pub fn query_restore_media(&self, media_id: &str, person_id: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            LET $meta_id = (SELECT meta FROM ONLY media WHERE id = $media_id LIMIT 1).meta;
+            UPDATE $meta_id
+                SET
+                    deleted_by = NONE,
+                    deleted_on = NONE,
+                    modified_by = $person_id,
+                    modified_on = time::now();
+            "#,
+        )
+        .bind("media_id", thing_from_string(media_id))
+        .bind("person_id", thing_from_string(person_id))
+    }
+}
@@ -27,6 +27,12 @@ pub fn select_meta_string() -> String {
     .to_string()
 }
 
+impl Default for MetaRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MetaRepo {
     pub fn new() -> Self {
         Self {
@@ -38,13 +44,19 @@ impl MetaRepo {
     ///
     /// Requires `$created_by` to be bound in the surrounding query.
     pub fn sql_create_meta(&self, meta_var: &str) -> String {
+        self.sql_create_meta_on(meta_var, "time::now()")
+    }
+
+    /// SQL snippet: like [`MetaRepo::sql_create_meta`], with `created_on` set to the
+    /// datetime expression `created_on` instead of now.
+    pub fn sql_create_meta_on(&self, meta_var: &str, created_on: &str) -> String {
         format!(
             r#"
             LET {meta_var} = meta:ulid();
             CREATE {meta_var}
             SET
                 created_by = $created_by,
-                created_on = time::now(),
+                created_on = {created_on},
                 modified_by = NONE,
                 modified_on = NONE,
                 deleted_by = NONE,
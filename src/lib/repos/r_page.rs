@@ -0,0 +1,62 @@
+use crate::db::nova_db::NovaQuery;
+use crate::models::page::{PageArgs, SortOrder};
+
+/// SQL clauses shared by every cursor-paginated listing.
+#[derive(Debug)]
+pub struct PageSql {
+    /// Date range conditions, each prefixed with `AND`. Shared by a page and its count.
+    pub filters: String,
+    /// Cursor condition prefixed with `AND`, empty on the first page.
+    pub after_cursor: String,
+    /// `ORDER BY .. LIMIT ..` fetching one extra row so we can tell if another page follows.
+    pub order_limit: String,
+}
+
+impl PageSql {
+    /// Builds the clauses for a listing ordered by the ULID record ids in `order_field`
+    /// and filtered on the datetime in `date_field`.
+    ///
+    /// Requires [`bind_page`] to be called on the surrounding query.
+    pub fn new(page: &PageArgs, order_field: &str, date_field: &str) -> Self {
+        let mut filters = String::new();
+        if page.from.is_some() {
+            filters.push_str(&format!(" AND {date_field} >= <datetime>$from"));
+        }
+        if page.to.is_some() {
+            filters.push_str(&format!(" AND {date_field} <= <datetime>$to"));
+        }
+
+        let (cmp, dir) = match page.sort {
+            SortOrder::Newest => ("<", "DESC"),
+            SortOrder::Oldest => (">", "ASC"),
+        };
+
+        let after_cursor = if page.cursor_ulid().is_some() {
+            format!(" AND {order_field} {cmp} $cursor")
+        } else {
+            String::new()
+        };
+
+        Self {
+            filters,
+            after_cursor,
+            order_limit: format!("ORDER BY {order_field} {dir} LIMIT {}", page.limit() + 1),
+        }
+    }
+}
+
+/// Binds `$cursor`, `$from` and `$to` for the clauses built by [`PageSql::new`].
+///
+/// `cursor_table` is the table of the record ids the listing is ordered by.
+pub fn bind_page(mut q: NovaQuery, page: &PageArgs, cursor_table: &str) -> NovaQuery {
+    if let Some(cursor) = page.cursor_record(cursor_table) {
+        q = q.bind("cursor", cursor);
+    }
+    if let Some(from) = page.from_rfc3339() {
+        q = q.bind("from", from);
+    }
+    if let Some(to) = page.to_rfc3339() {
+        q = q.bind("to", to);
+    }
+    q
+}
@@ -4,17 +4,55 @@ use surrealdb::types::RecordId;
 use time::OffsetDateTime;
 
 use crate::db::nova_db::NovaQuery;
-use crate::models::person::SignUpState;
+use crate::models::page::PageArgs;
+use crate::models::person::{SignUpState, UpdateProfileArgs};
 use crate::models::token::{Token, TokenRecord};
 use crate::utils::thing_from_string;
 
 use super::r_meta::MetaRepo;
+use super::r_page::{bind_page, PageSql};
+use super::r_posts::DRAFT_NOT_TRASHED;
 
 #[derive(Debug, Clone)]
 pub struct PersonsRepo {
     meta: MetaRepo,
 }
 
+/// Projection selecting an [`Author`] from a `person` record. Leaves out the email,
+/// password hash and admin status.
+///
+/// [`Author`]: crate::models::person::Author
+pub const SELECT_AUTHOR_STRING: &str = r#"
+    fn::string_id(id) as id,
+    username,
+    display_name,
+    bio,
+    avatar_url,
+    (links OR []) as links
+"#;
+
+/// Projection embedding an [`AuthorSummary`] of the `in` person on a `drafted` record.
+///
+/// [`AuthorSummary`]: crate::models::person::AuthorSummary
+pub const SELECT_AUTHOR_SUMMARY_STRING: &str = r#"
+    (
+        SELECT
+            fn::string_id(id) as id,
+            username,
+            display_name,
+            avatar_url
+        FROM ONLY person
+        WHERE id = $parent.in
+        LIMIT 1
+    ) as author_summary
+"#;
+
+impl Default for PersonsRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PersonsRepo {
     pub fn new() -> Self {
         Self {
@@ -78,6 +116,10 @@ impl PersonsRepo {
                 username,
                 email,
                 is_admin,
+                display_name,
+                bio,
+                avatar_url,
+                (links OR []) as links,
                 {}
             FROM ONLY person
             WHERE email = $email
@@ -88,29 +130,95 @@ impl PersonsRepo {
         NovaQuery::new(sql).bind("email", email)
     }
 
+    /// Query: select the public profile of a person who has published a post that is
+    /// still live (returns Option<Author>).
+    pub fn query_select_author(&self, person_id: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT {SELECT_AUTHOR_STRING}
+            FROM ONLY person
+            WHERE id = $id
+                AND count(
+                    SELECT id FROM drafted
+                    WHERE in = $id AND published = true AND {DRAFT_NOT_TRASHED}
+                ) > 0
+            LIMIT 1;
+            "#
+        );
+        NovaQuery::new(sql).bind("id", thing_from_string(person_id))
+    }
+
+    /// Query: replace the profile fields of a person (returns Option<Person>).
+    pub fn query_update_profile(&self, person_id: &str, profile: UpdateProfileArgs) -> NovaQuery {
+        // links are bound field by field so each one is stored as a plain object
+        let links = (0..profile.links.len())
+            .map(|i| format!("{{ label: $link_label_{i}, url: $link_url_{i} }}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let sql = format!(
+            r#"
+            UPDATE $id
+            SET
+                display_name = $display_name,
+                bio = $bio,
+                avatar_url = $avatar_url,
+                links = [{links}];
+
+            SELECT fn::string_id(id) as id, *, {}
+            FROM ONLY person
+            WHERE id = $id
+            LIMIT 1;
+            "#,
+            self.meta.select_meta_string
+        );
+
+        let mut q = NovaQuery::new(sql)
+            .bind("id", thing_from_string(person_id))
+            .bind("display_name", profile.display_name)
+            .bind("bio", profile.bio)
+            .bind("avatar_url", profile.avatar_url);
+        for (i, link) in profile.links.into_iter().enumerate() {
+            q = q
+                .bind(&format!("link_label_{i}"), link.label)
+                .bind(&format!("link_url_{i}"), link.url);
+        }
+        q
+    }
+
     /// Query: select person pass_hash by email (returns row with pass_hash field).
     pub fn query_select_person_hash_by_email(&self, email: &str) -> NovaQuery {
         NovaQuery::new("SELECT pass_hash FROM ONLY person WHERE email = $email LIMIT 1;")
             .bind("email", email)
     }
 
-    /// Query: select all persons (returns Vec<Person>).
-    pub fn query_select_persons(&self) -> NovaQuery {
+    /// Query: select a page of persons (returns Vec<Person>, then the total count).
+    pub fn query_select_persons(&self, page: &PageArgs) -> NovaQuery {
+        let PageSql {
+            filters,
+            after_cursor,
+            order_limit,
+        } = PageSql::new(page, "id", "meta.created_on");
+
         let sql = format!(
-            "SELECT fn::string_id(id) as id, *, {} FROM person;",
+            r#"
+            SELECT fn::string_id(id) as id, *, {}
+            FROM person
+            WHERE true{filters}{after_cursor}
+            {order_limit};
+
+            RETURN array::len((SELECT VALUE id FROM person WHERE true{filters}));
+            "#,
             self.meta.select_meta_string
         );
-        NovaQuery::new(sql)
+        bind_page(NovaQuery::new(sql), page, "person")
     }
 
     /// Query: create a new person + meta (run in a transaction).
     /// Multi-statement: creates meta, creates person, returns Person.
+    ///
+    /// A person created without `pass_hash` has no password and can't log in.
     pub fn query_insert_person(&self, new_person: SignUpState, created_by: &str) -> NovaQuery {
-        let pass_hash = new_person
-            .pass_hash
-            .clone()
-            .expect("Can't create user without pass_hash");
-
         let sql = format!(
             r#"
             {}
@@ -140,7 +248,7 @@ impl PersonsRepo {
             .bind("created_by", thing_from_string(created_by))
             .bind("email", new_person.email)
             .bind("username", new_person.username)
-            .bind("pass_hash", pass_hash)
+            .bind("pass_hash", new_person.pass_hash)
     }
 
     /// Query: select token record by id (returns Token).
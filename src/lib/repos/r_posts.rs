@@ -1,13 +1,108 @@
 use serde::Serialize;
 
 use crate::db::nova_db::NovaQuery;
-use crate::utils::thing_from_string;
+use crate::models::locale::LocaleChoice;
+use crate::models::page::PageArgs;
+use crate::utils::{thing_from_string, HIGHLIGHT_END, HIGHLIGHT_START};
 
+use super::r_media::SELECT_IMAGE_SET_STRING;
 use super::r_meta::MetaRepo;
+use super::r_page::{bind_page, PageSql};
+use super::r_persons::SELECT_AUTHOR_SUMMARY_STRING;
+
+/// Condition excluding drafts trashed on their own or along with their post.
+///
+/// Drafts share their post's meta record, so trashing a post trashes all of its drafts.
+pub const DRAFT_NOT_TRASHED: &str = "meta.deleted_on IS NONE AND deleted_on IS NONE";
+
+/// Condition excluding the working copy, leaving only checkpointed versions.
+///
+/// Drafts saved before working copies existed have no `working` field and count as checkpoints.
+const DRAFT_CHECKPOINT: &str = "working != true";
+
+/// Condition excluding trashed posts.
+const POST_NOT_TRASHED: &str = "meta.deleted_on IS NONE";
+
+/// Statement picking the translation each published post is read in: the one in `$lang`,
+/// else in `$fallback_lang`, else the first by language code, as
+/// [`LocaleChoice::choose`] does. Sets `$published_in_lang` to `[post, lang]` pairs.
+///
+/// Must come first in queries using [`PUBLISHED_IN_LANG`], so it runs once per query
+/// instead of per row. Requires [`bind_locale`] to be called on the surrounding query.
+const LET_PUBLISHED_IN_LANG: &str = r#"
+    LET $published_in_lang = (
+        SELECT out, array::group(lang) AS langs
+        FROM drafted
+        WHERE published = true
+            AND meta.deleted_on IS NONE
+            AND deleted_on IS NONE
+        GROUP BY out
+    ).map(|$post| [
+        $post.out,
+        IF $lang IN $post.langs THEN $lang
+        ELSE IF $fallback_lang IN $post.langs THEN $fallback_lang
+        ELSE array::sort($post.langs)[0]
+        END
+    ]);
+"#;
+
+/// Condition keeping one published draft per post, in the language picked by
+/// [`LET_PUBLISHED_IN_LANG`].
+const PUBLISHED_IN_LANG: &str = r#"published = true
+    AND meta.deleted_on IS NONE
+    AND deleted_on IS NONE
+    AND [out, lang] IN $published_in_lang"#;
+
+/// Projection listing the published translations of a `drafted` record's post.
+const SELECT_TRANSLATIONS_STRING: &str = r#"
+    (
+        SELECT
+            lang,
+            fn::string_id(id) as draft_id,
+            title
+        FROM drafted
+        WHERE out = $parent.out
+            AND published = true
+            AND meta.deleted_on IS NONE
+            AND deleted_on IS NONE
+        ORDER BY lang
+    ) as translations
+"#;
+
+/// `SET` fields of a draft imported from outside the blog. Always a checkpoint, dated `$at`
+/// rather than now.
+const SET_IMPORTED_DRAFT: &str = r#"
+    id = $drafted_id,
+    lang = $lang,
+    title = $title,
+    markdown = $markdown,
+    published = $published,
+    at = <datetime>$at,
+    image = $image,
+    tags = $tags,
+    excerpt = $excerpt,
+    cover_alt = $cover_alt,
+    seo_title = $seo_title,
+    meta_description = $meta_description,
+    canonical_url = $canonical_url,
+    publish_at = (IF !type::is_none($publish_at) THEN <datetime>$publish_at END),
+    published_on = (IF $published THEN <datetime>$created_on END),
+    visits = 0,
+    working = false,
+    meta = $meta_id
+"#;
+
+/// Binds `$lang` and `$fallback_lang` for [`LET_PUBLISHED_IN_LANG`].
+pub fn bind_locale(q: NovaQuery, locale: &LocaleChoice) -> NovaQuery {
+    q.bind("lang", locale.lang.clone())
+        .bind("fallback_lang", locale.fallback.clone())
+}
 
 #[derive(Debug, Clone)]
 pub struct PostsRepo {
     pub meta: MetaRepo,
+    pub select_version_string: String,
+    pub select_summary_string: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -18,10 +113,87 @@ pub struct DraftedArgs {
     pub image: String,
 }
 
+/// Projection selecting a [`PostVersion`] from a `drafted` record.
+///
+/// [`PostVersion`]: crate::models::post::PostVersion
+pub fn select_version_string(select_meta_string: &str) -> String {
+    format!(
+        r#"
+        fn::string_id(out) as id,
+        fn::string_id(id) as draft_id,
+        title,
+        markdown,
+        at,
+        fn::string_id(in) as author,
+        {SELECT_AUTHOR_SUMMARY_STRING},
+        lang,
+        {SELECT_TRANSLATIONS_STRING},
+        published,
+        image,
+        {SELECT_IMAGE_SET_STRING},
+        visits,
+        (tags OR []) as tags,
+        (out.categories OR []) as categories,
+        excerpt,
+        cover_alt,
+        seo_title,
+        meta_description,
+        canonical_url,
+        publish_at,
+        unpublish_at,
+        published_on,
+        (IF !type::is_none(restored_from) THEN fn::string_id(restored_from) END) as restored_from,
+        deleted_on,
+        (working = true) as working,
+        {select_meta_string}
+    "#
+    )
+}
+
+/// Projection selecting a [`PostSummary`] from a `drafted` record. The markdown is only
+/// selected for [`PostSummary::summarize`].
+///
+/// [`PostSummary`]: crate::models::post::PostSummary
+/// [`PostSummary::summarize`]: crate::models::post::PostSummary::summarize
+pub fn select_summary_string(select_meta_string: &str) -> String {
+    format!(
+        r#"
+        fn::string_id(out) as id,
+        fn::string_id(id) as draft_id,
+        title,
+        markdown,
+        at,
+        fn::string_id(in) as author,
+        {SELECT_AUTHOR_SUMMARY_STRING},
+        lang,
+        {SELECT_TRANSLATIONS_STRING},
+        published,
+        image,
+        {SELECT_IMAGE_SET_STRING},
+        visits,
+        (tags OR []) as tags,
+        (out.categories OR []) as categories,
+        excerpt,
+        cover_alt,
+        {select_meta_string}
+    "#
+    )
+}
+
+impl Default for PostsRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PostsRepo {
     pub fn new() -> Self {
+        let meta = MetaRepo::new();
+
         Self {
-            meta: MetaRepo::new(),
+            select_version_string: select_version_string(&meta.select_meta_string),
+            select_summary_string: select_summary_string(&meta.select_meta_string),
+            meta,
         }
     }
 
@@ -58,8 +230,14 @@ impl PostsRepo {
         NovaQuery::new(sql).bind("post_id", thing_from_string(post_id))
     }
 
-    /// Query: select all posts (returns Vec<PostHydrated>).
-    pub fn query_select_posts(&self) -> NovaQuery {
+    /// Query: select a page of posts (returns Vec<PostHydrated>, then the total count).
+    pub fn query_select_posts(&self, page: &PageArgs) -> NovaQuery {
+        let PageSql {
+            filters,
+            after_cursor,
+            order_limit,
+        } = PageSql::new(page, "id", "meta.created_on");
+
         let sql = format!(
             r#"
             SELECT
@@ -68,19 +246,21 @@ impl PostsRepo {
                     (
                         SELECT at, title
                         FROM drafted
-                        WHERE out = $parent.id
+                        WHERE out = $parent.id AND {DRAFT_NOT_TRASHED}
                         ORDER BY at DESC
                         LIMIT 1
                     ).title
                 ) as working_title,
-                meta.created_on,
                 {}
             FROM post
-            ORDER BY meta.created_on DESC;
+            WHERE {POST_NOT_TRASHED}{filters}{after_cursor}
+            {order_limit};
+
+            RETURN array::len((SELECT VALUE id FROM post WHERE {POST_NOT_TRASHED}{filters}));
             "#,
             self.meta.select_meta_string
         );
-        NovaQuery::new(sql)
+        bind_page(NovaQuery::new(sql), page, "post")
     }
 
     /// Query: select draft by draft_id (returns PostVersion).
@@ -88,22 +268,13 @@ impl PostsRepo {
         let sql = format!(
             r#"
             SELECT
-                fn::string_id(out) as id,
-                fn::string_id(id) as draft_id,
-                title,
-                markdown,
-                at,
-                fn::string_id(in) as author,
-                published,
-                image,
-                visits,
                 {}
             FROM drafted
-            WHERE id = $draft_id
+            WHERE id = $draft_id AND {DRAFT_NOT_TRASHED}
             ORDER BY at DESC
             LIMIT 1;
             "#,
-            self.meta.select_meta_string
+            self.select_version_string
         );
         NovaQuery::new(sql).bind("draft_id", thing_from_string(draft_id))
     }
@@ -113,114 +284,431 @@ impl PostsRepo {
         let sql = format!(
             r#"
             SELECT
-                fn::string_id(out) as id,
-                fn::string_id(id) as draft_id,
-                title,
-                markdown,
-                at,
-                fn::string_id(in) as author,
-                published,
-                image,
-                visits,
                 {}
             FROM drafted
-            WHERE out = $post_id
+            WHERE out = $post_id AND {DRAFT_CHECKPOINT} AND {DRAFT_NOT_TRASHED}
             ORDER BY at DESC;
             "#,
-            self.meta.select_meta_string
+            self.select_version_string
         );
         NovaQuery::new(sql).bind("post_id", thing_from_string(post_id))
     }
 
-    /// Query: create a draft version for an existing post (returns PostVersion).
+    /// Query: select the published draft of a post in a language (returns Option<PostVersion>).
+    pub fn query_select_published_draft(&self, post_id: &str, lang: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT
+                {}
+            FROM ONLY drafted
+            WHERE out = $post_id
+                AND lang = $lang
+                AND published = true
+                AND {DRAFT_NOT_TRASHED}
+            LIMIT 1;
+            "#,
+            self.select_version_string
+        );
+        NovaQuery::new(sql)
+            .bind("post_id", thing_from_string(post_id))
+            .bind("lang", lang.to_string())
+    }
+
+    /// Query: select the published draft of a post in every language it was published in
+    /// (returns Vec<PostVersion>).
+    pub fn query_select_published_translations(&self, post_id: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT
+                {}
+            FROM drafted
+            WHERE out = $post_id
+                AND published = true
+                AND {DRAFT_NOT_TRASHED};
+            "#,
+            self.select_version_string
+        );
+        NovaQuery::new(sql).bind("post_id", thing_from_string(post_id))
+    }
+
+    /// Query: select the newest draft of a post in a language, published or not
+    /// (returns Option<PostVersion>).
+    pub fn query_select_latest_draft(&self, post_id: &str, lang: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT
+                {}
+            FROM ONLY drafted
+            WHERE out = $post_id AND lang = $lang AND {DRAFT_NOT_TRASHED}
+            ORDER BY id DESC
+            LIMIT 1;
+            "#,
+            self.select_version_string
+        );
+        NovaQuery::new(sql)
+            .bind("post_id", thing_from_string(post_id))
+            .bind("lang", lang.to_string())
+    }
+
+    /// Query: save a draft for an existing post (run in a transaction, returns
+    /// PostVersion, then whether the write was rejected as a conflict).
+    ///
+    /// Uses the post's existing meta id so draft.meta matches post.meta. Working copies
+    /// belong to one author: when `$person_id` has one it is updated in place, and stays the
+    /// working copy when `$working` is true or is frozen into a checkpoint otherwise.
+    /// Without one a new draft is related, so another author's working copy is never
+    /// written to.
     ///
-    /// Uses the post's existing meta id so draft.meta matches post.meta.
+    /// When `$base_draft_id` is set and a newer draft exists, nothing is written unless
+    /// `$force` is true, and the newer draft is returned instead. The working copy records
+    /// the base it was started from, and counts as that base. A published draft
+    /// replaces the live one in its language, as [`PostsRepo::sql_publish_draft`] does.
     pub fn query_create_draft(&self) -> NovaQuery {
         let sql = format!(
             r#"
-            LET $drafted_id = drafted:ulid();
             LET $meta_id = (SELECT meta FROM ONLY post WHERE id = $post_id LIMIT 1).meta;
             IF $meta_id IS NONE {{ THROW "Post not found: " + fn::string_id($post_id) }};
 
+            LET $working_id = array::first(
+                SELECT VALUE id FROM drafted
+                WHERE out = $post_id
+                    AND lang = $lang
+                    AND in = $person_id
+                    AND working = true
+                    AND {DRAFT_NOT_TRASHED}
+                ORDER BY id DESC
+                LIMIT 1
+            );
+            LET $drafted_id = (IF $working_id IS NONE THEN drafted:ulid() ELSE $working_id END);
+
+            LET $latest_id = array::first(
+                SELECT VALUE id FROM drafted
+                WHERE out = $post_id AND lang = $lang AND {DRAFT_NOT_TRASHED}
+                ORDER BY id DESC
+                LIMIT 1
+            );
+            LET $own_working = $working_id IS NOT NONE
+                AND $latest_id = $working_id
+                AND $working_id.based_on = $base_draft_id;
+            LET $conflict = !$force
+                AND $base_draft_id IS NOT NONE
+                AND $latest_id IS NOT NONE
+                AND $latest_id != $base_draft_id
+                AND !$own_working;
+
+            IF !$conflict {{
+                IF $working_id IS NONE {{
+                    RELATE $person_id->drafted->$post_id
+                        SET
+                            id = $drafted_id,
+                            lang = $lang,
+                            title = $title,
+                            markdown = $markdown,
+                            published = $published,
+                            at = time::now(),
+                            image = $image,
+                            tags = $tags,
+                            excerpt = $excerpt,
+                            cover_alt = $cover_alt,
+                            seo_title = $seo_title,
+                            meta_description = $meta_description,
+                            canonical_url = $canonical_url,
+                            visits = 0,
+                            publish_at = (IF !type::is_none($publish_at) THEN <datetime>$publish_at END),
+                            unpublish_at = (IF !type::is_none($unpublish_at) THEN <datetime>$unpublish_at END),
+                            working = $working,
+                            based_on = $base_draft_id,
+                            meta = $meta_id;
+                }} ELSE {{
+                    UPDATE $working_id
+                        SET
+                            title = $title,
+                            markdown = $markdown,
+                            published = $published,
+                            at = time::now(),
+                            image = $image,
+                            tags = $tags,
+                            excerpt = $excerpt,
+                            cover_alt = $cover_alt,
+                            seo_title = $seo_title,
+                            meta_description = $meta_description,
+                            canonical_url = $canonical_url,
+                            publish_at = (IF !type::is_none($publish_at) THEN <datetime>$publish_at END),
+                            unpublish_at = (IF !type::is_none($unpublish_at) THEN <datetime>$unpublish_at END),
+                            working = $working;
+                }};
+            }};
+
+            IF !$conflict AND $published {{
+                LET $draft_id = $drafted_id;
+                {}
+            }};
+
+            SELECT
+                {}
+            FROM ONLY drafted
+            WHERE id = (IF $conflict THEN $latest_id ELSE $drafted_id END)
+            LIMIT 1;
+
+            RETURN $conflict;
+            "#,
+            self.sql_publish_draft(),
+            self.select_version_string
+        );
+        NovaQuery::new(sql)
+    }
+
+    /// Query: freeze `$person_id`'s working copy of a post's translation into a checkpoint
+    /// (returns Vec<PostVersion>, empty when there is no working copy).
+    pub fn query_checkpoint_working_draft(
+        &self,
+        post_id: &str,
+        lang: &str,
+        person_id: &str,
+    ) -> NovaQuery {
+        let sql = format!(
+            r#"
+            LET $working_id = array::first(
+                SELECT VALUE id FROM drafted
+                WHERE out = $post_id
+                    AND lang = $lang
+                    AND in = $person_id
+                    AND working = true
+                    AND {DRAFT_NOT_TRASHED}
+                ORDER BY id DESC
+                LIMIT 1
+            );
+            IF $working_id IS NOT NONE {{ UPDATE $working_id SET working = false }};
+
+            SELECT
+                {}
+            FROM drafted
+            WHERE id = $working_id;
+            "#,
+            self.select_version_string
+        );
+        NovaQuery::new(sql)
+            .bind("post_id", thing_from_string(post_id))
+            .bind("lang", lang.to_string())
+            .bind("person_id", thing_from_string(person_id))
+    }
+
+    /// Query: create a new draft copying an earlier one, authored by `$person_id`
+    /// (run in a transaction, returns PostVersion).
+    ///
+    /// The new draft records the version it was restored from, and is published in the
+    /// same transaction when `$publish` is true. A working copy `$person_id` has of the same
+    /// translation is frozen into a checkpoint first, so later autosaves don't write over
+    /// the restored version.
+    pub fn query_revert_draft(&self, source_id: &str, person_id: &str, publish: bool) -> NovaQuery {
+        let sql = format!(
+            r#"
+            LET $source = (SELECT * FROM ONLY drafted WHERE id = $source_id LIMIT 1);
+            IF $source IS NONE {{ THROW "Draft not found: " + fn::string_id($source_id) }};
+
+            LET $post_id = $source.out;
+            LET $drafted_id = drafted:ulid();
+
+            UPDATE drafted SET working = false
+            WHERE out = $post_id
+                AND lang = $source.lang
+                AND in = $person_id
+                AND working = true;
+
             RELATE $person_id->drafted->$post_id
                 SET
                     id = $drafted_id,
-                    title = $title,
-                    markdown = $markdown,
-                    published = $published,
+                    lang = $source.lang,
+                    title = $source.title,
+                    markdown = $source.markdown,
+                    published = false,
                     at = time::now(),
-                    image = $image,
+                    image = $source.image,
+                    tags = $source.tags OR [],
+                    excerpt = $source.excerpt,
+                    cover_alt = $source.cover_alt,
+                    seo_title = $source.seo_title,
+                    meta_description = $source.meta_description,
+                    canonical_url = $source.canonical_url,
                     visits = 0,
-                    meta = $meta_id;
+                    restored_from = $source_id,
+                    meta = $source.meta;
+
+            IF $publish {{
+                LET $draft_id = $drafted_id;
+                {}
+            }};
 
             SELECT
-                fn::string_id(id) as draft_id,
-                fn::string_id(in) as author,
-                fn::string_id(out) as id,
-                at,
-                title,
-                markdown,
-                published,
-                image,
-                visits,
                 {}
             FROM ONLY drafted
             WHERE id = $drafted_id
             LIMIT 1;
             "#,
-            self.meta.select_meta_string
+            self.sql_publish_draft(),
+            self.select_version_string
         );
         NovaQuery::new(sql)
+            .bind("source_id", thing_from_string(source_id))
+            .bind("person_id", thing_from_string(person_id))
+            .bind("publish", publish)
+    }
+
+    /// SQL snippet: unpublish every draft of `$draft_id`'s post in the same language, then
+    /// publish `$draft_id` and clear any publish time left on it. Translations are published
+    /// independently.
+    ///
+    /// A pending `unpublish_at` on the draft being replaced moves to `$draft_id`, unless it
+    /// has its own, so publishing an edit doesn't cancel or orphan the scheduled unpublish.
+    /// `published_on` moves the same way, so it stays when the translation first went live.
+    ///
+    /// Three statements. Run in a transaction with `$draft_id` bound.
+    pub fn sql_publish_draft(&self) -> &'static str {
+        r#"
+            LET $publishing = (SELECT out, lang, unpublish_at, published_on FROM ONLY drafted WHERE id = $draft_id LIMIT 1);
+            LET $replaced = (
+                UPDATE drafted SET published = false, unpublish_at = NONE
+                WHERE out = $publishing.out
+                    AND lang = $publishing.lang
+                    AND published = true
+                    AND id != $draft_id
+                RETURN BEFORE
+            );
+            UPDATE $draft_id SET
+                published = true,
+                publish_at = NONE,
+                unpublish_at = $publishing.unpublish_at OR array::first(array::compact($replaced.unpublish_at)),
+                published_on = $publishing.published_on OR array::first(array::compact($replaced.published_on)) OR time::now(),
+                working = false;
+        "#
     }
 
-    /// Query: publish a draft (returns PostVersion).
+    /// Query: publish a draft (returns true).
     pub fn query_publish_draft(&self, draft_id: &str) -> NovaQuery {
         let sql = format!(
             r#"
-            UPDATE $draft_id SET published = true;
+            {}
+            RETURN true;
+            "#,
+            self.sql_publish_draft()
+        );
+        NovaQuery::new(sql).bind("draft_id", thing_from_string(draft_id))
+    }
 
-            SELECT
-                fn::string_id(id) as draft_id,
-                fn::string_id(in) as author,
-                fn::string_id(out) as id,
-                at,
-                title,
-                markdown,
-                published,
-                image,
-                visits,
+    /// Query: publish a draft only if its `publish_at` is due, claiming the schedule so it
+    /// cannot fire twice (run in a transaction, returns whether it was published).
+    pub fn query_publish_scheduled_draft(&self, draft_id: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            LET $claimed = (
+                UPDATE drafted
+                SET publish_at = NONE
+                WHERE id = $draft_id
+                    AND publish_at IS NOT NONE
+                    AND publish_at <= time::now()
+                    AND {DRAFT_NOT_TRASHED}
+                RETURN BEFORE
+            );
+
+            IF array::len($claimed) > 0 {{
                 {}
-            FROM drafted
-            WHERE id = $draft_id
-            LIMIT 1;
+            }};
+
+            RETURN array::len($claimed) > 0;
             "#,
-            self.meta.select_meta_string
+            self.sql_publish_draft()
         );
         NovaQuery::new(sql).bind("draft_id", thing_from_string(draft_id))
     }
 
-    /// Query: unpublish a draft (returns PostVersion).
-    pub fn query_unpublish_draft(&self, draft_id: &str) -> NovaQuery {
+    /// Query: select ids of drafts whose `publish_at` is due (returns Vec<IdContainer>).
+    pub fn query_select_due_publish_ids(&self) -> NovaQuery {
+        NovaQuery::new(format!(
+            r#"
+            SELECT fn::string_id(id) as id, publish_at
+            FROM drafted
+            WHERE publish_at IS NOT NONE
+                AND publish_at <= time::now()
+                AND {DRAFT_NOT_TRASHED}
+            ORDER BY publish_at ASC;
+            "#
+        ))
+    }
+
+    /// Query: unpublish every draft whose `unpublish_at` is due and clear it in the same
+    /// statement (returns Vec<IdContainer> of the drafts that were unpublished).
+    pub fn query_unpublish_due_drafts(&self) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            UPDATE drafted
+            SET published = false, unpublish_at = NONE
+            WHERE unpublish_at IS NOT NONE AND unpublish_at <= time::now()
+            RETURN fn::string_id(id) as id;
+            "#,
+        )
+    }
+
+    /// Query: set or clear the publish and unpublish times of a draft that is not in the
+    /// trash (returns Option<PostVersion>).
+    pub fn query_schedule_draft(
+        &self,
+        draft_id: &str,
+        publish_at: Option<String>,
+        unpublish_at: Option<String>,
+    ) -> NovaQuery {
         let sql = format!(
             r#"
-            UPDATE $draft_id SET published = false;
+            UPDATE $draft_id
+            SET
+                publish_at = (IF !type::is_none($publish_at) THEN <datetime>$publish_at END),
+                unpublish_at = (IF !type::is_none($unpublish_at) THEN <datetime>$unpublish_at END)
+            WHERE {DRAFT_NOT_TRASHED};
 
             SELECT
-                fn::string_id(id) as draft_id,
-                fn::string_id(in) as author,
+                {}
+            FROM ONLY drafted
+            WHERE id = $draft_id AND {DRAFT_NOT_TRASHED}
+            LIMIT 1;
+            "#,
+            self.select_version_string
+        );
+        NovaQuery::new(sql)
+            .bind("draft_id", thing_from_string(draft_id))
+            .bind("publish_at", publish_at)
+            .bind("unpublish_at", unpublish_at)
+    }
+
+    /// Query: select drafts with a publish or unpublish time set (returns Vec<ScheduledDraft>).
+    pub fn query_select_scheduled_drafts(&self) -> NovaQuery {
+        NovaQuery::new(format!(
+            r#"
+            SELECT
                 fn::string_id(out) as id,
-                at,
+                fn::string_id(id) as draft_id,
                 title,
-                markdown,
-                published,
-                image,
-                visits,
+                publish_at,
+                unpublish_at
+            FROM drafted
+            WHERE (publish_at IS NOT NONE OR unpublish_at IS NOT NONE)
+                AND {DRAFT_NOT_TRASHED};
+            "#
+        ))
+    }
+
+    /// Query: unpublish a draft and clear its schedule, so a pending time can't act on it
+    /// or be carried forward when it is published again (returns PostVersion).
+    pub fn query_unpublish_draft(&self, draft_id: &str) -> NovaQuery {
+        let sql = format!(
+            r#"
+            UPDATE $draft_id SET published = false, publish_at = NONE, unpublish_at = NONE;
+
+            SELECT
                 {}
             FROM ONLY drafted
             WHERE id = $draft_id
             LIMIT 1;
             "#,
-            self.meta.select_meta_string
+            self.select_version_string
         );
         NovaQuery::new(sql).bind("draft_id", thing_from_string(draft_id))
     }
@@ -230,21 +718,12 @@ impl PostsRepo {
         let sql = format!(
             r#"
             SELECT
-                fn::string_id(out) as id,
-                fn::string_id(id) as draft_id,
-                title,
-                markdown,
-                at,
-                fn::string_id(in) as author,
-                published,
-                image,
-                visits,
                 {}
             FROM drafted
             WHERE published = false
             ORDER BY at DESC;
             "#,
-            self.meta.select_meta_string
+            self.select_version_string
         );
         NovaQuery::new(sql)
     }
@@ -254,49 +733,355 @@ impl PostsRepo {
         let sql = format!(
             r#"
             SELECT
-                fn::string_id(out) as id,
-                fn::string_id(id) as draft_id,
-                title,
-                markdown,
-                at,
-                fn::string_id(in) as author,
-                published,
-                image,
-                visits,
                 {}
             FROM drafted
             WHERE out = $post_id
                 AND published = false
+                AND {DRAFT_NOT_TRASHED}
             ORDER BY at DESC
             LIMIT 1;
             "#,
-            self.meta.select_meta_string
+            self.select_version_string
         );
         NovaQuery::new(sql).bind("post_id", thing_from_string(post_id))
     }
 
-    /// Query: select published posts (returns Vec<PostVersion>).
-    pub fn query_select_published_posts(&self) -> NovaQuery {
+    /// Query: select a page of published posts, one translation each
+    /// (returns Vec<PostSummary> after the `LET`, then the total count).
+    pub fn query_select_published_posts(
+        &self,
+        page: &PageArgs,
+        locale: &LocaleChoice,
+    ) -> NovaQuery {
+        let PageSql {
+            filters,
+            after_cursor,
+            order_limit,
+        } = PageSql::new(page, "id", "at");
+
+        let sql = format!(
+            r#"
+            {LET_PUBLISHED_IN_LANG}
+            SELECT
+                {}
+            FROM drafted
+            WHERE {PUBLISHED_IN_LANG}{filters}{after_cursor}
+            {order_limit};
+
+            RETURN array::len((
+                SELECT VALUE id FROM drafted WHERE {PUBLISHED_IN_LANG}{filters}
+            ));
+            "#,
+            self.select_summary_string
+        );
+        bind_locale(bind_page(NovaQuery::new(sql), page, "drafted"), locale)
+    }
+
+    /// Query: select the newest published versions, one translation each, optionally only
+    /// those tagged `$tag` (returns Vec<PostVersion> after the `LET`).
+    pub fn query_select_feed_posts(
+        &self,
+        tag: Option<&str>,
+        limit: u32,
+        locale: &LocaleChoice,
+    ) -> NovaQuery {
+        let tagged = if tag.is_some() {
+            " AND $tag IN tags"
+        } else {
+            ""
+        };
+
+        let sql = format!(
+            r#"
+            {LET_PUBLISHED_IN_LANG}
+            SELECT
+                {}
+            FROM drafted
+            WHERE {PUBLISHED_IN_LANG}{tagged}
+            ORDER BY id DESC
+            LIMIT {limit};
+            "#,
+            self.select_version_string
+        );
+        bind_locale(NovaQuery::new(sql), locale).bind("tag", tag.map(String::from))
+    }
+
+    /// Query: select every published post with when its newest published translation was
+    /// drafted (returns Vec<SitemapRow>).
+    pub fn query_select_sitemap_posts(&self) -> NovaQuery {
+        NovaQuery::new(format!(
+            r#"
+            SELECT fn::string_id(out) as id, math::max(at) as lastmod
+            FROM drafted
+            WHERE published = true AND {DRAFT_NOT_TRASHED}
+            GROUP BY id
+            ORDER BY id ASC;
+            "#
+        ))
+    }
+
+    /// Query: select every author of a published post with when they last published
+    /// (returns Vec<SitemapRow>).
+    pub fn query_select_sitemap_authors(&self) -> NovaQuery {
+        NovaQuery::new(format!(
+            r#"
+            SELECT fn::string_id(in) as id, math::max(at) as lastmod
+            FROM drafted
+            WHERE published = true AND {DRAFT_NOT_TRASHED}
+            GROUP BY id;
+            "#
+        ))
+    }
+
+    /// Query: select one published post at random (returns PostVersion after the `LET`).
+    pub fn query_select_random_published_post(&self, locale: &LocaleChoice) -> NovaQuery {
+        let sql = format!(
+            r#"
+            {LET_PUBLISHED_IN_LANG}
+            SELECT
+                {}
+            FROM drafted
+            WHERE {PUBLISHED_IN_LANG}
+            ORDER BY rand()
+            LIMIT 1;
+            "#,
+            self.select_version_string
+        );
+        bind_locale(NovaQuery::new(sql), locale)
+    }
+
+    /// Query: full-text search over published posts, ranked by BM25 score
+    /// (returns rows of PostSummary + SearchRank after the `LET`, then the total count).
+    ///
+    /// Uses the `drafted_title_search` (ref 1) and `drafted_markdown_search` (ref 2) indexes.
+    /// Matches are highlighted with [`HIGHLIGHT_START`] and [`HIGHLIGHT_END`], not markup,
+    /// so the text can be escaped before it is marked up.
+    pub fn query_search_published_posts(
+        &self,
+        terms: &str,
+        start: u32,
+        limit: u32,
+        locale: &LocaleChoice,
+    ) -> NovaQuery {
+        let sql = format!(
+            r#"
+            {LET_PUBLISHED_IN_LANG}
+            SELECT
+                {},
+                (search::score(1) ?? 0) + (search::score(2) ?? 0) AS score,
+                search::highlight($highlight_start, $highlight_end, 1) AS title_highlight,
+                search::highlight($highlight_start, $highlight_end, 2) AS markdown_highlight
+            FROM drafted
+            WHERE {PUBLISHED_IN_LANG}
+                AND (title @1@ $terms OR markdown @2@ $terms)
+            ORDER BY score DESC
+            LIMIT {}
+            START {start};
+
+            RETURN array::len((
+                SELECT VALUE id
+                FROM drafted
+                WHERE {PUBLISHED_IN_LANG}
+                    AND (title @1@ $terms OR markdown @2@ $terms)
+            ));
+            "#,
+            self.select_summary_string,
+            limit + 1
+        );
+        bind_locale(NovaQuery::new(sql), locale)
+            .bind("terms", terms)
+            .bind("highlight_start", HIGHLIGHT_START.to_string())
+            .bind("highlight_end", HIGHLIGHT_END.to_string())
+    }
+
+    /// Query: move a post and all of its drafts to the trash by soft-deleting the post's
+    /// meta record (returns true).
+    pub fn query_trash_post(&self, post_id: &str, person_id: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            LET $meta_id = (SELECT meta FROM ONLY post WHERE id = $post_id LIMIT 1).meta;
+            UPDATE $meta_id SET deleted_on = time::now(), deleted_by = $person_id;
+            RETURN $meta_id IS NOT NONE;
+            "#,
+        )
+        .bind("post_id", thing_from_string(post_id))
+        .bind("person_id", thing_from_string(person_id))
+    }
+
+    /// Query: restore a trashed post and its drafts (returns true).
+    pub fn query_restore_post(&self, post_id: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            LET $meta_id = (SELECT meta FROM ONLY post WHERE id = $post_id LIMIT 1).meta;
+            UPDATE $meta_id SET deleted_on = NONE, deleted_by = NONE;
+            RETURN $meta_id IS NOT NONE;
+            "#,
+        )
+        .bind("post_id", thing_from_string(post_id))
+    }
+
+    /// Query: move a single draft to the trash (returns Vec<IdContainer> of the trashed draft).
+    ///
+    /// Drafts share their post's meta record, so a draft is trashed with its own fields.
+    pub fn query_trash_draft(&self, draft_id: &str, person_id: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            UPDATE drafted
+            SET deleted_on = time::now(), deleted_by = $person_id
+            WHERE id = $draft_id
+            RETURN fn::string_id(id) as id;
+            "#,
+        )
+        .bind("draft_id", thing_from_string(draft_id))
+        .bind("person_id", thing_from_string(person_id))
+    }
+
+    /// Query: restore a single trashed draft (returns Vec<IdContainer> of the restored draft).
+    pub fn query_restore_draft(&self, draft_id: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            UPDATE drafted
+            SET deleted_on = NONE, deleted_by = NONE
+            WHERE id = $draft_id
+            RETURN fn::string_id(id) as id;
+            "#,
+        )
+        .bind("draft_id", thing_from_string(draft_id))
+    }
+
+    /// Query: select trashed posts, then drafts trashed on their own
+    /// (returns Vec<PostHydrated>, then Vec<PostVersion>).
+    pub fn query_select_trash(&self) -> NovaQuery {
+        let sql = format!(
+            r#"
+            SELECT
+                fn::string_id(id) as id,
+                array::first(
+                    (
+                        SELECT at, title
+                        FROM drafted
+                        WHERE out = $parent.id
+                        ORDER BY at DESC
+                        LIMIT 1
+                    ).title
+                ) as working_title,
+                {}
+            FROM post
+            WHERE meta.deleted_on IS NOT NONE
+            ORDER BY meta.deleted_on DESC;
+
+            SELECT
+                {}
+            FROM drafted
+            WHERE deleted_on IS NOT NONE AND meta.deleted_on IS NONE
+            ORDER BY deleted_on DESC;
+            "#,
+            self.meta.select_meta_string, self.select_version_string
+        );
+        NovaQuery::new(sql)
+    }
+
+    /// Query: permanently delete posts and drafts trashed before `cutoff`
+    /// (run in a transaction, returns the number of posts, then the number of drafts).
+    ///
+    /// Purging a post also removes all of its drafts, comments, reactions and views, its
+    /// meta record, and its place in other posts' related posts.
+    pub fn query_purge_trash(&self, cutoff: String) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            LET $posts = SELECT VALUE id FROM post
+                WHERE meta.deleted_on IS NOT NONE AND meta.deleted_on < <datetime>$cutoff;
+            LET $metas = SELECT VALUE meta FROM post WHERE id IN $posts;
+            LET $drafts = SELECT VALUE id FROM drafted
+                WHERE out IN $posts
+                    OR (deleted_on IS NOT NONE AND deleted_on < <datetime>$cutoff);
+            LET $comment_metas = SELECT VALUE meta FROM commented WHERE out IN $posts;
+            LET $post_keys = SELECT VALUE fn::string_id(id) FROM post WHERE id IN $posts;
+
+            DELETE commented WHERE out IN $posts;
+            DELETE reacted WHERE out IN $posts;
+            DELETE post_view WHERE post IN $posts;
+            DELETE post_visitor WHERE post IN $posts;
+            DELETE drafted WHERE id IN $drafts;
+            DELETE post WHERE id IN $posts;
+            DELETE meta WHERE id IN $metas OR id IN $comment_metas;
+            UPDATE post SET related = array::complement(related, $post_keys)
+                WHERE related IS NOT NONE
+                    AND array::len(array::intersect(related, $post_keys)) > 0;
+
+            RETURN array::len($posts);
+            RETURN array::len($drafts);
+            "#,
+        )
+        .bind("cutoff", cutoff)
+    }
+
+    /// Query: select the id, title and markdown of every published post, one translation
+    /// each, after the `LET` (returns Vec<RelatedSource>).
+    pub fn query_select_related_sources(&self, locale: &LocaleChoice) -> NovaQuery {
         let sql = format!(
             r#"
+            {LET_PUBLISHED_IN_LANG}
             SELECT
                 fn::string_id(out) as id,
-                fn::string_id(id) as draft_id,
                 title,
                 markdown,
-                at,
-                fn::string_id(in) as author,
-                published,
-                image,
-                visits,
+                at
+            FROM drafted
+            WHERE {PUBLISHED_IN_LANG};
+            "#
+        );
+        bind_locale(NovaQuery::new(sql), locale)
+    }
+
+    /// Query: store the ranked related post ids of each post in `related`.
+    ///
+    /// Posts missing from `related` keep their previous list, which is filtered down to
+    /// published posts when read.
+    pub fn query_set_related(&self, related: Vec<(String, Vec<String>)>) -> NovaQuery {
+        let (post_ids, related_ids): (Vec<_>, Vec<_>) = related
+            .into_iter()
+            .map(|(post_id, related_ids)| (thing_from_string(&post_id), related_ids))
+            .unzip();
+
+        NovaQuery::new(
+            "UPDATE $posts SET related = $related[array::find_index($posts, id)] RETURN NONE;",
+        )
+        .bind("posts", post_ids)
+        .bind("related", related_ids)
+    }
+
+    /// Query: select the published versions of the posts related to a post, one translation
+    /// each (returns Vec<PostSummary> in no particular order, then the ranked related post ids).
+    pub fn query_select_related_posts(&self, post_id: &str, locale: &LocaleChoice) -> NovaQuery {
+        let sql = format!(
+            r#"
+            LET $related = (SELECT VALUE related FROM ONLY $post_id) OR [];
+            {LET_PUBLISHED_IN_LANG}
+            SELECT
                 {}
             FROM drafted
-            WHERE published = true
-            ORDER BY at DESC;
+            WHERE fn::string_id(out) IN $related
+                AND {PUBLISHED_IN_LANG};
+
+            RETURN $related;
             "#,
-            self.meta.select_meta_string
+            self.select_summary_string
         );
-        NovaQuery::new(sql)
+        bind_locale(NovaQuery::new(sql), locale).bind("post_id", thing_from_string(post_id))
+    }
+
+    /// Query: set `$lang` on drafts saved before posts could be translated (returns the
+    /// number of drafts updated).
+    pub fn query_backfill_draft_lang(&self, lang: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            RETURN array::len((
+                UPDATE drafted SET lang = $lang WHERE lang IS NONE RETURN NONE
+            ));
+            "#,
+        )
+        .bind("lang", lang.to_string())
     }
 
     /// Query: unpublish all drafts for a post (returns true).
@@ -320,16 +1105,124 @@ impl PostsRepo {
         .bind("draft_id", thing_from_string(draft_id))
     }
 
-    /// Query: select unpublished post ids (returns Vec<IdContainer>).
-    pub fn query_select_unpublished_post_ids(&self) -> NovaQuery {
-        NovaQuery::new(
+    /// Query: select the post imported from `source` (returns the post id, its categories,
+    /// then its latest PostVersion in `lang`; each may be NONE).
+    pub fn query_select_imported_post(&self, source: &str, lang: &str) -> NovaQuery {
+        let sql = format!(
             r#"
-            LET $published = SELECT out FROM drafted WHERE published = true;
+            LET $post_id = array::first(
+                SELECT VALUE id FROM post WHERE imported_from = $source LIMIT 1
+            );
 
-            LET $unpublished = SELECT fn::string_id(out) as id FROM drafted WHERE out NOT IN $published.out;
+            RETURN (IF $post_id IS NOT NONE THEN fn::string_id($post_id) END);
+            RETURN (IF $post_id IS NOT NONE THEN ($post_id.categories OR []) END);
 
-            RETURN array::distinct($unpublished);
+            SELECT
+                {}
+            FROM ONLY drafted
+            WHERE out = $post_id AND lang = $lang AND {DRAFT_NOT_TRASHED}
+            ORDER BY id DESC
+            LIMIT 1;
             "#,
-        )
+            self.select_version_string
+        );
+        NovaQuery::new(sql)
+            .bind("source", source.to_string())
+            .bind("lang", lang.to_string())
+    }
+
+    /// Query: create a post imported from `$source` with its first draft (run in a
+    /// transaction, returns PostVersion).
+    ///
+    /// The post's meta is dated `$created_on` and the draft `$at`, so imported posts keep
+    /// the dates they were written on.
+    pub fn query_import_post(&self) -> NovaQuery {
+        let sql = format!(
+            r#"
+            {}
+            LET $post_id = post:ulid();
+            CREATE $post_id
+            SET
+                meta = $meta_id,
+                imported_from = $source,
+                categories = $categories;
+
+            LET $drafted_id = drafted:ulid();
+            RELATE $person_id->drafted->$post_id SET {SET_IMPORTED_DRAFT};
+
+            SELECT
+                {}
+            FROM ONLY drafted
+            WHERE id = $drafted_id
+            LIMIT 1;
+            "#,
+            self.meta
+                .sql_create_meta_on("$meta_id", "<datetime>$created_on"),
+            self.select_version_string
+        );
+        NovaQuery::new(sql)
+    }
+
+    /// Query: add a new imported draft to `$post_id` and replace its categories (run in a
+    /// transaction, returns PostVersion).
+    ///
+    /// The source decides what is live, so the draft is published when `$published` is
+    /// true, and otherwise the post's translation in `$lang` is unpublished.
+    pub fn query_import_draft(&self) -> NovaQuery {
+        let sql = format!(
+            r#"
+            LET $meta_id = (SELECT meta FROM ONLY post WHERE id = $post_id LIMIT 1).meta;
+            UPDATE $post_id SET categories = $categories;
+
+            LET $drafted_id = drafted:ulid();
+            RELATE $person_id->drafted->$post_id SET {SET_IMPORTED_DRAFT};
+
+            IF $published {{
+                LET $draft_id = $drafted_id;
+                {}
+            }} ELSE {{
+                UPDATE drafted SET published = false WHERE out = $post_id AND lang = $lang;
+            }};
+
+            SELECT
+                {}
+            FROM ONLY drafted
+            WHERE id = $drafted_id
+            LIMIT 1;
+            "#,
+            self.sql_publish_draft(),
+            self.select_version_string
+        );
+        NovaQuery::new(sql)
+    }
+
+    /// Query: select a page of ids of posts with no published draft
+    /// (returns Vec<IdContainer>, then the total count).
+    pub fn query_select_unpublished_post_ids(&self, page: &PageArgs) -> NovaQuery {
+        let PageSql {
+            filters,
+            after_cursor,
+            order_limit,
+        } = PageSql::new(page, "id", "at");
+
+        let sql = format!(
+            r#"
+            LET $published = SELECT out FROM drafted WHERE published = true AND {DRAFT_NOT_TRASHED};
+
+            LET $unpublished = array::distinct(
+                SELECT VALUE out
+                FROM drafted
+                WHERE out NOT IN $published.out AND {DRAFT_NOT_TRASHED}{filters}
+            );
+
+            SELECT fn::string_id(id) as id
+            FROM post
+            WHERE id IN $unpublished{after_cursor}
+            {order_limit};
+
+            RETURN array::len($unpublished);
+            "#
+        );
+        bind_page(NovaQuery::new(sql), page, "post")
     }
 }
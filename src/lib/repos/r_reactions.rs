@@ -0,0 +1,83 @@
+use crate::db::nova_db::NovaQuery;
+use crate::utils::thing_from_string;
+
+#[derive(Debug, Clone)]
+pub struct ReactionsRepo {}
+
+impl Default for ReactionsRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReactionsRepo {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Query: react to a published post as `$person_id`, doing nothing when the person
+    /// already reacted with this kind (returns whether the post is published).
+    pub fn query_add_reaction(&self, post_id: &str, person_id: &str, kind: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            LET $published = array::len(
+                SELECT VALUE id FROM drafted
+                WHERE out = $post_id
+                    AND published = true
+                    AND meta.deleted_on IS NONE
+                    AND deleted_on IS NONE
+            ) > 0;
+            LET $exists = array::len(
+                SELECT VALUE id FROM reacted
+                WHERE in = $person_id AND out = $post_id AND kind = $kind
+            ) > 0;
+
+            IF $published AND !$exists {
+                RELATE $person_id->reacted->$post_id SET kind = $kind, at = time::now();
+            };
+
+            RETURN $published;
+            "#,
+        )
+        .bind("post_id", thing_from_string(post_id))
+        .bind("person_id", thing_from_string(person_id))
+        .bind("kind", kind.to_string())
+    }
+
+    /// Query: remove a person's reaction of one kind from a post (returns nothing).
+    pub fn query_remove_reaction(&self, post_id: &str, person_id: &str, kind: &str) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            DELETE reacted WHERE in = $person_id AND out = $post_id AND kind = $kind;
+            "#,
+        )
+        .bind("post_id", thing_from_string(post_id))
+        .bind("person_id", thing_from_string(person_id))
+        .bind("kind", kind.to_string())
+    }
+
+    /// Query: count reactions by kind on each post, then select the kinds `$person_id`
+    /// reacted with (returns Vec<ReactionCount>, then Vec<OwnReaction>).
+    pub fn query_select_reactions(
+        &self,
+        post_ids: Vec<String>,
+        person_id: Option<&str>,
+    ) -> NovaQuery {
+        NovaQuery::new(
+            r#"
+            SELECT fn::string_id(out) as post, kind, count() as count
+            FROM reacted
+            WHERE fn::string_id(out) IN $post_ids
+            GROUP BY post, kind;
+
+            SELECT fn::string_id(out) as post, kind
+            FROM reacted
+            WHERE $person_id IS NOT NONE
+                AND in = $person_id
+                AND fn::string_id(out) IN $post_ids;
+            "#,
+        )
+        .bind("post_ids", post_ids)
+        .bind("person_id", person_id.map(thing_from_string))
+    }
+}
@@ -1,2 +1,9 @@
+pub mod s_analytics;
+pub mod s_comments;
+pub mod s_feeds;
+pub mod s_media;
 pub mod s_persons;
 pub mod s_posts;
+pub mod s_previews;
+pub mod s_reactions;
+pub mod s_sitemap;
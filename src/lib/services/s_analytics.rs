@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+
+use sha2::{Digest, Sha256};
+use surrealdb::types::RecordId;
+use time::{Duration, OffsetDateTime, Time};
+use tracing::{info, instrument};
+
+use crate::db::nova_db::{NovaDB, NovaResponse};
+use crate::db::SurrealDBConnection;
+use crate::models::analytics::{
+    DailyViews, PopularWindow, PostDailyViews, PostViewTotal, RankedPost, RankedPosts, RankingArgs,
+    ViewRangeArgs, ViewSeries,
+};
+use crate::models::locale::LocaleChoice;
+use crate::models::post::PostSummary;
+use crate::repos::r_analytics::AnalyticsRepo;
+use crate::utils::datetime_string;
+
+/// Days of views returned when the caller does not pass `from`.
+const DEFAULT_VIEW_RANGE_DAYS: i64 = 30;
+
+/// Posts kept in each cached ranking, and so the largest `limit` a caller can ask for.
+const MAX_RANKED_POSTS: u32 = 50;
+
+/// Posts returned by the ranking endpoints when the caller does not pass `limit`.
+const DEFAULT_RANKED_POSTS: u32 = 10;
+
+/// Days of views that contribute to the trending score.
+const TRENDING_WINDOW_DAYS: i64 = 14;
+
+/// Days after which a view counts for half as much towards the trending score.
+const TRENDING_HALF_LIFE_DAYS: f64 = 3.0;
+
+/// Rankings served to readers, recomputed in the background by [`AnalyticsService::refresh_rankings`].
+#[derive(Debug, Default)]
+struct Rankings {
+    popular: HashMap<PopularWindow, CachedRanking>,
+    trending: CachedRanking,
+}
+
+/// A ranking holding every published translation of each post, so each request can be
+/// answered in its own language.
+#[derive(Debug, Clone, Default)]
+struct CachedRanking {
+    items: Vec<CachedRankedPost>,
+    computed_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedRankedPost {
+    translations: Vec<PostSummary>,
+    views: u64,
+    score: f64,
+}
+
+#[derive(Clone)]
+pub struct AnalyticsService {
+    repo: AnalyticsRepo,
+    conn: SurrealDBConnection,
+    salt: String,
+    /// Proxies whose `X-Forwarded-For` header is believed.
+    trusted_proxies: Vec<IpAddr>,
+    rankings: Arc<RwLock<Rankings>>,
+}
+
+// keep the salt out of logs and traces
+impl std::fmt::Debug for AnalyticsService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnalyticsService")
+            .field("repo", &self.repo)
+            .field("conn", &self.conn)
+            .field("trusted_proxies", &self.trusted_proxies)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AnalyticsService {
+    pub async fn new(
+        conn: SurrealDBConnection,
+        salt: String,
+        trusted_proxies: Vec<IpAddr>,
+    ) -> Self {
+        Self {
+            repo: AnalyticsRepo::new(),
+            conn,
+            salt,
+            trusted_proxies,
+            rankings: Arc::new(RwLock::new(Rankings::default())),
+        }
+    }
+
+    /// The address of the visitor behind a request from `peer`.
+    ///
+    /// `X-Forwarded-For` is only read when `peer` is a trusted proxy, since any client can
+    /// send it. The visitor is then the last address in it not added by a trusted proxy.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        if !self.trusted_proxies.contains(&peer) {
+            return peer;
+        }
+
+        let forwarded: Vec<IpAddr> = forwarded_for
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|ip| ip.trim().parse().ok())
+            .collect();
+
+        forwarded
+            .iter()
+            .rev()
+            .find(|ip| !self.trusted_proxies.contains(ip))
+            .or(forwarded.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+
+    /// Count a view of a post, once per visitor per UTC day.
+    ///
+    /// Visitors are identified by a salted hash of the day, post, IP address and user
+    /// agent, so the raw address is never stored and hashes can't be linked across days.
+    /// Returns whether the view was counted.
+    #[instrument(skip(self, ip, user_agent))]
+    pub async fn record_view(&self, post_id: String, ip: &str, user_agent: &str) -> bool {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let today = OffsetDateTime::now_utc().date();
+        let post_key = post_id
+            .split_once(':')
+            .map(|(_, key)| key)
+            .unwrap_or(&post_id);
+
+        let mut hasher = Sha256::new();
+        for part in [
+            self.salt.as_str(),
+            &today.to_string(),
+            &post_id,
+            ip,
+            user_agent,
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        let visitor_hash = hex::encode(hasher.finalize());
+
+        let q = self.repo.query_record_view(
+            &post_id,
+            RecordId::new("post_visitor", format!("{post_key}_{today}_{visitor_hash}")),
+            RecordId::new("post_view", format!("{post_key}_{today}")),
+            datetime_string(Some(today.midnight().assume_utc())).expect("day format failed"),
+        );
+
+        let tx = db.begin().await.expect("tx start failed");
+        let mut resp: NovaResponse = tx
+            .query(&q.sql)
+            .bind(q.args)
+            .await
+            .expect("record view failed")
+            .into();
+        tx.commit().await.expect("tx commit failed");
+
+        // Statement indices (LET counted in SurrealDB v3):
+        //   0: LET $draft_id
+        //   1: LET $new
+        //   2: IF $new CREATE visitor, UPSERT view, UPDATE draft visits
+        //   3: RETURN $new
+        resp.take_one::<bool>(3).unwrap_or(false)
+    }
+
+    /// Daily unique views of a single post.
+    #[instrument(skip(self))]
+    pub async fn get_post_views(&self, post_id: String, range: ViewRangeArgs) -> ViewSeries {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let (from, to) = day_range(&range);
+
+        let mut resp = db
+            .exec(self.repo.query_select_post_views(
+                &post_id,
+                datetime_string(Some(from)).expect("from format failed"),
+                datetime_string(Some(to)).expect("to format failed"),
+            ))
+            .await
+            .expect("db query failed");
+
+        let days: Vec<DailyViews> = resp.take_vec(0).unwrap_or_default();
+
+        ViewSeries {
+            post: Some(post_id),
+            from,
+            to,
+            total: days.iter().map(|d| d.views).sum(),
+            days,
+        }
+    }
+
+    /// Daily unique views summed across every post.
+    #[instrument(skip(self))]
+    pub async fn get_site_views(&self, range: ViewRangeArgs) -> ViewSeries {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let (from, to) = day_range(&range);
+
+        let mut resp = db
+            .exec(self.repo.query_select_site_views(
+                datetime_string(Some(from)).expect("from format failed"),
+                datetime_string(Some(to)).expect("to format failed"),
+            ))
+            .await
+            .expect("db query failed");
+
+        let days: Vec<DailyViews> = resp.take_vec(0).unwrap_or_default();
+
+        ViewSeries {
+            post: None,
+            from,
+            to,
+            total: days.iter().map(|d| d.views).sum(),
+            days,
+        }
+    }
+
+    /// Delete visitor hashes from before today. Only the daily totals are kept.
+    #[instrument(skip(self))]
+    pub async fn prune_visitors(&self) {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let today = OffsetDateTime::now_utc().date().midnight().assume_utc();
+
+        db.exec(
+            self.repo
+                .query_prune_visitors(datetime_string(Some(today)).expect("day format failed")),
+        )
+        .await
+        .expect("db query failed");
+
+        info!("s: pruned visitor hashes");
+    }
+
+    /// Most-read posts over `args.window`, from the cached rankings, each in the language
+    /// chosen by `locale`.
+    pub fn get_popular_posts(&self, args: RankingArgs, locale: &LocaleChoice) -> RankedPosts {
+        let rankings = self.rankings.read().expect("rankings lock poisoned");
+
+        match rankings.popular.get(&args.window) {
+            Some(ranked) => take_ranked(ranked, args.limit, locale),
+            None => RankedPosts::default(),
+        }
+    }
+
+    /// Posts with the most recent views, from the cached rankings, each in the language
+    /// chosen by `locale`.
+    pub fn get_trending_posts(&self, args: RankingArgs, locale: &LocaleChoice) -> RankedPosts {
+        let rankings = self.rankings.read().expect("rankings lock poisoned");
+
+        take_ranked(&rankings.trending, args.limit, locale)
+    }
+
+    /// Recompute the popular and trending rankings from the daily view rollups.
+    #[instrument(skip(self))]
+    pub async fn refresh_rankings(&self) {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let now = OffsetDateTime::now_utc();
+        let today = now.replace_time(Time::MIDNIGHT);
+
+        let mut popular = HashMap::new();
+        for window in PopularWindow::ALL {
+            let from = window
+                .days()
+                .and_then(|days| datetime_string(Some(today - Duration::days(days))));
+
+            let mut resp = db
+                .exec(self.repo.query_select_top_posts(from, MAX_RANKED_POSTS))
+                .await
+                .expect("db query failed");
+            // Statement indices: 0=LET $published, 1=SELECT top posts
+            let totals: Vec<PostViewTotal> = resp.take_vec(1).unwrap_or_default();
+
+            let scored = totals
+                .into_iter()
+                .map(|t| (t.post, t.views, t.views as f64))
+                .collect();
+
+            popular.insert(
+                window,
+                CachedRanking {
+                    items: self.hydrate(&db, scored).await,
+                    computed_at: Some(now),
+                },
+            );
+        }
+
+        let mut resp = db
+            .exec(
+                self.repo.query_select_daily_post_views(
+                    datetime_string(Some(today - Duration::days(TRENDING_WINDOW_DAYS)))
+                        .expect("from format failed"),
+                ),
+            )
+            .await
+            .expect("db query failed");
+        // Statement indices: 0=LET $published, 1=SELECT daily views
+        let daily: Vec<PostDailyViews> = resp.take_vec(1).unwrap_or_default();
+
+        let trending = CachedRanking {
+            items: self.hydrate(&db, trending_scores(daily, today)).await,
+            computed_at: Some(now),
+        };
+
+        info!("s: refreshed post rankings");
+
+        let mut rankings = self.rankings.write().expect("rankings lock poisoned");
+        rankings.popular = popular;
+        rankings.trending = trending;
+    }
+
+    /// Pairs `(post id, views, score)` with the post's published translations, keeping the
+    /// order of `scored` and dropping posts that are no longer published.
+    async fn hydrate(&self, db: &NovaDB, scored: Vec<(String, u64, f64)>) -> Vec<CachedRankedPost> {
+        if scored.is_empty() {
+            return vec![];
+        }
+
+        let ids: Vec<String> = scored.iter().map(|(id, _, _)| id.clone()).collect();
+        let mut resp = db
+            .exec(self.repo.query_select_published_summaries(&ids))
+            .await
+            .expect("db query failed");
+
+        let mut translations: HashMap<String, Vec<PostSummary>> = HashMap::new();
+        for summary in resp.take_vec::<PostSummary>(0).unwrap_or_default() {
+            let summary = summary.summarize();
+            translations
+                .entry(summary.id.clone())
+                .or_default()
+                .push(summary);
+        }
+
+        scored
+            .into_iter()
+            .filter_map(|(id, views, score)| {
+                translations
+                    .remove(&id)
+                    .map(|translations| CachedRankedPost {
+                        translations,
+                        views,
+                        score,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Sums each post's daily views, halving the weight of a day's views every
+/// `TRENDING_HALF_LIFE_DAYS`. Returns the top posts, highest score first.
+fn trending_scores(daily: Vec<PostDailyViews>, today: OffsetDateTime) -> Vec<(String, u64, f64)> {
+    let mut scores: HashMap<String, (u64, f64)> = HashMap::new();
+    for d in daily {
+        let age_days = (today - d.day).whole_days().max(0) as f64;
+        let weight = 0.5_f64.powf(age_days / TRENDING_HALF_LIFE_DAYS);
+
+        let entry = scores.entry(d.post).or_default();
+        entry.0 += d.views;
+        entry.1 += d.views as f64 * weight;
+    }
+
+    let mut ranked: Vec<(String, u64, f64)> = scores
+        .into_iter()
+        .map(|(post, (views, score))| (post, views, score))
+        .collect();
+    ranked.sort_by(|a, b| b.2.total_cmp(&a.2));
+    ranked.truncate(MAX_RANKED_POSTS as usize);
+    ranked
+}
+
+/// The first `limit` posts of a cached ranking, each in the translation `locale` prefers.
+fn take_ranked(ranked: &CachedRanking, limit: Option<u32>, locale: &LocaleChoice) -> RankedPosts {
+    let limit = limit
+        .unwrap_or(DEFAULT_RANKED_POSTS)
+        .clamp(1, MAX_RANKED_POSTS);
+
+    RankedPosts {
+        items: ranked
+            .items
+            .iter()
+            .take(limit as usize)
+            .filter_map(|r| {
+                locale
+                    .choose(r.translations.clone(), |p| &p.lang)
+                    .map(|post| RankedPost {
+                        post,
+                        views: r.views,
+                        score: r.score,
+                    })
+            })
+            .collect(),
+        computed_at: ranked.computed_at,
+    }
+}
+
+/// Whole UTC days covered by `range`, defaulting to the last 30 days.
+fn day_range(range: &ViewRangeArgs) -> (OffsetDateTime, OffsetDateTime) {
+    let to = range
+        .to
+        .unwrap_or_else(OffsetDateTime::now_utc)
+        .to_offset(time::UtcOffset::UTC)
+        .replace_time(Time::MIDNIGHT);
+    let from = range
+        .from
+        .map(|f| f.to_offset(time::UtcOffset::UTC))
+        .unwrap_or(to - Duration::days(DEFAULT_VIEW_RANGE_DAYS))
+        .replace_time(Time::MIDNIGHT);
+
+    (from, to)
+}
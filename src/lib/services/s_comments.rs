@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+
+use surrealdb::engine::any::Any;
+use surrealdb::method::Transaction;
+use time::{Duration, OffsetDateTime};
+use tracing::{info, instrument};
+
+use crate::constants::{
+    COMMENT_DELETE_WINDOW_MINUTES, COMMENT_EDIT_WINDOW_MINUTES, MAX_COMMENT_DEPTH,
+    MAX_COMMENT_LENGTH,
+};
+use crate::db::nova_db::{NovaDB, NovaResponse};
+use crate::db::SurrealDBConnection;
+use crate::models::comment::{
+    Comment, CommentError, CommentNode, CommentStatus, CreateCommentArgs, PostCommentState,
+    ReplyParent,
+};
+use crate::models::meta::IdContainer;
+use crate::models::page::{Page, PageArgs};
+use crate::models::person::Person;
+use crate::repos::r_comments::CommentsRepo;
+use crate::utils::is_record_id;
+
+#[derive(Debug, Clone)]
+pub struct CommentsService {
+    repo: CommentsRepo,
+    conn: SurrealDBConnection,
+}
+
+impl CommentsService {
+    pub async fn new(conn: SurrealDBConnection) -> Self {
+        Self {
+            repo: CommentsRepo::new(),
+            conn,
+        }
+    }
+
+    /// The approved comments on a published post, threaded by reply. Unpublished and
+    /// trashed posts are not found, as they are for the post endpoints.
+    ///
+    /// A comment that is not visible itself is kept as a placeholder without a body when
+    /// it has visible replies, so the thread keeps its shape.
+    #[instrument(skip(self))]
+    pub async fn get_post_comments(
+        &self,
+        post_id: String,
+    ) -> Result<Vec<CommentNode>, CommentError> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_post_comment_state(&post_id))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $post, 1=LET $published, 2=RETURN state
+        let state: PostCommentState = resp.take_one(2).expect("comment state malformed");
+        if !state.found || !state.published {
+            return Err(CommentError::NotFound);
+        }
+
+        let mut resp = db
+            .exec(self.repo.query_select_post_comments(&post_id))
+            .await
+            .expect("db query failed");
+
+        Ok(build_thread(
+            resp.take_vec::<Comment>(0).unwrap_or_default(),
+        ))
+    }
+
+    /// Comment on a published post, or reply to an approved comment on it.
+    ///
+    /// Comments by admins are approved straight away, everyone else's wait in the
+    /// moderation queue.
+    #[instrument(skip(self, args))]
+    pub async fn create_comment(
+        &self,
+        post_id: String,
+        person: Person,
+        args: CreateCommentArgs,
+    ) -> Result<Comment, CommentError> {
+        let body = validate_body(&args.body)?;
+        if args
+            .parent_id
+            .as_deref()
+            .is_some_and(|id| !is_record_id(id, "commented"))
+        {
+            return Err(CommentError::Invalid(
+                "Unable to find comment to reply to.".into(),
+            ));
+        }
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        // checked in the transaction the comment is written in, so the post can't be
+        // unpublished or closed in between
+        let tx = db.begin().await.expect("tx start failed");
+        if let Err(e) = self
+            .check_can_comment(&tx, &post_id, args.parent_id.as_deref())
+            .await
+        {
+            tx.cancel().await.expect("tx cancel failed");
+            return Err(e);
+        }
+
+        let status = if person.is_admin {
+            CommentStatus::Approved
+        } else {
+            CommentStatus::Pending
+        };
+
+        let q = self.repo.query_create_comment(
+            &post_id,
+            &person.id,
+            body,
+            args.parent_id.as_deref(),
+            status,
+        );
+
+        let mut resp: NovaResponse = tx
+            .query(&q.sql)
+            .bind(q.args)
+            .await
+            .expect("create comment failed")
+            .into();
+        tx.commit().await.expect("tx commit failed");
+
+        // Statement indices (LET counted in SurrealDB v3):
+        //   0: LET $meta_id
+        //   1: CREATE meta
+        //   2: LET $comment_id
+        //   3: RELATE (comment relation)
+        //   4: SELECT commented with meta join
+        Ok(resp.take_one::<Comment>(4).expect("comment create failed"))
+    }
+
+    /// Replace the body of a comment. Only its author can, within the edit window.
+    ///
+    /// The edit goes back to the moderation queue unless an admin made it, so an approved
+    /// comment can't be changed into something nobody reviewed. Comments marked as spam or
+    /// removed keep their status, so editing them doesn't put them back in the queue.
+    #[instrument(skip(self, body))]
+    pub async fn edit_comment(
+        &self,
+        comment_id: String,
+        person: Person,
+        body: String,
+    ) -> Result<Comment, CommentError> {
+        let body = validate_body(&body)?;
+        let comment = self
+            .get_own_comment(&comment_id, &person, COMMENT_EDIT_WINDOW_MINUTES)
+            .await?;
+
+        let status = match comment.status {
+            CommentStatus::Spam | CommentStatus::Removed => comment.status,
+            _ if person.is_admin => CommentStatus::Approved,
+            _ => CommentStatus::Pending,
+        };
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(
+                self.repo
+                    .query_edit_comment(&comment_id, &person.id, body, status),
+            )
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $meta_id, 1=UPDATE comment, 2=UPDATE meta, 3=SELECT comment
+        Ok(resp.take_one::<Comment>(3).expect("comment edit failed"))
+    }
+
+    /// Soft delete a comment. Only its author can, within the delete window.
+    #[instrument(skip(self))]
+    pub async fn delete_comment(
+        &self,
+        comment_id: String,
+        person: Person,
+    ) -> Result<(), CommentError> {
+        self.get_own_comment(&comment_id, &person, COMMENT_DELETE_WINDOW_MINUTES)
+            .await?;
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        db.exec(self.repo.query_delete_comment(&comment_id, &person.id))
+            .await
+            .expect("db query failed");
+
+        Ok(())
+    }
+
+    /// Move a comment to another moderation state, recording who moderated it.
+    #[instrument(skip(self))]
+    pub async fn moderate_comment(
+        &self,
+        comment_id: String,
+        person_id: String,
+        status: CommentStatus,
+    ) -> Option<Comment> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(
+                self.repo
+                    .query_set_comment_status(&comment_id, &person_id, status),
+            )
+            .await
+            .expect("db query failed");
+
+        info!("comment {} moderated as {}", &comment_id, status.as_str());
+
+        // Statement indices: 0=LET $meta_id, 1=IF UPDATE, 2=SELECT comment
+        resp.take_vec::<Comment>(2)
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+    }
+
+    /// A page of comments in `status` across all posts, newest first by default.
+    #[instrument(skip(self))]
+    pub async fn get_moderation_queue(
+        &self,
+        status: CommentStatus,
+        page: PageArgs,
+    ) -> Page<Comment> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_moderation_queue(status, &page))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=SELECT page, 1=RETURN total
+        let comments = resp.take_vec::<Comment>(0).unwrap_or_default();
+        let total = resp.take_one::<u64>(1).unwrap_or_default();
+
+        Page::new(comments, page.limit(), total, |c| &c.id)
+    }
+
+    /// Open or close a post for new comments. Existing comments stay visible.
+    #[instrument(skip(self))]
+    pub async fn set_comments_open(&self, post_id: String, open: bool) -> bool {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_set_comments_open(&post_id, open))
+            .await
+            .expect("db query failed");
+
+        !resp
+            .take_vec::<IdContainer>(0)
+            .unwrap_or_default()
+            .is_empty()
+    }
+
+    /// Whether the post is published and open for comments, and the comment replied to, if
+    /// any, is visible on it and not nested too deeply to reply to.
+    async fn check_can_comment(
+        &self,
+        tx: &Transaction<Any>,
+        post_id: &str,
+        parent_id: Option<&str>,
+    ) -> Result<(), CommentError> {
+        let q = self.repo.query_select_post_comment_state(post_id);
+        let mut resp: NovaResponse = tx
+            .query(&q.sql)
+            .bind(q.args)
+            .await
+            .expect("db query failed")
+            .into();
+
+        // Statement indices: 0=LET $post, 1=LET $published, 2=RETURN state
+        let state: PostCommentState = resp.take_one(2).expect("comment state malformed");
+        if !state.found {
+            return Err(CommentError::NotFound);
+        }
+        if !state.published || !state.open {
+            return Err(CommentError::Closed);
+        }
+
+        let Some(parent_id) = parent_id else {
+            return Ok(());
+        };
+
+        let q = self.repo.query_select_reply_parent(post_id, parent_id);
+        let mut resp: NovaResponse = tx
+            .query(&q.sql)
+            .bind(q.args)
+            .await
+            .expect("db query failed")
+            .into();
+
+        let parent = resp
+            .take_opt::<ReplyParent>(0)
+            .ok()
+            .flatten()
+            .filter(|p| p.status == CommentStatus::Approved && !p.deleted)
+            .ok_or_else(|| CommentError::Invalid("Unable to find comment to reply to.".into()))?;
+
+        if parent.depth >= MAX_COMMENT_DEPTH {
+            return Err(CommentError::Invalid(format!(
+                "Replies can only be nested {} levels deep.",
+                MAX_COMMENT_DEPTH
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// The comment, if `person` wrote it less than `window_minutes` ago and it is not deleted.
+    async fn get_own_comment(
+        &self,
+        comment_id: &str,
+        person: &Person,
+        window_minutes: i64,
+    ) -> Result<Comment, CommentError> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_comment(comment_id))
+            .await
+            .expect("db query failed");
+
+        let comment = resp
+            .take_vec::<Comment>(0)
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .filter(|c| c.meta.deleted_on.is_none())
+            .ok_or(CommentError::NotFound)?;
+
+        if comment.author != person.id {
+            return Err(CommentError::NotAuthor);
+        }
+        if OffsetDateTime::now_utc() - comment.at > Duration::minutes(window_minutes) {
+            return Err(CommentError::WindowExpired);
+        }
+
+        Ok(comment)
+    }
+}
+
+/// Trimmed comment body, rejecting empty and overly long ones.
+fn validate_body(body: &str) -> Result<String, CommentError> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err(CommentError::Invalid("Comment can't be empty.".into()));
+    }
+    if body.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(CommentError::Invalid(format!(
+            "Comment must be at most {} characters.",
+            MAX_COMMENT_LENGTH
+        )));
+    }
+    Ok(body.to_string())
+}
+
+fn is_visible(comment: &Comment) -> bool {
+    comment.status == CommentStatus::Approved && comment.meta.deleted_on.is_none()
+}
+
+/// Nests comments under their parents, oldest first at every level, dropping comments
+/// that are neither visible nor have visible replies.
+fn build_thread(comments: Vec<Comment>) -> Vec<CommentNode> {
+    let mut children: HashMap<Option<String>, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        children
+            .entry(comment.parent.clone())
+            .or_default()
+            .push(comment);
+    }
+
+    fn nodes(
+        parent: Option<String>,
+        children: &mut HashMap<Option<String>, Vec<Comment>>,
+    ) -> Vec<CommentNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|comment| {
+                let replies = nodes(Some(comment.id.clone()), children);
+                let visible = is_visible(&comment);
+
+                if !visible && replies.is_empty() {
+                    return None;
+                }
+
+                Some(CommentNode {
+                    id: comment.id,
+                    author_summary: comment.author_summary.filter(|_| visible),
+                    body: visible.then_some(comment.body),
+                    at: comment.at,
+                    edited_on: comment.edited_on,
+                    replies,
+                })
+            })
+            .collect()
+    }
+
+    nodes(None, &mut children)
+}
@@ -0,0 +1,116 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use crate::constants::FEED_SIZE;
+use crate::db::nova_db::NovaDB;
+use crate::db::SurrealDBConnection;
+use crate::models::feed::{Feed, FeedArgs, FeedEntry, FeedFormat, SiteConfig};
+use crate::models::locale::LocaleConfig;
+use crate::models::post::PostVersion;
+use crate::render::render_markdown;
+use crate::repos::r_posts::PostsRepo;
+
+/// Characters escaped when a tag is put in a url: everything but the unreserved ones, so
+/// the same encoding is safe in a path segment and in a query value.
+const TAG_ESCAPED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+#[derive(Debug, Clone)]
+pub struct FeedsService {
+    repo: PostsRepo,
+    conn: SurrealDBConnection,
+    site: SiteConfig,
+    locales: LocaleConfig,
+}
+
+impl FeedsService {
+    pub async fn new(conn: SurrealDBConnection, site: SiteConfig, locales: LocaleConfig) -> Self {
+        Self {
+            repo: PostsRepo::new(),
+            conn,
+            site,
+            locales,
+        }
+    }
+
+    /// The newest published posts as a feed, optionally only those with `args.tag`.
+    /// Feed readers rarely send `Accept-Language`, so only `args.lang` picks the language.
+    #[instrument(skip(self))]
+    pub async fn get_feed(&self, format: FeedFormat, args: FeedArgs) -> Feed {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let tag = args.tag.as_deref().map(|t| t.trim().to_lowercase());
+        let locale = self.locales.negotiate(args.lang.as_deref(), None);
+
+        let mut resp = db
+            .exec(
+                self.repo
+                    .query_select_feed_posts(tag.as_deref(), FEED_SIZE, &locale),
+            )
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $published_in_lang, 1=SELECT feed posts
+        let posts = resp.take_vec::<PostVersion>(1).unwrap_or_default();
+
+        let (title, home_url, feed_url) = match &tag {
+            Some(tag) => {
+                let escaped = utf8_percent_encode(tag, TAG_ESCAPED);
+                (
+                    format!("{} – {}", self.site.title, tag),
+                    format!("{}/tags/{}", self.site.url, escaped),
+                    format!("{}{}?tag={}", self.site.url, format.path(), escaped),
+                )
+            }
+            None => (
+                self.site.title.clone(),
+                self.site.url.clone(),
+                format!("{}{}", self.site.url, format.path()),
+            ),
+        };
+
+        Feed {
+            title,
+            description: self.site.description.clone(),
+            home_url,
+            feed_url,
+            updated: posts
+                .iter()
+                .map(|p| p.at)
+                .max()
+                .unwrap_or_else(OffsetDateTime::now_utc),
+            entries: posts
+                .into_iter()
+                .map(|post| self.entry(post, args.full))
+                .collect(),
+        }
+    }
+
+    fn entry(&self, post: PostVersion, full: bool) -> FeedEntry {
+        let rendered = render_markdown(post.markdown());
+
+        let author_name = post
+            .author_summary
+            .as_ref()
+            .map(|a| a.display_name.clone().unwrap_or_else(|| a.username.clone()))
+            .unwrap_or_else(|| post.author.clone());
+
+        FeedEntry {
+            url: self.site.post_url(&post.id),
+            author_url: self.site.author_url(&post.author),
+            id: post.id,
+            title: post.title,
+            summary: post.excerpt.unwrap_or(rendered.excerpt),
+            content_html: full.then_some(rendered.html),
+            author_name,
+            image: Some(post.image).filter(|i| !i.is_empty()),
+            tags: post.tags,
+            published: post.meta.created_on,
+            updated: post.at,
+        }
+    }
+}
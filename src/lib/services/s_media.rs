@@ -0,0 +1,440 @@
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use tracing::{info, instrument, warn};
+
+use crate::constants::{MAX_ALT_TEXT_LENGTH, MAX_CAPTION_LENGTH};
+use crate::db::nova_db::{NovaDB, NovaQuery, NovaResponse};
+use crate::db::SurrealDBConnection;
+use crate::imaging::{process_image, strip_gif, PROCESSED_IMAGE_TYPES};
+use crate::models::media::{
+    ImageSet, ImageVariant, Media, MediaDetailsArgs, MediaError, MediaFile, MediaListArgs,
+    MediaReference, UploadedMedia,
+};
+use crate::models::page::{Page, PageArgs};
+use crate::repos::r_media::MediaRepo;
+use crate::storage::MediaStore;
+use crate::utils::thing_from_string;
+
+/// Types uploads are accepted as, by the type sniffed from their bytes. Images are only
+/// accepted in formats whose metadata is stripped on upload, which leaves out AVIF.
+const ALLOWED_MEDIA_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "video/mp4",
+    "video/webm",
+    "application/pdf",
+];
+
+#[derive(Debug, Clone)]
+pub struct MediaService {
+    repo: MediaRepo,
+    conn: SurrealDBConnection,
+    store: Arc<dyn MediaStore>,
+    /// Base URL media files are served from, without a trailing slash.
+    public_url: String,
+    max_upload_bytes: usize,
+    /// Widths images are resized to, when narrower than the upload.
+    image_widths: Vec<u32>,
+}
+
+impl MediaService {
+    pub async fn new(
+        conn: SurrealDBConnection,
+        store: Arc<dyn MediaStore>,
+        public_url: String,
+        max_upload_bytes: usize,
+        image_widths: Vec<u32>,
+    ) -> Self {
+        Self {
+            repo: MediaRepo::new(),
+            conn,
+            store,
+            public_url: public_url.trim_end_matches('/').to_string(),
+            max_upload_bytes,
+            image_widths,
+        }
+    }
+
+    /// Largest upload accepted, in bytes.
+    pub fn max_upload_bytes(&self) -> usize {
+        self.max_upload_bytes
+    }
+
+    /// Store an uploaded file. The type is sniffed from the bytes, and content that was
+    /// uploaded before is not stored again: the existing media is returned instead.
+    ///
+    /// Images are stored without their metadata, along with resized and WebP variants.
+    /// GIFs are only stripped, so animations are not lost to resizing.
+    #[instrument(skip(self, bytes))]
+    pub async fn upload(
+        &self,
+        person_id: String,
+        original_name: Option<String>,
+        bytes: Vec<u8>,
+    ) -> Result<UploadedMedia, MediaError> {
+        if bytes.is_empty() {
+            return Err(MediaError::Empty);
+        }
+        if bytes.len() > self.max_upload_bytes {
+            return Err(MediaError::TooLarge(self.max_upload_bytes));
+        }
+
+        let kind = infer::get(&bytes).ok_or(MediaError::UnsupportedType(None))?;
+        if !ALLOWED_MEDIA_TYPES.contains(&kind.mime_type()) {
+            return Err(MediaError::UnsupportedType(Some(kind.mime_type().into())));
+        }
+
+        let hash = hex::encode(Sha256::digest(&bytes));
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_media_by_hash(&hash))
+            .await
+            .expect("db query failed");
+
+        if let Some(existing) = resp.take_opt::<Media>(0).ok().flatten() {
+            info!("upload matches existing media {}", &existing.id);
+
+            if existing.meta.deleted_on.is_some() {
+                db.exec(self.repo.query_restore_media(&existing.id, &person_id))
+                    .await
+                    .expect("db query failed");
+            }
+
+            return Ok(UploadedMedia {
+                media: self.with_url(existing),
+                duplicate: true,
+            });
+        }
+
+        let key = format!("{}.{}", hash, kind.extension());
+
+        let (bytes, image) = if PROCESSED_IMAGE_TYPES.contains(&kind.mime_type()) {
+            let (bytes, image) = self
+                .store_variants(&hash, &key, kind.mime_type(), bytes)
+                .await?;
+            (bytes, Some(image))
+        } else if kind.mime_type() == "image/gif" {
+            let stripped = tokio::task::spawn_blocking(move || strip_gif(&bytes))
+                .await
+                .expect("image processing panicked")
+                .map_err(|e| MediaError::Undecodable(e.to_string()))?;
+            (stripped, None)
+        } else {
+            (bytes, None)
+        };
+        let size = bytes.len() as u64;
+
+        self.store
+            .put(&key, bytes, kind.mime_type())
+            .await
+            .map_err(|e| MediaError::Store(e.to_string()))?;
+
+        let q = self
+            .repo
+            .query_create_media(image.as_ref())
+            .bind("created_by", thing_from_string(&person_id))
+            .bind("hash", hash.clone())
+            .bind("key", key)
+            .bind("content_type", kind.mime_type().to_string())
+            .bind("size", size as i64)
+            .bind("original_name", original_name);
+
+        let Some(media) = self.create_media(&db, q).await else {
+            // a concurrent upload of the same content claimed the hash first; its file is
+            // stored under the same key, so hand back its record like any other duplicate
+            let mut resp = db
+                .exec(self.repo.query_select_media_by_hash(&hash))
+                .await
+                .expect("db query failed");
+            let existing = resp
+                .take_opt::<Media>(0)
+                .ok()
+                .flatten()
+                .expect("media create failed");
+
+            info!("upload raced existing media {}", &existing.id);
+
+            return Ok(UploadedMedia {
+                media: self.with_url(existing),
+                duplicate: true,
+            });
+        };
+
+        info!("stored media {} as {}", &media.id, &media.key);
+
+        Ok(UploadedMedia {
+            media: self.with_url(media),
+            duplicate: false,
+        })
+    }
+
+    /// Runs a [`MediaRepo::query_create_media`] query in a transaction. Returns `None` when
+    /// the transaction fails, as it does when the unique hash index rejects the media.
+    async fn create_media(&self, db: &NovaDB, q: NovaQuery) -> Option<Media> {
+        let tx = db.begin().await.expect("tx start failed");
+        let mut resp: NovaResponse = match tx.query(&q.sql).bind(q.args).await {
+            Ok(resp) => resp.into(),
+            Err(e) => {
+                warn!("create media failed: {e}");
+                tx.cancel().await.ok();
+                return None;
+            }
+        };
+
+        // Statement indices (LET counted in SurrealDB v3):
+        //   0: LET $meta_id
+        //   1: CREATE meta
+        //   2: LET $media_id
+        //   3: CREATE media
+        //   4: SELECT media with meta join
+        match resp.take_one::<Media>(4) {
+            Ok(media) => {
+                tx.commit().await.ok()?;
+                Some(media)
+            }
+            Err(e) => {
+                warn!("create media failed: {e}");
+                tx.cancel().await.ok();
+                None
+            }
+        }
+    }
+
+    /// The bytes of a live media file or image variant by its key.
+    #[instrument(skip(self))]
+    pub async fn get_file(&self, key: String) -> Option<MediaFile> {
+        // Original keys are `{hash}.{ext}` and variant keys `{hash}-{width}w.{ext}`.
+        let hash = key.split(['-', '.']).next().unwrap_or_default();
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_live_media_by_hash(hash))
+            .await
+            .expect("db query failed");
+
+        let media = resp.take_opt::<Media>(0).ok().flatten()?;
+
+        let (content_type, etag) = if media.key == key {
+            (media.content_type, media.hash)
+        } else {
+            let variant = media.variants.into_iter().find(|v| v.key == key)?;
+            (variant.content_type, variant.key)
+        };
+        let bytes = self.store.get(&key).await.ok().flatten()?;
+
+        Some(MediaFile {
+            bytes,
+            content_type,
+            etag,
+        })
+    }
+
+    /// A page of the media library, newest first by default.
+    #[instrument(skip(self))]
+    pub async fn list_media(&self, args: MediaListArgs, page: PageArgs) -> Page<Media> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_media_page(&args, &page))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=SELECT page, 1=RETURN total
+        let media = resp.take_vec::<Media>(0).unwrap_or_default();
+        let total = resp.take_one::<u64>(1).unwrap_or_default();
+
+        Page::new(media, page.limit(), total, |m| &m.id).map(|m| self.with_url(m))
+    }
+
+    /// Replace the alt text and caption of media. Blank text clears the field.
+    #[instrument(skip(self, args))]
+    pub async fn update_details(
+        &self,
+        key: String,
+        person_id: String,
+        args: MediaDetailsArgs,
+    ) -> Result<Media, MediaError> {
+        let alt_text = validate_text(args.alt_text, "Alt text", MAX_ALT_TEXT_LENGTH)?;
+        let caption = validate_text(args.caption, "Caption", MAX_CAPTION_LENGTH)?;
+
+        let media = self.get_media(&key).await?;
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(
+                self.repo
+                    .query_update_media_details(&media.id, &person_id, alt_text, caption),
+            )
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $meta_id, 1=UPDATE media, 2=UPDATE meta, 3=SELECT media
+        let media = resp.take_one::<Media>(3).expect("media update failed");
+
+        Ok(self.with_url(media))
+    }
+
+    /// Drafts using media as their image or in their markdown, newest first. Not trashed
+    /// drafts are searched, published or not.
+    #[instrument(skip(self))]
+    pub async fn get_references(&self, key: String) -> Result<Vec<MediaReference>, MediaError> {
+        let media = self.get_media(&key).await?;
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_media_references(&media.hash))
+            .await
+            .expect("db query failed");
+
+        Ok(resp.take_vec::<MediaReference>(0).unwrap_or_default())
+    }
+
+    /// Move media to the trash. Refused while a published post uses it, unless `force`d.
+    #[instrument(skip(self))]
+    pub async fn delete_media(
+        &self,
+        key: String,
+        person_id: String,
+        force: bool,
+    ) -> Result<(), MediaError> {
+        let media = self.get_media(&key).await?;
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        if !force {
+            let mut resp = db
+                .exec(self.repo.query_select_media_references(&media.hash))
+                .await
+                .expect("db query failed");
+
+            let mut post_ids: Vec<String> = resp
+                .take_vec::<MediaReference>(0)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|r| r.published)
+                .map(|r| r.post_id)
+                .collect();
+            post_ids.sort();
+            post_ids.dedup();
+
+            if !post_ids.is_empty() {
+                return Err(MediaError::InUse(post_ids));
+            }
+        }
+
+        db.exec(self.repo.query_delete_media(&media.id, &person_id))
+            .await
+            .expect("db query failed");
+
+        info!("media {} deleted by {}", &media.id, &person_id);
+
+        Ok(())
+    }
+
+    /// Live media by its own key, not an image variant's.
+    async fn get_media(&self, key: &str) -> Result<Media, MediaError> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_media_by_key(key))
+            .await
+            .expect("db query failed");
+
+        resp.take_opt::<Media>(0)
+            .ok()
+            .flatten()
+            .ok_or(MediaError::NotFound)
+    }
+
+    /// Decodes an image, stores its variants and returns the image re-encoded without
+    /// metadata, to be stored in place of the upload.
+    async fn store_variants(
+        &self,
+        hash: &str,
+        key: &str,
+        content_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<(Vec<u8>, ImageSet), MediaError> {
+        let widths = self.image_widths.clone();
+        let image_type = content_type.to_string();
+        let processed =
+            tokio::task::spawn_blocking(move || process_image(&bytes, &image_type, &widths))
+                .await
+                .expect("image processing panicked")
+                .map_err(|e| MediaError::Undecodable(e.to_string()))?;
+
+        let mut variants = vec![ImageVariant {
+            key: key.to_string(),
+            url: self.url(key),
+            width: processed.width,
+            height: processed.height,
+            content_type: content_type.to_string(),
+        }];
+
+        for variant in processed.variants {
+            let variant_key = format!("{}-{}w.{}", hash, variant.width, variant.extension);
+
+            self.store
+                .put(&variant_key, variant.bytes, variant.content_type)
+                .await
+                .map_err(|e| MediaError::Store(e.to_string()))?;
+
+            variants.push(ImageVariant {
+                url: self.url(&variant_key),
+                key: variant_key,
+                width: variant.width,
+                height: variant.height,
+                content_type: variant.content_type.to_string(),
+            });
+        }
+        variants.sort_by_key(|v| v.width);
+
+        info!("stored {} variants of {}", variants.len() - 1, key);
+
+        Ok((
+            processed.original,
+            ImageSet {
+                width: processed.width,
+                height: processed.height,
+                blurhash: processed.blurhash,
+                variants,
+                alt_text: None,
+                caption: None,
+            },
+        ))
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_url, key)
+    }
+
+    fn with_url(&self, mut media: Media) -> Media {
+        media.url = self.url(&media.key);
+        media
+    }
+}
+
+/// Trimmed text, `None` when blank.
+fn validate_text(
+    text: Option<String>,
+    field: &str,
+    max_length: usize,
+) -> Result<Option<String>, MediaError> {
+    let text = text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+
+    match text {
+        Some(t) if t.chars().count() > max_length => Err(MediaError::Invalid(format!(
+            "{} must be at most {} characters.",
+            field, max_length
+        ))),
+        text => Ok(text),
+    }
+}
@@ -11,7 +11,12 @@ use crate::{
         SurrealDBConnection,
     },
     models::{
-        person::{LogInCreds, Person, PersonCheck, PersonCheckResponse, SignUpState},
+        import::ImportedAuthor,
+        page::{Page, PageArgs},
+        person::{
+            Author, LogInCreds, Person, PersonCheck, PersonCheckResponse, SignUpState,
+            UpdateProfileArgs,
+        },
         token::{Token, TokenRecord},
     },
     repos::r_persons::PersonsRepo,
@@ -72,6 +77,10 @@ impl PersonsService {
             .to_string();
         sign_up_state.pass_hash = Some(password_hash);
 
+        self.insert_person(sign_up_state).await
+    }
+
+    async fn insert_person(&self, sign_up_state: SignUpState) -> Person {
         let db = NovaDB::new(&self.conn).await.expect("db connect failed");
         let q = self.repo.query_insert_person(sign_up_state, SYSTEM_ID);
 
@@ -254,17 +263,150 @@ impl PersonsService {
     }
 
     #[instrument(skip(self))]
-    pub async fn get_persons(&self) -> Vec<Person> {
+    pub async fn get_person_by_email(&self, email: String) -> Option<Person> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_person_by_email(&email))
+            .await
+            .expect("db query failed");
+
+        resp.take_opt::<Person>(0).unwrap_or(None)
+    }
+
+    /// The person posts imported from `login` are written as: the person with `email`, or a
+    /// new one created for the import with a username based on `login` that isn't taken yet.
+    ///
+    /// New persons are created without a password, and there is no way to set one, so they
+    /// can't log in. They only exist to be credited as the author of their imported posts.
+    #[instrument(skip(self))]
+    pub async fn import_author(
+        &self,
+        login: String,
+        email: String,
+        display_name: String,
+        dry_run: bool,
+    ) -> ImportedAuthor {
+        if let Some(person) = self.get_person_by_email(email.clone()).await {
+            return ImportedAuthor {
+                login,
+                email,
+                person_id: Some(person.id),
+                created: false,
+            };
+        }
+
+        if dry_run {
+            return ImportedAuthor {
+                login,
+                email,
+                person_id: None,
+                created: true,
+            };
+        }
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let base: String = login
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            .collect();
+        let base = if base.is_empty() {
+            "author".into()
+        } else {
+            base
+        };
+
+        let mut username = base.clone();
+        for suffix in 2.. {
+            let mut resp = db
+                .exec(self.repo.query_is_unique_username(&username))
+                .await
+                .expect("db query failed");
+
+            // Statement indices: 0=LET $count, 1=RETURN unique
+            if resp.take_one::<bool>(1).unwrap_or(false) {
+                break;
+            }
+            username = format!("{}{}", base, suffix);
+        }
+
+        info!(
+            "creating person {} for imported author {}",
+            &username, &login
+        );
+
+        let mut person = self
+            .insert_person(SignUpState {
+                username,
+                email: email.clone(),
+                password: String::new(),
+                pass_hash: None,
+            })
+            .await;
+
+        let profile = UpdateProfileArgs {
+            display_name: Some(display_name.trim().to_string()).filter(|n| !n.is_empty()),
+            bio: None,
+            avatar_url: None,
+            links: vec![],
+        };
+        if profile.display_name.is_some() && profile.validate().is_ok() {
+            person = self.update_profile(person.id, profile).await;
+        }
+
+        ImportedAuthor {
+            login,
+            email,
+            person_id: Some(person.id),
+            created: true,
+        }
+    }
+
+    /// Public profile of a person, without their email or admin status. Only people with a
+    /// published post are authors, so anyone else is `None`.
+    #[instrument(skip(self))]
+    pub async fn get_author(&self, person_id: String) -> Option<Author> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_author(&person_id))
+            .await
+            .expect("db query failed");
+
+        resp.take_opt::<Author>(0).unwrap_or(None)
+    }
+
+    /// Replace the profile fields of a person. Expects `profile` to have been validated.
+    #[instrument(skip(self))]
+    pub async fn update_profile(&self, person_id: String, profile: UpdateProfileArgs) -> Person {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_update_profile(&person_id, profile))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=UPDATE, 1=SELECT person with meta join
+        resp.take_one::<Person>(1).expect("profile update failed")
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_persons(&self, page: PageArgs) -> Page<Person> {
         info!("s: get persons");
 
         let db = NovaDB::new(&self.conn).await.expect("db connect failed");
 
         let mut resp = db
-            .exec(self.repo.query_select_persons())
+            .exec(self.repo.query_select_persons(&page))
             .await
             .expect("db query failed");
 
-        resp.take_vec::<Person>(0).unwrap_or_default()
+        // Statement indices: 0=SELECT page, 1=RETURN total
+        let persons = resp.take_vec::<Person>(0).unwrap_or_default();
+        let total = resp.take_one::<u64>(1).unwrap_or_default();
+
+        Page::new(persons, page.limit(), total, |p| &p.id)
     }
 
     #[instrument(skip(self))]
@@ -1,28 +1,67 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 use futures::future::join_all;
-use rand::seq::SliceRandom;
+use time::{Duration, OffsetDateTime};
 use tracing::{info, instrument};
+use ulid::Ulid;
 
 use crate::db::nova_db::{NovaDB, NovaQuery, NovaResponse};
 use crate::db::SurrealDBConnection;
+use crate::diff::diff_field;
+use crate::models::diff::{DiffArgs, DraftDiff};
+use crate::models::import::{ImportAction, ImportError, ImportPostArgs, ImportResult};
+use crate::models::locale::{LocaleChoice, LocaleConfig};
 use crate::models::meta::IdContainer;
-use crate::models::post::{DraftPostArgs, Post, PostHydrated, PostVersion};
+use crate::models::page::{Page, PageArgs};
+use crate::models::post::{
+    DraftConflict, DraftError, DraftPostArgs, Post, PostHydrated, PostSummary, PostVersion,
+    PurgeArgs, PurgeResult, RelatedSource, ScheduleArgs, ScheduledAction, ScheduledChange,
+    ScheduledDraft, Trash,
+};
+use crate::models::search::{SearchArgs, SearchRank, SearchResult};
+use crate::related::rank_related;
 use crate::repos::r_posts::PostsRepo;
-use crate::utils::thing_from_string;
+use crate::utils::{
+    datetime_string, highlight_snippet, is_record_id, mark_highlights, thing_from_string,
+};
+
+/// Characters of markdown kept either side of the first match in a search snippet.
+const SNIPPET_RADIUS: usize = 80;
+
+/// Related posts stored for each post, and so the largest `limit` a caller can ask for.
+const MAX_RELATED_POSTS: usize = 10;
+
+/// Related posts returned when the caller does not pass `limit`.
+const DEFAULT_RELATED_POSTS: usize = 5;
 
 #[derive(Debug, Clone)]
 pub struct PostsService {
     repo: PostsRepo,
     conn: SurrealDBConnection,
+    locales: LocaleConfig,
+    /// Set when a post is published, until the related posts are next recomputed.
+    related_stale: Arc<AtomicBool>,
 }
 
 impl PostsService {
-    pub async fn new(conn: SurrealDBConnection) -> Self {
+    pub async fn new(conn: SurrealDBConnection, locales: LocaleConfig) -> Self {
         Self {
             repo: PostsRepo::new(),
             conn,
+            locales,
+            // nothing says the stored rankings are current when the server starts
+            related_stale: Arc::new(AtomicBool::new(true)),
         }
     }
 
+    /// Languages posts can be written in.
+    pub fn locales(&self) -> &LocaleConfig {
+        &self.locales
+    }
+
     #[instrument(skip(self))]
     pub async fn get_post(&self, post_id: String) -> Post {
         info!("s: get post");
@@ -38,17 +77,21 @@ impl PostsService {
     }
 
     #[instrument(skip(self))]
-    pub async fn get_posts(&self) -> Vec<PostHydrated> {
+    pub async fn get_posts(&self, page: PageArgs) -> Page<PostHydrated> {
         info!("s: get posts");
 
         let db = NovaDB::new(&self.conn).await.expect("db connect failed");
 
         let mut resp = db
-            .exec(self.repo.query_select_posts())
+            .exec(self.repo.query_select_posts(&page))
             .await
             .expect("db query failed");
 
-        resp.take_vec::<PostHydrated>(0).unwrap_or_default()
+        // Statement indices: 0=SELECT page, 1=RETURN total
+        let posts = resp.take_vec::<PostHydrated>(0).unwrap_or_default();
+        let total = resp.take_one::<u64>(1).unwrap_or_default();
+
+        Page::new(posts, page.limit(), total, |p| &p.id)
     }
 
     #[instrument(skip(self))]
@@ -67,7 +110,7 @@ impl PostsService {
     }
 
     #[instrument(skip(self))]
-    pub async fn get_draft(&self, draft_id: String) -> PostVersion {
+    pub async fn get_draft(&self, draft_id: String) -> Option<PostVersion> {
         let db = NovaDB::new(&self.conn).await.expect("db connect failed");
 
         let mut resp = db
@@ -75,7 +118,71 @@ impl PostsService {
             .await
             .expect("db query failed");
 
-        resp.take_one::<PostVersion>(0).expect("draft not found")
+        resp.take_vec::<PostVersion>(0)
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+    }
+
+    /// Diffs the title, markdown and image of two versions of a post.
+    ///
+    /// Returns `None` if either side cannot be found or belongs to a different post.
+    #[instrument(skip(self))]
+    pub async fn diff_drafts(&self, post_id: String, args: DiffArgs) -> Option<DraftDiff> {
+        let lang = match args.lang.as_deref() {
+            Some(lang) => self.locales.supported(lang)?,
+            None => self.locales.fallback.clone(),
+        };
+
+        let from = self
+            .resolve_version(&post_id, args.from.as_deref().unwrap_or("published"), &lang)
+            .await?;
+        let to = self
+            .resolve_version(&post_id, args.to.as_deref().unwrap_or("latest"), &lang)
+            .await?;
+
+        Some(DraftDiff {
+            title: diff_field(&from.title, &to.title),
+            markdown: diff_field(from.markdown(), to.markdown()),
+            image: diff_field(&from.image, &to.image),
+            from,
+            to,
+        })
+    }
+
+    /// Looks up a version of a post by draft id, or by the `published` and `latest` keywords
+    /// in the translation `lang`.
+    #[instrument(skip(self))]
+    async fn resolve_version(
+        &self,
+        post_id: &str,
+        version: &str,
+        lang: &str,
+    ) -> Option<PostVersion> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let q = match version {
+            "published" => self.repo.query_select_published_draft(post_id, lang),
+            "latest" => self.repo.query_select_latest_draft(post_id, lang),
+            draft_id => {
+                let is_draft_id = draft_id
+                    .split_once(':')
+                    .is_some_and(|(_, key)| Ulid::from_str(key).is_ok());
+                if !is_draft_id {
+                    return None;
+                }
+                self.repo.query_select_draft(draft_id)
+            }
+        };
+
+        let mut resp = db.exec(q).await.expect("db query failed");
+
+        let version = match version {
+            "published" | "latest" => resp.take_opt::<PostVersion>(0).unwrap_or(None),
+            _ => resp.take_vec::<PostVersion>(0).unwrap_or_default().pop(),
+        };
+
+        version.filter(|v| v.id == post_id)
     }
 
     /// Create a new draft for a post.
@@ -83,10 +190,21 @@ impl PostsService {
     /// If `draft.id` is `Some`, adds a new draft to an existing post (no transaction needed).
     /// If `draft.id` is `None`, creates a new post + draft atomically in a transaction.
     #[instrument(skip(self))]
-    pub async fn create_draft(&self, draft: DraftPostArgs, author_id: String) -> PostVersion {
+    pub async fn create_draft(
+        &self,
+        mut draft: DraftPostArgs,
+        author_id: String,
+    ) -> Result<PostVersion, DraftError> {
+        draft.validate_fields()?;
+        let lang = self.draft_lang(&draft)?;
+
         let db = NovaDB::new(&self.conn).await.expect("db connect failed");
 
-        // Case A: existing post — just add a new draft version.
+        // a published draft is always a checkpoint
+        let working = draft.autosave && !draft.published;
+        let tags = draft.normalized_tags();
+
+        // Case A: existing post — save into the author's working copy or add a new draft version.
         if let Some(post_id) = draft.id {
             info!(
                 "post id exists on draft! adding draft to post: {:#?}",
@@ -98,22 +216,65 @@ impl PostsService {
                 .query_create_draft()
                 .bind("person_id", thing_from_string(&author_id))
                 .bind("post_id", thing_from_string(&post_id))
+                .bind("lang", lang)
                 .bind("title", draft.title)
                 .bind("markdown", draft.markdown)
                 .bind("published", draft.published)
-                .bind("image", draft.image);
+                .bind("image", draft.image)
+                .bind("tags", tags)
+                .bind("excerpt", draft.excerpt)
+                .bind("cover_alt", draft.cover_alt)
+                .bind("seo_title", draft.seo_title)
+                .bind("meta_description", draft.meta_description)
+                .bind("canonical_url", draft.canonical_url)
+                .bind("publish_at", datetime_string(draft.publish_at))
+                .bind("unpublish_at", datetime_string(draft.unpublish_at))
+                .bind(
+                    "base_draft_id",
+                    draft.base_draft_id.as_deref().map(thing_from_string),
+                )
+                .bind("force", draft.force)
+                .bind("working", working);
 
-            let mut resp = db.exec(q).await.expect("db query failed");
+            // the conflict check and the write must see the same latest draft
+            let tx = db.begin().await.expect("tx start failed");
+            let mut resp: NovaResponse = tx
+                .query(&q.sql)
+                .bind(q.args)
+                .await
+                .expect("create draft failed")
+                .into();
+            tx.commit().await.expect("tx commit failed");
 
             // Statement indices in query_create_draft (LET counted in SurrealDB v3):
-            //   0: LET $drafted_id
-            //   1: LET $meta_id
-            //   2: IF post-not-found check (NONE or throws)
-            //   3: RELATE (draft relation)
-            //   4: SELECT drafted with meta join
-            return resp
-                .take_one::<PostVersion>(4)
+            //   0: LET $meta_id
+            //   1: IF post-not-found check (NONE or throws)
+            //   2: LET $working_id
+            //   3: LET $drafted_id
+            //   4: LET $latest_id
+            //   5: LET $own_working
+            //   6: LET $conflict
+            //   7: IF !$conflict RELATE or UPDATE working copy
+            //   8: IF !$conflict AND $published { publish }
+            //   9: SELECT saved or conflicting draft with meta join
+            //  10: RETURN $conflict
+            let version = resp
+                .take_one::<PostVersion>(9)
                 .expect("draft create failed");
+
+            if resp.take_one::<bool>(10).unwrap_or(false) {
+                info!("draft conflicts with newer draft: {}", &version.draft_id);
+                return Err(DraftError::Conflict(Box::new(DraftConflict {
+                    base_draft_id: draft.base_draft_id.unwrap_or_default(),
+                    latest: version,
+                })));
+            }
+
+            if version.published == Some(true) {
+                self.mark_related_stale();
+            }
+
+            return Ok(version);
         }
 
         // Case B: no post — create post + meta + draft atomically.
@@ -128,40 +289,51 @@ impl PostsService {
             RELATE $person_id->drafted->$post_id
                 SET
                     id = $drafted_id,
+                    lang = $lang,
                     title = $title,
                     markdown = $markdown,
                     published = $published,
                     at = time::now(),
                     image = $image,
+                    tags = $tags,
+                    excerpt = $excerpt,
+                    cover_alt = $cover_alt,
+                    seo_title = $seo_title,
+                    meta_description = $meta_description,
+                    canonical_url = $canonical_url,
                     visits = 0,
+                    publish_at = (IF !type::is_none($publish_at) THEN <datetime>$publish_at END),
+                    unpublish_at = (IF !type::is_none($unpublish_at) THEN <datetime>$unpublish_at END),
+                    working = $working,
                     meta = $meta_id;
 
             SELECT
-                fn::string_id(id) as draft_id,
-                fn::string_id(in) as author,
-                fn::string_id(out) as id,
-                at,
-                title,
-                markdown,
-                published,
-                image,
-                visits,
                 {}
             FROM ONLY drafted
             WHERE id = $drafted_id
             LIMIT 1;
             "#,
             self.repo.meta.sql_create_meta("$meta_id"),
-            self.repo.meta.select_meta_string
+            self.repo.select_version_string
         );
 
         let q = NovaQuery::new(sql)
             .bind("created_by", thing_from_string(&author_id))
             .bind("person_id", thing_from_string(&author_id))
+            .bind("lang", lang)
             .bind("title", draft.title)
             .bind("markdown", draft.markdown)
             .bind("published", draft.published)
-            .bind("image", draft.image);
+            .bind("image", draft.image)
+            .bind("tags", tags)
+            .bind("excerpt", draft.excerpt)
+            .bind("cover_alt", draft.cover_alt)
+            .bind("seo_title", draft.seo_title)
+            .bind("meta_description", draft.meta_description)
+            .bind("canonical_url", draft.canonical_url)
+            .bind("publish_at", datetime_string(draft.publish_at))
+            .bind("unpublish_at", datetime_string(draft.unpublish_at))
+            .bind("working", working);
 
         let tx = db.begin().await.expect("tx start failed");
         let mut resp: NovaResponse = tx
@@ -180,34 +352,256 @@ impl PostsService {
         //   4: LET $drafted_id
         //   5: RELATE (draft relation)
         //   6: SELECT drafted with meta join
-        resp.take_one::<PostVersion>(6)
-            .expect("draft create failed")
+        Ok(resp
+            .take_one::<PostVersion>(6)
+            .expect("draft create failed"))
+    }
+
+    /// Import a post written outside the blog, keeping its dates.
+    ///
+    /// The first import of `args.source` creates a post, and the first in each other language
+    /// adds a translation to it. Later imports of the same source add a draft only when its
+    /// content changed, and change nothing otherwise, so importing again is safe. With
+    /// `dry_run` nothing is written and the result says what would have happened.
+    ///
+    /// Related posts are not refreshed, so call [`PostsService::refresh_related`] once
+    /// after importing.
+    #[instrument(skip(self, args), fields(source = %args.source))]
+    pub async fn import_post(
+        &self,
+        args: ImportPostArgs,
+        author_id: String,
+        dry_run: bool,
+    ) -> Result<ImportResult, ImportError> {
+        let ImportPostArgs {
+            source,
+            mut draft,
+            categories,
+            created_on,
+            updated_on,
+        } = args;
+
+        let invalid = |e: DraftError| match e {
+            DraftError::Invalid(message) => ImportError::Invalid(message),
+            DraftError::Conflict(_) => ImportError::Invalid("Draft conflict.".into()),
+            DraftError::NotFound => ImportError::Invalid("Draft not found.".into()),
+        };
+        draft.validate_fields().map_err(invalid)?;
+        let lang = self.draft_lang(&draft).map_err(invalid)?;
+        let tags = draft.normalized_tags();
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_imported_post(&source, &lang))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $post_id, 1=RETURN post id, 2=RETURN categories,
+        // 3=SELECT latest draft
+        let post_id = resp.take_opt::<String>(1).ok().flatten();
+        let stored_categories = resp.take_opt::<Vec<String>>(2).ok().flatten();
+        let latest = resp.take_opt::<PostVersion>(3).ok().flatten();
+
+        // categories live on the post, so a post imported before they were kept gets them
+        // the next time it is imported
+        let unchanged = latest.as_ref().is_some_and(|latest| {
+            stored_categories.as_ref() == Some(&categories)
+                && latest.title == draft.title
+                && latest.markdown() == draft.markdown
+                && latest.published.unwrap_or(false) == draft.published
+                && latest.image == draft.image
+                && latest.tags == tags
+                && latest.excerpt == draft.excerpt
+                && latest.cover_alt == draft.cover_alt
+                && latest.seo_title == draft.seo_title
+                && latest.meta_description == draft.meta_description
+                && latest.canonical_url == draft.canonical_url
+                && latest.publish_at == draft.publish_at
+        });
+        // a source's first draft in a language is a new translation, even of an existing post
+        let action = match (&latest, unchanged) {
+            (None, _) => ImportAction::Created,
+            (Some(_), false) => ImportAction::Updated,
+            (Some(_), true) => ImportAction::Unchanged,
+        };
+
+        if dry_run || action == ImportAction::Unchanged {
+            return Ok(ImportResult {
+                source,
+                action,
+                post_id,
+                draft_id: latest.filter(|_| unchanged).map(|l| l.draft_id),
+            });
+        }
+
+        info!("importing {:?}: {}", action, &source);
+
+        let q = match &post_id {
+            None => self.repo.query_import_post().bind("source", source.clone()),
+            Some(post_id) => self
+                .repo
+                .query_import_draft()
+                .bind("post_id", thing_from_string(post_id)),
+        }
+        .bind("created_by", thing_from_string(&author_id))
+        .bind("person_id", thing_from_string(&author_id))
+        .bind("created_on", datetime_string(Some(created_on)))
+        .bind("at", datetime_string(Some(updated_on)))
+        .bind("lang", lang)
+        .bind("title", draft.title)
+        .bind("markdown", draft.markdown)
+        .bind("published", draft.published)
+        .bind("image", draft.image)
+        .bind("tags", tags)
+        .bind("categories", categories)
+        .bind("excerpt", draft.excerpt)
+        .bind("cover_alt", draft.cover_alt)
+        .bind("seo_title", draft.seo_title)
+        .bind("meta_description", draft.meta_description)
+        .bind("canonical_url", draft.canonical_url)
+        .bind("publish_at", datetime_string(draft.publish_at));
+
+        let tx = db.begin().await.expect("tx start failed");
+        let mut resp: NovaResponse = tx
+            .query(&q.sql)
+            .bind(q.args)
+            .await
+            .expect("import post failed")
+            .into();
+        tx.commit().await.expect("tx commit failed");
+
+        // Statement indices in query_import_post (LET counted in SurrealDB v3):
+        //   0: LET $meta_id, 1: CREATE meta, 2: LET $post_id, 3: CREATE post,
+        //   4: LET $drafted_id, 5: RELATE, 6: SELECT drafted with meta join
+        // and in query_import_draft:
+        //   0: LET $meta_id, 1: UPDATE post, 2: LET $drafted_id, 3: RELATE,
+        //   4: IF publish or unpublish, 5: SELECT drafted with meta join
+        let index = if post_id.is_none() { 6 } else { 5 };
+        let version = resp
+            .take_one::<PostVersion>(index)
+            .expect("import post failed");
+
+        Ok(ImportResult {
+            source,
+            action,
+            post_id: Some(version.id),
+            draft_id: Some(version.draft_id),
+        })
+    }
+
+    /// The configured locale a draft is written in, the fallback when it names none.
+    fn draft_lang(&self, draft: &DraftPostArgs) -> Result<String, DraftError> {
+        match draft.lang.as_deref() {
+            Some(lang) => self
+                .locales
+                .supported(lang)
+                .ok_or_else(|| DraftError::Invalid(format!("Unsupported language: {}", lang))),
+            None => Ok(self.locales.fallback.clone()),
+        }
+    }
+
+    /// Freeze `person_id`'s working copy of a post's translation into an immutable checkpoint.
+    /// Without `lang` the fallback locale's working copy is checkpointed.
+    #[instrument(skip(self))]
+    pub async fn checkpoint_draft(
+        &self,
+        post_id: String,
+        lang: Option<String>,
+        person_id: String,
+    ) -> Option<PostVersion> {
+        let lang = match lang.as_deref() {
+            Some(lang) => self.locales.supported(lang)?,
+            None => self.locales.fallback.clone(),
+        };
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(
+                self.repo
+                    .query_checkpoint_working_draft(&post_id, &lang, &person_id),
+            )
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $working_id, 1=IF UPDATE, 2=SELECT draft with meta join
+        resp.take_vec::<PostVersion>(2)
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+    }
+
+    /// Create a new draft from an earlier version of a post, authored by `author_id`.
+    ///
+    /// When `publish` is true the new draft is published in the same transaction.
+    #[instrument(skip(self))]
+    pub async fn revert_draft(
+        &self,
+        draft_id: String,
+        author_id: String,
+        publish: bool,
+    ) -> PostVersion {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let q = self.repo.query_revert_draft(&draft_id, &author_id, publish);
+
+        let tx = db.begin().await.expect("tx start failed");
+        let mut resp: NovaResponse = tx
+            .query(&q.sql)
+            .bind(q.args)
+            .await
+            .expect("revert draft failed")
+            .into();
+        tx.commit().await.expect("tx commit failed");
+
+        // Statement indices (LET counted in SurrealDB v3):
+        //   0: LET $source
+        //   1: IF source-not-found check (NONE or throws)
+        //   2: LET $post_id
+        //   3: LET $drafted_id
+        //   4: UPDATE author's working copy into a checkpoint
+        //   5: RELATE (draft relation)
+        //   6: IF $publish { publish }
+        //   7: SELECT drafted with meta join
+        let version = resp
+            .take_one::<PostVersion>(7)
+            .expect("revert draft failed");
+
+        if publish {
+            self.mark_related_stale();
+        }
+
+        version
     }
 
-    /// Gets all current draft versions of posts that are not published.
+    /// Gets a page of the current draft versions of posts that are not published.
+    ///
+    /// Paged by post id, so the cursor refers to the post rather than the draft.
     #[instrument(skip(self))]
-    pub async fn get_drafted_posts(&self) -> Vec<PostVersion> {
+    pub async fn get_drafted_posts(&self, page: PageArgs) -> Page<PostVersion> {
         let db = NovaDB::new(&self.conn).await.expect("db connect failed");
 
         let mut resp = db
-            .exec(self.repo.query_select_unpublished_post_ids())
+            .exec(self.repo.query_select_unpublished_post_ids(&page))
             .await
             .expect("db query failed");
 
         // Statement indices (LET counted in SurrealDB v3):
         //   0: LET $published
         //   1: LET $unpublished
-        //   2: RETURN array::distinct($unpublished)
+        //   2: SELECT page of post ids
+        //   3: RETURN total
         let ids: Vec<IdContainer> = resp.take_vec(2).unwrap_or_default();
+        let total = resp.take_one::<u64>(3).unwrap_or_default();
 
-        let unpublished_post_ids: Vec<String> = ids.into_iter().map(|c| c.id).collect();
+        let ids = Page::new(ids, page.limit(), total, |c| &c.id);
 
-        join_all(
-            unpublished_post_ids
-                .into_iter()
-                .map(|p| self.get_current_draft(p)),
-        )
-        .await
+        Page {
+            items: join_all(ids.items.into_iter().map(|c| self.get_current_draft(c.id))).await,
+            next_cursor: ids.next_cursor,
+            total: ids.total,
+        }
     }
 
     /// Gets the most recent unpublished draft for the given post id.
@@ -224,24 +618,13 @@ impl PostsService {
             .expect("current draft not found")
     }
 
-    /// Publish a draft: unpublishes all other drafts for that post, then publishes this one.
+    /// Publish a draft: unpublishes the other drafts of that post in the same language, then
+    /// publishes this one.
     #[instrument(skip(self))]
     pub async fn publish_draft(&self, draft_id: String) -> bool {
         let db = NovaDB::new(&self.conn).await.expect("db connect failed");
 
-        // Single transactional query:
-        // 1. Find the post for this draft
-        // 2. Unpublish all drafts for that post
-        // 3. Publish the target draft
-        let q = NovaQuery::new(
-            r#"
-            LET $post_id = (SELECT out FROM ONLY drafted WHERE id = $draft_id LIMIT 1).out;
-            UPDATE drafted SET published = false WHERE out = $post_id;
-            UPDATE $draft_id SET published = true;
-            RETURN true;
-            "#,
-        )
-        .bind("draft_id", thing_from_string(&draft_id));
+        let q = self.repo.query_publish_draft(&draft_id);
 
         let tx = db.begin().await.expect("tx start failed");
         let mut resp: NovaResponse = tx
@@ -253,24 +636,174 @@ impl PostsService {
         tx.commit().await.expect("tx commit failed");
 
         // Statement indices (LET counted in SurrealDB v3):
-        //   0: LET $post_id
-        //   1: UPDATE drafted (unpublish all)
+        //   0: LET $publishing
+        //   1: UPDATE drafted (unpublish the same language)
         //   2: UPDATE $draft_id (publish)
         //   3: RETURN true
-        resp.take_one::<bool>(3).unwrap_or(false)
+        let published = resp.take_one::<bool>(3).unwrap_or(false);
+
+        if published {
+            self.mark_related_stale();
+        }
+
+        published
+    }
+
+    /// Set or clear the times a draft is automatically published and unpublished. Drafts in
+    /// the trash can't be scheduled.
+    #[instrument(skip(self))]
+    pub async fn schedule_draft(
+        &self,
+        draft_id: String,
+        schedule: ScheduleArgs,
+    ) -> Result<PostVersion, DraftError> {
+        schedule.validate()?;
+        if !is_record_id(&draft_id, "drafted") {
+            return Err(DraftError::NotFound);
+        }
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_schedule_draft(
+                &draft_id,
+                datetime_string(schedule.publish_at),
+                datetime_string(schedule.unpublish_at),
+            ))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=UPDATE, 1=SELECT draft with meta join
+        resp.take_opt::<PostVersion>(1)
+            .expect("draft schedule failed")
+            .ok_or(DraftError::NotFound)
+    }
+
+    /// Gets every pending scheduled publish and unpublish, soonest first.
+    #[instrument(skip(self))]
+    pub async fn get_scheduled_changes(&self) -> Vec<ScheduledChange> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_scheduled_drafts())
+            .await
+            .expect("db query failed");
+
+        let drafts = resp.take_vec::<ScheduledDraft>(0).unwrap_or_default();
+
+        let mut changes: Vec<ScheduledChange> = drafts
+            .into_iter()
+            .flat_map(|d| {
+                [
+                    (ScheduledAction::Publish, d.publish_at),
+                    (ScheduledAction::Unpublish, d.unpublish_at),
+                ]
+                .into_iter()
+                .filter_map(|(action, at)| {
+                    at.map(|at| ScheduledChange {
+                        id: d.id.clone(),
+                        draft_id: d.draft_id.clone(),
+                        title: d.title.clone(),
+                        action,
+                        at,
+                    })
+                })
+                .collect::<Vec<_>>()
+            })
+            .collect();
+
+        changes.sort_by_key(|c| c.at);
+        changes
+    }
+
+    /// Applies every scheduled publish and unpublish that is due.
+    ///
+    /// Each publish claims its schedule in the same transaction that publishes the draft,
+    /// so a change is applied at most once even if runs overlap or the server restarts.
+    /// Returns the ids of the drafts that were published and unpublished.
+    #[instrument(skip(self))]
+    pub async fn apply_scheduled_changes(&self) -> (Vec<String>, Vec<String>) {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_due_publish_ids())
+            .await
+            .expect("db query failed");
+
+        let due: Vec<IdContainer> = resp.take_vec(0).unwrap_or_default();
+
+        let mut published = vec![];
+        for draft in due {
+            let q = self.repo.query_publish_scheduled_draft(&draft.id);
+
+            let tx = db.begin().await.expect("tx start failed");
+            let mut resp: NovaResponse = tx
+                .query(&q.sql)
+                .bind(q.args)
+                .await
+                .expect("scheduled publish failed")
+                .into();
+            tx.commit().await.expect("tx commit failed");
+
+            // Statement indices (LET counted in SurrealDB v3):
+            //   0: LET $claimed
+            //   1: IF claimed { publish }
+            //   2: RETURN claimed
+            if resp.take_one::<bool>(2).unwrap_or(false) {
+                info!("published scheduled draft: {}", &draft.id);
+                published.push(draft.id);
+            }
+        }
+
+        // unpublishing touches a single draft, so one statement is enough to claim and apply it
+        let mut resp = db
+            .exec(self.repo.query_unpublish_due_drafts())
+            .await
+            .expect("db query failed");
+
+        let unpublished: Vec<String> = resp
+            .take_vec::<IdContainer>(0)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+
+        for draft_id in &unpublished {
+            info!("unpublished scheduled draft: {}", draft_id);
+        }
+
+        if !published.is_empty() {
+            self.mark_related_stale();
+        }
+
+        (published, unpublished)
     }
 
-    /// Gets all published post versions.
+    /// Gets a page of published post versions, without their markdown, in the chosen
+    /// language or else the fallback.
     #[instrument(skip(self))]
-    pub async fn get_published_posts(&self) -> Vec<PostVersion> {
+    pub async fn get_published_posts(
+        &self,
+        page: PageArgs,
+        locale: LocaleChoice,
+    ) -> Page<PostSummary> {
         let db = NovaDB::new(&self.conn).await.expect("db connect failed");
 
         let mut resp = db
-            .exec(self.repo.query_select_published_posts())
+            .exec(self.repo.query_select_published_posts(&page, &locale))
             .await
             .expect("db query failed");
 
-        resp.take_vec::<PostVersion>(0).unwrap_or_default()
+        // Statement indices: 0=LET $published_in_lang, 1=SELECT page, 2=RETURN total
+        let posts: Vec<PostSummary> = resp
+            .take_vec::<PostSummary>(1)
+            .unwrap_or_default()
+            .into_iter()
+            .map(PostSummary::summarize)
+            .collect();
+        let total = resp.take_one::<u64>(2).unwrap_or_default();
+
+        Page::new(posts, page.limit(), total, |p| &p.draft_id)
     }
 
     /// Unpublish the draft with the given draft id.
@@ -291,13 +824,331 @@ impl PostsService {
             .unwrap_or(false)
     }
 
+    /// Full-text search over the currently published version of each post.
+    ///
+    /// Results are ranked by relevance, so the cursor is an offset into the ranking
+    /// rather than a record id.
+    #[instrument(skip(self))]
+    pub async fn search_posts(&self, args: SearchArgs, locale: LocaleChoice) -> Page<SearchResult> {
+        let terms = args.q.trim();
+        if terms.is_empty() {
+            return Page {
+                items: vec![],
+                next_cursor: None,
+                total: 0,
+            };
+        }
+
+        let limit = PageArgs {
+            limit: args.limit,
+            ..Default::default()
+        }
+        .limit();
+        let start = args.cursor.unwrap_or(0);
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(
+                self.repo
+                    .query_search_published_posts(terms, start, limit, &locale),
+            )
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $published_in_lang, 1=SELECT ranked page, 2=RETURN total
+        let rows = resp
+            .take_vec::<serde_json::Value>(1)
+            .expect("search failed");
+        let total = resp.take_one::<u64>(2).unwrap_or_default();
+
+        let has_more = rows.len() > limit as usize;
+        let items = rows
+            .into_iter()
+            .take(limit as usize)
+            .map(|row| {
+                let rank: SearchRank =
+                    serde_json::from_value(row.clone()).expect("search rank malformed");
+                let post = serde_json::from_value::<PostSummary>(row)
+                    .expect("search result malformed")
+                    .summarize();
+
+                SearchResult {
+                    score: rank.score.unwrap_or_default(),
+                    title: mark_highlights(
+                        &rank.title_highlight.unwrap_or_else(|| post.title.clone()),
+                    ),
+                    snippet: highlight_snippet(
+                        &rank.markdown_highlight.unwrap_or_default(),
+                        SNIPPET_RADIUS,
+                    ),
+                    post,
+                }
+            })
+            .collect();
+
+        Page {
+            items,
+            next_cursor: has_more.then(|| (start + limit).to_string()),
+            total,
+        }
+    }
+
+    /// Published posts related to `post_id`, most related first.
+    ///
+    /// Reads the ranking stored by [`PostsService::refresh_related`], so a post that was
+    /// unpublished since is skipped rather than ranked again.
+    #[instrument(skip(self))]
+    pub async fn get_related_posts(
+        &self,
+        post_id: String,
+        limit: Option<usize>,
+        locale: LocaleChoice,
+    ) -> Vec<PostSummary> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_related_posts(&post_id, &locale))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $related, 1=LET $published_in_lang, 2=SELECT summaries,
+        // 3=RETURN $related
+        let mut summaries: HashMap<String, PostSummary> = resp
+            .take_vec::<PostSummary>(2)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|s| (s.id.clone(), s))
+            .collect();
+        let related: Vec<String> = resp.take_one(3).unwrap_or_default();
+
+        let limit = limit
+            .unwrap_or(DEFAULT_RELATED_POSTS)
+            .clamp(1, MAX_RELATED_POSTS);
+
+        related
+            .into_iter()
+            .filter_map(|id| summaries.remove(&id))
+            .take(limit)
+            .map(PostSummary::summarize)
+            .collect()
+    }
+
+    /// Put drafts saved before posts could be translated in the default language. Cheap
+    /// once done, so it runs every time the api starts.
+    #[instrument(skip(self))]
+    pub async fn backfill_draft_langs(&self) {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_backfill_draft_lang(&self.locales.fallback))
+            .await
+            .expect("db query failed");
+
+        let updated = resp.take_one::<u64>(0).unwrap_or_default();
+        if updated > 0 {
+            info!(
+                "s: set lang {} on {} drafts",
+                &self.locales.fallback, updated
+            );
+        }
+    }
+
+    /// Recompute the related posts on the next run of the related refresher, rather than
+    /// in the request that published a post.
+    fn mark_related_stale(&self) {
+        self.related_stale.store(true, Ordering::Relaxed);
+    }
+
+    /// Recompute the related posts if a post was published since they were last computed.
+    ///
+    /// Run by the related refresher, so any number of publishes between two runs cost a
+    /// single recompute.
+    pub async fn refresh_related_if_stale(&self) {
+        if self.related_stale.swap(false, Ordering::Relaxed) {
+            self.refresh_related().await;
+        }
+    }
+
+    /// Recompute and store the related posts of every published post.
+    ///
+    /// A new post changes both what it is related to and how rare each term is across the
+    /// blog, so every post is ranked again.
+    #[instrument(skip(self))]
+    pub async fn refresh_related(&self) {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(
+                self.repo
+                    .query_select_related_sources(&self.locales.fallback_choice()),
+            )
+            .await
+            .expect("db query failed");
+        // Statement indices: 0=LET $published_in_lang, 1=SELECT published drafts
+        let published: Vec<RelatedSource> = resp.take_vec(1).unwrap_or_default();
+
+        let related = rank_related(&published, MAX_RELATED_POSTS);
+        if related.is_empty() {
+            return;
+        }
+
+        db.exec(self.repo.query_set_related(related.into_iter().collect()))
+            .await
+            .expect("store related posts failed");
+
+        info!("s: refreshed related posts for {} posts", published.len());
+    }
+
+    /// Move a post and all of its drafts to the trash. Trashed posts are hidden from every
+    /// listing until restored or purged.
+    #[instrument(skip(self))]
+    pub async fn trash_post(&self, post_id: String, person_id: String) -> bool {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_trash_post(&post_id, &person_id))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $meta_id, 1=UPDATE meta, 2=RETURN found
+        resp.take_one::<bool>(2).unwrap_or(false)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn restore_post(&self, post_id: String) -> bool {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_restore_post(&post_id))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $meta_id, 1=UPDATE meta, 2=RETURN found
+        resp.take_one::<bool>(2).unwrap_or(false)
+    }
+
+    /// Move a single draft to the trash, leaving the rest of its post untouched.
+    #[instrument(skip(self))]
+    pub async fn trash_draft(&self, draft_id: String, person_id: String) -> bool {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_trash_draft(&draft_id, &person_id))
+            .await
+            .expect("db query failed");
+
+        !resp
+            .take_vec::<IdContainer>(0)
+            .unwrap_or_default()
+            .is_empty()
+    }
+
+    #[instrument(skip(self))]
+    pub async fn restore_draft(&self, draft_id: String) -> bool {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_restore_draft(&draft_id))
+            .await
+            .expect("db query failed");
+
+        !resp
+            .take_vec::<IdContainer>(0)
+            .unwrap_or_default()
+            .is_empty()
+    }
+
+    /// Trashed posts, most recently trashed first, followed by drafts trashed on their own.
+    #[instrument(skip(self))]
+    pub async fn get_trash(&self) -> Trash {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_trash())
+            .await
+            .expect("db query failed");
+
+        Trash {
+            posts: resp.take_vec::<PostHydrated>(0).unwrap_or_default(),
+            drafts: resp.take_vec::<PostVersion>(1).unwrap_or_default(),
+        }
+    }
+
+    /// Permanently delete everything that has been in the trash for longer than
+    /// `older_than_days`.
+    #[instrument(skip(self))]
+    pub async fn purge_trash(&self, args: PurgeArgs) -> PurgeResult {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let cutoff = OffsetDateTime::now_utc() - Duration::days(args.older_than_days.into());
+        let q = self
+            .repo
+            .query_purge_trash(datetime_string(Some(cutoff)).expect("cutoff format failed"));
+
+        let tx = db.begin().await.expect("tx start failed");
+        let mut resp: NovaResponse = tx
+            .query(&q.sql)
+            .bind(q.args)
+            .await
+            .expect("purge trash failed")
+            .into();
+        tx.commit().await.expect("tx commit failed");
+
+        // Statement indices (LET counted in SurrealDB v3):
+        //   0: LET $posts
+        //   1: LET $metas
+        //   2: LET $drafts
+        //   3: LET $comment_metas
+        //   4: LET $post_keys
+        //   5-11: DELETE commented, reacted, post_view, post_visitor, drafted, post, meta
+        //   12: UPDATE related posts
+        //   13: RETURN posts purged
+        //   14: RETURN drafts purged
+        let result = PurgeResult {
+            posts: resp.take_one::<u64>(13).unwrap_or_default(),
+            drafts: resp.take_one::<u64>(14).unwrap_or_default(),
+        };
+
+        info!(
+            posts = result.posts,
+            drafts = result.drafts,
+            "s: purged trash"
+        );
+
+        result
+    }
+
     #[instrument(skip(self))]
-    pub async fn get_random_post(&self) -> PostVersion {
-        let published_posts = self.get_published_posts().await;
+    pub async fn get_random_post(&self, locale: LocaleChoice) -> PostVersion {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_random_published_post(&locale))
+            .await
+            .expect("db query failed");
 
-        published_posts
-            .choose(&mut rand::thread_rng())
+        // Statement indices: 0=LET $published_in_lang, 1=SELECT random draft
+        resp.take_first::<PostVersion>(1)
             .expect("unable to choose random published post.")
-            .to_owned()
+    }
+
+    /// The published version of a post in the chosen language, else in the fallback, else
+    /// in any language it was published in.
+    #[instrument(skip(self))]
+    pub async fn get_published_post(
+        &self,
+        post_id: String,
+        locale: LocaleChoice,
+    ) -> Option<PostVersion> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_published_translations(&post_id))
+            .await
+            .expect("db query failed");
+
+        let translations = resp.take_vec::<PostVersion>(0).unwrap_or_default();
+        locale.choose(translations, |t| &t.lang)
     }
 }
@@ -0,0 +1,76 @@
+use tracing::instrument;
+
+use crate::db::nova_db::NovaDB;
+use crate::db::SurrealDBConnection;
+use crate::head::{head_metadata, oembed};
+use crate::models::feed::SiteConfig;
+use crate::models::head::{HeadMetadata, OEmbed, OEmbedArgs, OEmbedError};
+use crate::models::locale::{LocaleChoice, LocaleConfig};
+use crate::models::post::PostVersion;
+use crate::repos::r_posts::PostsRepo;
+
+#[derive(Debug, Clone)]
+pub struct PreviewsService {
+    repo: PostsRepo,
+    conn: SurrealDBConnection,
+    site: SiteConfig,
+    locales: LocaleConfig,
+}
+
+impl PreviewsService {
+    pub async fn new(conn: SurrealDBConnection, site: SiteConfig, locales: LocaleConfig) -> Self {
+        Self {
+            repo: PostsRepo::new(),
+            conn,
+            site,
+            locales,
+        }
+    }
+
+    pub fn locales(&self) -> &LocaleConfig {
+        &self.locales
+    }
+
+    /// Open Graph, Twitter card and JSON-LD metadata for a published post, in the
+    /// translation `locale` chooses.
+    #[instrument(skip(self))]
+    pub async fn get_head_metadata(
+        &self,
+        post_id: String,
+        locale: LocaleChoice,
+    ) -> Option<HeadMetadata> {
+        self.get_published(&post_id, &locale)
+            .await
+            .map(|post| head_metadata(&post, &self.site))
+    }
+
+    /// oEmbed response for the public URL of a published post.
+    #[instrument(skip(self))]
+    pub async fn get_oembed(&self, args: OEmbedArgs) -> Result<OEmbed, OEmbedError> {
+        if args.format.as_deref().is_some_and(|f| f != "json") {
+            return Err(OEmbedError::UnsupportedFormat);
+        }
+
+        let post_id = self
+            .site
+            .post_id_from_url(&args.url)
+            .ok_or(OEmbedError::NotFound)?;
+
+        self.get_published(&post_id, &self.locales.fallback_choice())
+            .await
+            .map(|post| oembed(&post, &self.site))
+            .ok_or(OEmbedError::NotFound)
+    }
+
+    async fn get_published(&self, post_id: &str, locale: &LocaleChoice) -> Option<PostVersion> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_published_translations(post_id))
+            .await
+            .expect("db query failed");
+
+        let translations = resp.take_vec::<PostVersion>(0).unwrap_or_default();
+        locale.choose(translations, |t| &t.lang)
+    }
+}
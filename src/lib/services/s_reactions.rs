@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use tracing::{info, instrument};
+
+use crate::db::nova_db::NovaDB;
+use crate::db::SurrealDBConnection;
+use crate::models::page::Page;
+use crate::models::post::{PostSummary, PostVersion};
+use crate::models::reaction::{OwnReaction, PostReactions, ReactionCount, ReactionError};
+use crate::repos::r_reactions::ReactionsRepo;
+
+#[derive(Debug, Clone)]
+pub struct ReactionsService {
+    repo: ReactionsRepo,
+    conn: SurrealDBConnection,
+    kinds: Vec<String>,
+}
+
+impl ReactionsService {
+    /// `kinds` is the set of reactions persons can choose from.
+    pub async fn new(conn: SurrealDBConnection, kinds: Vec<String>) -> Self {
+        Self {
+            repo: ReactionsRepo::new(),
+            conn,
+            kinds,
+        }
+    }
+
+    /// The reaction kinds persons can choose from.
+    pub fn kinds(&self) -> &[String] {
+        &self.kinds
+    }
+
+    /// React to a published post. Reacting again with the same kind changes nothing.
+    /// Returns the post's reactions afterwards.
+    #[instrument(skip(self))]
+    pub async fn add_reaction(
+        &self,
+        post_id: String,
+        person_id: String,
+        kind: String,
+    ) -> Result<PostReactions, ReactionError> {
+        self.check_kind(&kind)?;
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_add_reaction(&post_id, &person_id, &kind))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=LET $published, 1=LET $exists, 2=IF RELATE, 3=RETURN $published
+        if !resp.take_one::<bool>(3).unwrap_or_default() {
+            return Err(ReactionError::NotFound);
+        }
+
+        info!("{} reacted to post {}", &person_id, &post_id);
+
+        Ok(self.get_post_reactions(post_id, Some(person_id)).await)
+    }
+
+    /// Take back a reaction. Returns the post's reactions afterwards.
+    #[instrument(skip(self))]
+    pub async fn remove_reaction(
+        &self,
+        post_id: String,
+        person_id: String,
+        kind: String,
+    ) -> Result<PostReactions, ReactionError> {
+        self.check_kind(&kind)?;
+
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        db.exec(self.repo.query_remove_reaction(&post_id, &person_id, &kind))
+            .await
+            .expect("db query failed");
+
+        Ok(self.get_post_reactions(post_id, Some(person_id)).await)
+    }
+
+    /// Reactions on a single post, with `person_id`'s own reactions when given.
+    #[instrument(skip(self))]
+    pub async fn get_post_reactions(
+        &self,
+        post_id: String,
+        person_id: Option<String>,
+    ) -> PostReactions {
+        self.get_reactions(vec![post_id.clone()], person_id.as_deref())
+            .await
+            .remove(&post_id)
+            .unwrap_or_default()
+    }
+
+    /// Fills in the reactions of a version, when it is the published one.
+    #[instrument(skip(self, post))]
+    pub async fn with_reactions(
+        &self,
+        mut post: PostVersion,
+        person_id: Option<String>,
+    ) -> PostVersion {
+        if post.published == Some(true) {
+            post.reactions = Some(self.get_post_reactions(post.id.clone(), person_id).await);
+        }
+        post
+    }
+
+    /// Fills in the reactions of every published post in a listing.
+    #[instrument(skip(self, page))]
+    pub async fn with_page_reactions(
+        &self,
+        mut page: Page<PostSummary>,
+        person_id: Option<String>,
+    ) -> Page<PostSummary> {
+        let post_ids = page
+            .items
+            .iter()
+            .filter(|p| p.published == Some(true))
+            .map(|p| p.id.clone())
+            .collect::<Vec<_>>();
+
+        if post_ids.is_empty() {
+            return page;
+        }
+
+        let mut reactions = self.get_reactions(post_ids, person_id.as_deref()).await;
+        for post in page.items.iter_mut().filter(|p| p.published == Some(true)) {
+            post.reactions = Some(reactions.remove(&post.id).unwrap_or_default());
+        }
+        page
+    }
+
+    /// Reactions on each of the posts, keyed by post id. Posts nobody reacted to are left out.
+    async fn get_reactions(
+        &self,
+        post_ids: Vec<String>,
+        person_id: Option<&str>,
+    ) -> HashMap<String, PostReactions> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut resp = db
+            .exec(self.repo.query_select_reactions(post_ids, person_id))
+            .await
+            .expect("db query failed");
+
+        // Statement indices: 0=SELECT counts, 1=SELECT own reactions
+        let counts = resp.take_vec::<ReactionCount>(0).unwrap_or_default();
+        let own = resp.take_vec::<OwnReaction>(1).unwrap_or_default();
+
+        let mut reactions: HashMap<String, PostReactions> = HashMap::new();
+        for count in counts {
+            reactions
+                .entry(count.post)
+                .or_default()
+                .counts
+                .insert(count.kind, count.count);
+        }
+        for reaction in own {
+            reactions
+                .entry(reaction.post)
+                .or_default()
+                .mine
+                .push(reaction.kind);
+        }
+        reactions
+    }
+
+    fn check_kind(&self, kind: &str) -> Result<(), ReactionError> {
+        if self.kinds.iter().any(|k| k == kind) {
+            Ok(())
+        } else {
+            Err(ReactionError::UnknownKind(kind.to_string()))
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use tracing::instrument;
+
+use crate::db::nova_db::NovaDB;
+use crate::db::SurrealDBConnection;
+use crate::models::feed::SiteConfig;
+use crate::models::sitemap::{Sitemap, SitemapRow, SitemapUrl};
+use crate::sitemap::{write_index, write_urlsets, SitemapPage, SitemapSource};
+
+#[derive(Debug, Clone)]
+pub struct SitemapService {
+    conn: SurrealDBConnection,
+    site: SiteConfig,
+    sources: Arc<Vec<Box<dyn SitemapSource>>>,
+}
+
+impl SitemapService {
+    /// `sources` are the kinds of pages listed, in the order they appear in the sitemap.
+    pub async fn new(
+        conn: SurrealDBConnection,
+        site: SiteConfig,
+        sources: Vec<Box<dyn SitemapSource>>,
+    ) -> Self {
+        Self {
+            conn,
+            site,
+            sources: Arc::new(sources),
+        }
+    }
+
+    /// Every public URL in a single sitemap, or an index of sitemap pages when they do not
+    /// fit in one.
+    #[instrument(skip(self))]
+    pub async fn get_sitemap(&self) -> Sitemap {
+        let mut pages = self.get_pages().await;
+
+        if pages.len() == 1 {
+            Sitemap::Urlset(pages.remove(0).xml)
+        } else {
+            Sitemap::Index(write_index(&self.site, &pages))
+        }
+    }
+
+    /// One page of the sitemap, counting from 1.
+    #[instrument(skip(self))]
+    pub async fn get_sitemap_page(&self, page: usize) -> Option<String> {
+        let pages = self.get_pages().await;
+
+        page.checked_sub(1)
+            .and_then(|i| pages.into_iter().nth(i))
+            .map(|p| p.xml)
+    }
+
+    async fn get_pages(&self) -> Vec<SitemapPage> {
+        let db = NovaDB::new(&self.conn).await.expect("db connect failed");
+
+        let mut urls: Vec<SitemapUrl> = vec![];
+        for source in self.sources.iter() {
+            let mut resp = db.exec(source.query()).await.expect("db query failed");
+
+            urls.extend(
+                resp.take_vec::<SitemapRow>(0)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|row| SitemapUrl {
+                        loc: source.url(&self.site, &row.id),
+                        lastmod: row.lastmod,
+                    }),
+            );
+        }
+
+        write_urlsets(&urls)
+    }
+}
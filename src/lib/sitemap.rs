@@ -0,0 +1,197 @@
+use std::fmt::Debug;
+
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::constants::{SITEMAP_MAX_BYTES, SITEMAP_MAX_URLS};
+use crate::db::nova_db::NovaQuery;
+use crate::models::feed::SiteConfig;
+use crate::models::sitemap::SitemapUrl;
+use crate::repos::r_posts::PostsRepo;
+use crate::utils::xml_escape;
+
+const URLSET_OPEN: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+    "\n",
+    r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#
+);
+const URLSET_CLOSE: &str = "</urlset>";
+
+/// A kind of public page listed in the sitemap. Register more with the
+/// [`SitemapService`] to list other pages.
+///
+/// [`SitemapService`]: crate::services::s_sitemap::SitemapService
+pub trait SitemapSource: Debug + Send + Sync {
+    /// Query selecting the pages of this kind (returns Vec<SitemapRow>).
+    fn query(&self) -> NovaQuery;
+
+    /// Public URL of a page selected by [`SitemapSource::query`].
+    fn url(&self, site: &SiteConfig, id: &str) -> String;
+}
+
+/// Fixed pages of the site, like the home page, given as paths.
+#[derive(Debug, Clone)]
+pub struct StaticPages {
+    paths: Vec<String>,
+}
+
+impl StaticPages {
+    pub fn new(paths: &[&str]) -> Self {
+        Self {
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+impl SitemapSource for StaticPages {
+    fn query(&self) -> NovaQuery {
+        let rows = (0..self.paths.len())
+            .map(|i| format!("{{ id: $path_{i} }}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut q = NovaQuery::new(format!("RETURN [{rows}];"));
+        for (i, path) in self.paths.iter().enumerate() {
+            q = q.bind(&format!("path_{i}"), path.clone());
+        }
+        q
+    }
+
+    fn url(&self, site: &SiteConfig, id: &str) -> String {
+        format!("{}{}", site.url, id)
+    }
+}
+
+/// Every published post, last modified when its published version was drafted.
+#[derive(Debug, Clone)]
+pub struct PublishedPosts {
+    repo: PostsRepo,
+}
+
+impl Default for PublishedPosts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PublishedPosts {
+    pub fn new() -> Self {
+        Self {
+            repo: PostsRepo::new(),
+        }
+    }
+}
+
+impl SitemapSource for PublishedPosts {
+    fn query(&self) -> NovaQuery {
+        self.repo.query_select_sitemap_posts()
+    }
+
+    fn url(&self, site: &SiteConfig, id: &str) -> String {
+        site.post_url(id)
+    }
+}
+
+/// The profile of every author with a published post.
+#[derive(Debug, Clone)]
+pub struct AuthorProfiles {
+    repo: PostsRepo,
+}
+
+impl Default for AuthorProfiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuthorProfiles {
+    pub fn new() -> Self {
+        Self {
+            repo: PostsRepo::new(),
+        }
+    }
+}
+
+impl SitemapSource for AuthorProfiles {
+    fn query(&self) -> NovaQuery {
+        self.repo.query_select_sitemap_authors()
+    }
+
+    fn url(&self, site: &SiteConfig, id: &str) -> String {
+        site.author_url(id)
+    }
+}
+
+/// A sitemap file, along with the newest `lastmod` in it for the sitemap index.
+#[derive(Debug, Clone)]
+pub struct SitemapPage {
+    pub xml: String,
+    pub lastmod: Option<OffsetDateTime>,
+}
+
+/// Splits `urls` into as many sitemap files as needed to stay within the protocol's
+/// URL count and file size limits. Always returns at least one, possibly empty, file.
+pub fn write_urlsets(urls: &[SitemapUrl]) -> Vec<SitemapPage> {
+    let max_body = SITEMAP_MAX_BYTES - URLSET_OPEN.len() - URLSET_CLOSE.len();
+
+    let mut pages = vec![];
+    let mut body = String::new();
+    let mut count = 0;
+    let mut lastmod = None;
+
+    for url in urls {
+        let entry = url_entry(url);
+
+        if count > 0 && (count == SITEMAP_MAX_URLS || body.len() + entry.len() > max_body) {
+            pages.push(SitemapPage {
+                xml: format!("{URLSET_OPEN}{body}{URLSET_CLOSE}"),
+                lastmod,
+            });
+            body.clear();
+            count = 0;
+            lastmod = None;
+        }
+
+        body.push_str(&entry);
+        count += 1;
+        lastmod = lastmod.max(url.lastmod);
+    }
+
+    pages.push(SitemapPage {
+        xml: format!("{URLSET_OPEN}{body}{URLSET_CLOSE}"),
+        lastmod,
+    });
+    pages
+}
+
+/// A sitemap index pointing at each page, served at `/sitemaps/{n}.xml` counting from 1.
+pub fn write_index(site: &SiteConfig, pages: &[SitemapPage]) -> String {
+    let mut out = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    out.push('\n');
+    out.push_str(r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+
+    for (i, page) in pages.iter().enumerate() {
+        out.push_str("<sitemap>");
+        out.push_str(&format!(
+            "<loc>{}</loc>",
+            xml_escape(&format!("{}/sitemaps/{}.xml", site.url, i + 1))
+        ));
+        if let Some(lastmod) = page.lastmod.and_then(|l| l.format(&Rfc3339).ok()) {
+            out.push_str(&format!("<lastmod>{}</lastmod>", lastmod));
+        }
+        out.push_str("</sitemap>");
+    }
+
+    out.push_str("</sitemapindex>");
+    out
+}
+
+fn url_entry(url: &SitemapUrl) -> String {
+    let lastmod = url
+        .lastmod
+        .and_then(|l| l.format(&Rfc3339).ok())
+        .map(|l| format!("<lastmod>{}</lastmod>", l))
+        .unwrap_or_default();
+
+    format!("<url><loc>{}</loc>{}</url>", xml_escape(&url.loc), lastmod)
+}
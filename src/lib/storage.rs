@@ -0,0 +1,51 @@
+pub mod local;
+pub mod s3;
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+
+/// Where uploaded media files are kept. Files are addressed by a key made from their
+/// content hash, so a key always refers to the same bytes.
+#[async_trait]
+pub trait MediaStore: Debug + Send + Sync {
+    /// Stores `bytes` under `key`, replacing anything already there.
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<(), StoreError>;
+
+    /// The bytes stored under `key`, `None` when there are none.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError>;
+
+    /// Removes whatever is stored under `key`. Removing a missing key is not an error.
+    async fn delete(&self, key: &str) -> Result<(), StoreError>;
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    /// The key could escape the store, e.g. by containing a path separator.
+    InvalidKey(String),
+    Backend(String),
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidKey(key) => write!(f, "invalid media key: {}", key),
+            Self::Backend(message) => write!(f, "media store failed: {}", message),
+        }
+    }
+}
+
+/// Keys are `{hash}.{extension}`, so anything else is refused before it reaches a backend.
+pub fn check_key(key: &str) -> Result<(), StoreError> {
+    let valid = !key.is_empty()
+        && !key.starts_with('.')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidKey(key.to_string()))
+    }
+}
@@ -0,0 +1,56 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use tokio::fs;
+
+use super::{check_key, MediaStore, StoreError};
+
+/// Keeps media as files in a directory on the local filesystem.
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[async_trait]
+impl MediaStore for LocalStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> Result<(), StoreError> {
+        check_key(key)?;
+
+        fs::create_dir_all(&self.root).await.map_err(backend)?;
+
+        // write next to the final path and rename, so readers never see a partial file
+        let tmp = self.root.join(format!("{}.tmp", key));
+        fs::write(&tmp, bytes).await.map_err(backend)?;
+        fs::rename(&tmp, self.root.join(key)).await.map_err(backend)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        check_key(key)?;
+
+        match fs::read(self.root.join(key)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StoreError> {
+        check_key(key)?;
+
+        match fs::remove_file(self.root.join(key)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(backend(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn backend(e: std::io::Error) -> StoreError {
+    StoreError::Backend(e.to_string())
+}
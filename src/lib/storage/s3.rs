@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+
+use super::{check_key, MediaStore, StoreError};
+use crate::constants::MEDIA_CACHE_CONTROL;
+
+/// Connection settings for an S3 compatible bucket.
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    pub bucket: String,
+    pub region: String,
+    /// Custom endpoint for S3 compatible services, like a local MinIO. `None` uses AWS.
+    pub endpoint: Option<String>,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+/// Keeps media as objects in an S3 compatible bucket.
+#[derive(Debug, Clone)]
+pub struct S3Store {
+    client: Client,
+    bucket: String,
+}
+
+impl S3Store {
+    pub fn new(config: S3Config) -> Self {
+        let mut builder = aws_sdk_s3::config::Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(config.region))
+            .credentials_provider(Credentials::new(
+                config.access_key,
+                config.secret_key,
+                None,
+                None,
+                "nb_blog_api",
+            ));
+
+        if let Some(endpoint) = config.endpoint {
+            // most S3 stand-ins only understand path style bucket addressing
+            builder = builder.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        Self {
+            client: Client::from_conf(builder.build()),
+            bucket: config.bucket,
+        }
+    }
+}
+
+#[async_trait]
+impl MediaStore for S3Store {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<(), StoreError> {
+        check_key(key)?;
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .cache_control(MEDIA_CACHE_CONTROL)
+            .body(ByteStream::from(bytes))
+            .send()
+            .await
+            .map_err(|e| StoreError::Backend(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        check_key(key)?;
+
+        let object = match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(object) => object,
+            Err(e) => {
+                return match e.into_service_error() {
+                    GetObjectError::NoSuchKey(_) => Ok(None),
+                    e => Err(StoreError::Backend(e.to_string())),
+                }
+            }
+        };
+
+        let bytes = object
+            .body
+            .collect()
+            .await
+            .map_err(|e| StoreError::Backend(e.to_string()))?;
+
+        Ok(Some(bytes.into_bytes().to_vec()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StoreError> {
+        check_key(key)?;
+
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| StoreError::Backend(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The MinIO started by `cargo make start-s3`, unless the `S3_*` variables say otherwise.
+    fn local_store() -> S3Store {
+        let var = |name: &str, default: &str| std::env::var(name).unwrap_or(default.into());
+
+        S3Store::new(S3Config {
+            bucket: var("S3_BUCKET", "media"),
+            region: var("S3_REGION", "us-east-1"),
+            endpoint: Some(var("S3_ENDPOINT", "http://localhost:52002")),
+            access_key: var("S3_ACCESS_KEY", "minioadmin"),
+            secret_key: var("S3_SECRET_KEY", "minioadmin"),
+        })
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the local S3 stand-in, start it with `cargo make start-s3`"]
+    async fn put_get_and_delete_round_trip() {
+        let store = local_store();
+        let key = format!("{}.txt", ulid::Ulid::new().to_string().to_lowercase());
+        let bytes = b"stored in the local stand-in".to_vec();
+
+        assert_eq!(store.get(&key).await.unwrap(), None);
+
+        store.put(&key, bytes.clone(), "text/plain").await.unwrap();
+        assert_eq!(store.get(&key).await.unwrap(), Some(bytes));
+
+        store.delete(&key).await.unwrap();
+        assert_eq!(store.get(&key).await.unwrap(), None);
+
+        // deleting a missing key is not an error
+        store.delete(&key).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn invalid_keys_never_reach_the_bucket() {
+        let store = local_store();
+
+        assert!(matches!(
+            store.get("../secrets").await,
+            Err(StoreError::InvalidKey(_))
+        ));
+    }
+}
@@ -1,9 +1,58 @@
+use std::env;
 use std::str::FromStr;
 
 use surrealdb::types::{RecordId, RecordIdKey};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime, UtcOffset};
 use tracing::{debug, instrument};
 use ulid::Ulid;
 
+use crate::constants::{
+    DEFAULT_LOCALE, NB_DB_ADDRESS, NB_DB_NAME, NB_DB_NAMESPACE, NB_DB_PSWD, NB_DB_USER,
+    NB_DEFAULT_LOCALE, NB_LOCALES,
+};
+use crate::db::SurrealDBConnection;
+use crate::models::locale::LocaleConfig;
+
+pub fn get_env<T: From<std::string::String>>(env_key: &str) -> T {
+    env::var(env_key)
+        .unwrap_or_else(|_| panic!("cannot find {}", env_key))
+        .into()
+}
+
+/// Database connection details from the environment.
+pub fn db_connection() -> SurrealDBConnection {
+    SurrealDBConnection {
+        address: get_env(NB_DB_ADDRESS),
+        username: get_env(NB_DB_USER),
+        password: get_env(NB_DB_PSWD),
+        namespace: get_env(NB_DB_NAMESPACE),
+        database: get_env(NB_DB_NAME),
+    }
+}
+
+/// Languages posts are written in, from the environment.
+pub fn locale_config() -> LocaleConfig {
+    LocaleConfig::new(
+        env::var(NB_LOCALES)
+            .unwrap_or_default()
+            .split(',')
+            .map(|l| l.to_string())
+            .collect(),
+        env::var(NB_DEFAULT_LOCALE)
+            .ok()
+            .filter(|l| !l.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_LOCALE.into()),
+    )
+}
+
+/// Whether `thing_string` is a `"table:ulid"` id on `table`, i.e. one that
+/// [`thing_from_string`] accepts. Use it on ids taken from requests.
+pub fn is_record_id(thing_string: &str, table: &str) -> bool {
+    thing_string
+        .split_once(':')
+        .is_some_and(|(t, key)| t == table && Ulid::from_str(key).is_ok())
+}
+
 /// Creates a [`RecordId`] from a `"table:ulid"` string.
 ///
 /// Panics if the string does not contain exactly one `':'`, or if the part
@@ -37,3 +86,100 @@ pub fn record_id_to_string(id: RecordId) -> String {
         other => panic!("unexpected RecordIdKey variant: {:?}", other),
     }
 }
+
+/// Formats an optional datetime as RFC 3339 for binding into a query.
+///
+/// Cast it back with `<datetime>` in SurrealQL; `NONE` is bound when there is no datetime.
+pub fn datetime_string(datetime: Option<OffsetDateTime>) -> Option<String> {
+    datetime.and_then(|d| d.format(&Rfc3339).ok())
+}
+
+/// Formats a datetime as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`, for headers
+/// like `Last-Modified`.
+pub fn http_date(datetime: OffsetDateTime) -> String {
+    let d = datetime.to_offset(UtcOffset::UTC);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        &d.weekday().to_string()[..3],
+        d.day(),
+        &d.month().to_string()[..3],
+        d.year(),
+        d.hour(),
+        d.minute(),
+        d.second()
+    )
+}
+
+/// Escapes text for use in xml content and attribute values.
+pub fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Marks the start of a search match in text highlighted by the database. A private use
+/// character, so unlike a `<mark>` tag it can't be confused with markup written in a post.
+pub const HIGHLIGHT_START: char = '\u{E000}';
+
+/// Marks the end of a search match, see [`HIGHLIGHT_START`].
+pub const HIGHLIGHT_END: char = '\u{E001}';
+
+/// Escapes highlighted text for html, then wraps the matches in `<mark>` tags.
+pub fn mark_highlights(highlighted: &str) -> String {
+    xml_escape(highlighted)
+        .replace(HIGHLIGHT_START, "<mark>")
+        .replace(HIGHLIGHT_END, "</mark>")
+}
+
+/// Cuts a window of roughly `radius` characters either side of the first match in
+/// `highlighted`, adding ellipses where text was trimmed, and marks it up with
+/// [`mark_highlights`]. Falls back to the start of the text when nothing is marked.
+pub fn highlight_snippet(highlighted: &str, radius: usize) -> String {
+    let chars: Vec<char> = highlighted.chars().collect();
+    let mark_start = chars
+        .iter()
+        .position(|c| *c == HIGHLIGHT_START)
+        .unwrap_or(0);
+    let mark_end = chars
+        .iter()
+        .position(|c| *c == HIGHLIGHT_END)
+        .map(|idx| idx + 1)
+        .unwrap_or(mark_start);
+
+    let start = mark_start.saturating_sub(radius);
+    let end = (mark_end + radius).min(chars.len());
+
+    let mut snippet: String = chars[start..end].iter().collect();
+
+    // rebalance marks opened before or closed after the window
+    if let Some(close) = snippet.find(HIGHLIGHT_END) {
+        if snippet
+            .find(HIGHLIGHT_START)
+            .is_none_or(|open| close < open)
+        {
+            snippet.insert(0, HIGHLIGHT_START);
+        }
+    }
+    if snippet.matches(HIGHLIGHT_START).count() > snippet.matches(HIGHLIGHT_END).count() {
+        snippet.push(HIGHLIGHT_END);
+    }
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    out.push_str(&mark_highlights(snippet.trim()));
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
@@ -1,49 +1,94 @@
-use std::{env, net::SocketAddr};
+use std::{env, net::SocketAddr, sync::Arc};
 
 use axum::{
-    extract::{MatchedPath, Request},
+    extract::{DefaultBodyLimit, MatchedPath, Request},
     http::{
         header::{AUTHORIZATION, CONTENT_TYPE, COOKIE},
         HeaderValue, Method,
     },
     middleware::{from_fn, from_fn_with_state},
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use axum_server::tls_rustls::RustlsConfig;
 use constants::{
-    NB_ALLOWED_ORIGIN, NB_DB_ADDRESS, NB_DB_NAME, NB_DB_NAMESPACE, NB_DB_PSWD, NB_DB_USER,
-    NB_SERVER_ADDRESS, NB_TLS_CERT, NB_TLS_KEY,
+    NB_ALLOWED_ORIGIN, NB_IMAGE_WIDTHS, NB_MAX_UPLOAD_BYTES, NB_MEDIA_PUBLIC_URL, NB_MEDIA_ROOT,
+    NB_MEDIA_STORE, NB_REACTION_KINDS, NB_S3_ACCESS_KEY, NB_S3_BUCKET, NB_S3_ENDPOINT,
+    NB_S3_REGION, NB_S3_SECRET_KEY, NB_SERVER_ADDRESS, NB_SITE_DESCRIPTION, NB_SITE_TITLE,
+    NB_SITE_URL, NB_TLS_CERT, NB_TLS_KEY, NB_TRUSTED_PROXIES, NB_VIEW_SALT,
 };
 use nb_lib::{
-    db::SurrealDBConnection,
-    services::{s_persons::PersonsService, s_posts::PostsService},
+    constants::{
+        DEFAULT_IMAGE_WIDTHS, DEFAULT_MAX_UPLOAD_BYTES, DEFAULT_REACTION_KINDS, NB_DB_ADDRESS,
+        NB_DB_NAME, NB_DB_NAMESPACE, NB_DB_PSWD, NB_DB_USER,
+    },
+    models::feed::SiteConfig,
+    services::{
+        s_analytics::AnalyticsService, s_comments::CommentsService, s_feeds::FeedsService,
+        s_media::MediaService, s_persons::PersonsService, s_posts::PostsService,
+        s_previews::PreviewsService, s_reactions::ReactionsService, s_sitemap::SitemapService,
+    },
+    sitemap::{AuthorProfiles, PublishedPosts, StaticPages},
+    storage::{
+        local::LocalStore,
+        s3::{S3Config, S3Store},
+        MediaStore,
+    },
+    utils::{db_connection, get_env, locale_config},
 };
+use rand::distributions::{Alphanumeric, DistString};
 use rustls::crypto::{aws_lc_rs, CryptoProvider};
 use surrealdb::{engine::any::connect, opt::auth::Database};
 use tokio::net::TcpListener;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing::{debug, error, info, info_span, instrument, trace};
+use tracing::{debug, error, info, info_span, instrument, trace, warn};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 pub mod constants;
 pub mod controllers;
 pub mod errors;
+pub mod jobs;
 pub mod middleware;
 pub mod utils;
 
 use controllers::{
+    c_analytics::{
+        get_popular_posts, get_post_views, get_site_views, get_trending_posts, handle_record_view,
+    },
+    c_comments::{
+        get_moderation_queue, get_post_comments, handle_create_comment, handle_delete_comment,
+        handle_edit_comment, handle_moderate_comment, handle_update_comment_settings,
+    },
+    c_feeds::{get_atom_feed, get_json_feed, get_rss_feed},
+    c_media::{
+        get_media_file, get_media_library, get_media_references, handle_delete_media,
+        handle_update_media, handle_upload_media,
+    },
     c_persons::{
-        get_persons, handle_check_person_validity, handle_get_person, login_person, logout_person,
-        refresh_token, signup_person,
+        get_persons, handle_check_person_validity, handle_get_author, handle_get_person,
+        handle_update_profile, login_person, logout_person, refresh_token, signup_person,
     },
     c_posts::{
-        get_draft, get_drafted_posts, get_post_drafts, get_posts, get_published_posts,
-        handle_create_draft, handle_get_random_post, publish_draft, unpublish_post,
+        get_draft, get_drafted_posts, get_post_drafts, get_posts, get_published_post,
+        get_published_posts, get_related_posts, get_scheduled_changes, get_trash,
+        handle_checkpoint_draft, handle_create_draft, handle_diff_drafts, handle_get_random_post,
+        handle_purge_trash, handle_restore_draft, handle_restore_post, handle_revert_draft,
+        handle_schedule_draft, handle_search_posts, handle_trash_draft, handle_trash_post,
+        publish_draft, unpublish_post,
     },
+    c_previews::{get_oembed, get_post_head},
+    c_reactions::{
+        get_post_reactions, get_reaction_kinds, handle_add_reaction, handle_remove_reaction,
+    },
+    c_sitemap::{get_sitemap, get_sitemap_page},
+};
+use jobs::{
+    spawn_publish_scheduler, spawn_rankings_refresher, spawn_related_refresher,
+    spawn_visitor_pruner,
+};
+use middleware::{
+    get_request_id_service, identify_person, is_admin, require_authentication, NbBlogServices,
 };
-use middleware::{get_request_id_service, is_admin, require_authentication, NbBlogServices};
-use utils::get_env;
 
 #[instrument]
 #[tokio::main]
@@ -62,8 +107,19 @@ async fn main() {
 
     connect_to_db().await;
 
+    let state = init_services().await;
+
+    // drafts from before translations were added take DEFAULT_LOCALE
+    state.posts.backfill_draft_langs().await;
+
+    // start background jobs
+    spawn_publish_scheduler(state.posts.clone());
+    spawn_visitor_pruner(state.analytics.clone());
+    spawn_rankings_refresher(state.analytics.clone());
+    spawn_related_refresher(state.posts.clone());
+
     // build our application
-    let app = init_api().await;
+    let app = init_api(state).await;
 
     let port = get_env::<String>("SERVER_PORT")
         .parse()
@@ -105,7 +161,7 @@ async fn connect_to_db() {
 }
 
 // #[instrument]
-async fn init_api() -> Router {
+async fn init_api(state: NbBlogServices) -> Router {
     let allowed_origin: String = get_env(NB_ALLOWED_ORIGIN);
     println!("allowed origin val: {}", &allowed_origin);
 
@@ -116,12 +172,10 @@ async fn init_api() -> Router {
                 .parse::<HeaderValue>()
                 .expect("Unable to read allowed origin."),
         )
-        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
         .allow_headers([AUTHORIZATION, CONTENT_TYPE, COOKIE])
         .allow_credentials(true);
 
-    let state = init_services().await;
-
     Router::new()
         // admin persons routes
         .route("/persons", get(get_persons))
@@ -132,7 +186,50 @@ async fn init_api() -> Router {
         .route("/posts/drafts", post(handle_create_draft)) // ?publish=bool
         .route("/posts/drafts/{draft_id}/publish", post(publish_draft))
         .route("/posts/drafts/{draft_id}/publish", delete(unpublish_post))
+        .route(
+            "/posts/drafts/{draft_id}/schedule",
+            post(handle_schedule_draft),
+        )
+        .route("/posts/drafts/{draft_id}/revert", post(handle_revert_draft)) // ?publish=bool
+        .route("/posts/scheduled", get(get_scheduled_changes))
         .route("/posts/{post_id}/drafts", get(get_post_drafts))
+        .route("/posts/{post_id}/drafts/diff", get(handle_diff_drafts)) // ?from=&to=
+        .route("/posts/{post_id}", delete(handle_trash_post))
+        .route("/posts/{post_id}/restore", post(handle_restore_post))
+        .route("/posts/{post_id}/checkpoint", post(handle_checkpoint_draft))
+        .route("/posts/drafts/{draft_id}", delete(handle_trash_draft))
+        .route(
+            "/posts/drafts/{draft_id}/restore",
+            post(handle_restore_draft),
+        )
+        .route("/analytics/views", get(get_site_views)) // ?from=&to=
+        .route("/analytics/posts/{post_id}/views", get(get_post_views)) // ?from=&to=
+        .route("/posts/trash", get(get_trash))
+        .route("/posts/trash", delete(handle_purge_trash)) // ?older_than_days=
+        //
+        // admin comments routes
+        .route("/comments/moderation", get(get_moderation_queue)) // ?status=
+        .route(
+            "/comments/{comment_id}/status",
+            put(handle_moderate_comment),
+        )
+        .route(
+            "/posts/{post_id}/comments/settings",
+            put(handle_update_comment_settings),
+        )
+        //
+        // admin media library routes
+        .route(
+            "/media",
+            // leave room for the multipart framing around the file itself
+            post(handle_upload_media).layer(DefaultBodyLimit::max(
+                state.media.max_upload_bytes() + 64 * 1024,
+            )),
+        )
+        .route("/media", get(get_media_library)) // ?type=&q=
+        .route("/media/{key}", put(handle_update_media))
+        .route("/media/{key}", delete(handle_delete_media)) // ?force=bool
+        .route("/media/{key}/references", get(get_media_references))
         //
         .layer(from_fn(is_admin))
         // ^^ admin layer ^^
@@ -140,6 +237,15 @@ async fn init_api() -> Router {
         .route("/persons/{person_id}/logout", delete(logout_person))
         // eventual endpoints for profiles, comments, etc. will go in between the authorization check and the admin check
         .route("/persons/{person_id}", get(handle_get_person))
+        .route("/persons/{person_id}/profile", put(handle_update_profile))
+        .route("/posts/{post_id}/comments", post(handle_create_comment))
+        .route("/comments/{comment_id}", put(handle_edit_comment))
+        .route("/comments/{comment_id}", delete(handle_delete_comment))
+        .route("/posts/{post_id}/reactions", post(handle_add_reaction))
+        .route(
+            "/posts/{post_id}/reactions/{kind}",
+            delete(handle_remove_reaction),
+        )
         //
         .layer(from_fn_with_state(state.clone(), require_authentication))
         // ^^ authentication layer ^^
@@ -152,9 +258,48 @@ async fn init_api() -> Router {
         .route("/persons/valid", get(handle_check_person_validity))
         //
         // anonymous public posts routes
-        .route("/posts/drafts/{draft_id}", get(get_draft))
-        .route("/posts/random", get(handle_get_random_post))
-        .route("/posts/published", get(get_published_posts))
+        .route(
+            "/posts/drafts/{draft_id}",
+            get(get_draft).route_layer(from_fn_with_state(state.clone(), identify_person)),
+        )
+        .route("/authors/{author_id}", get(handle_get_author))
+        .route(
+            "/posts/random",
+            get(handle_get_random_post)
+                .route_layer(from_fn_with_state(state.clone(), identify_person)),
+        )
+        .route("/posts/popular", get(get_popular_posts)) // ?window=&limit=
+        .route("/posts/trending", get(get_trending_posts)) // ?limit=
+        .route("/posts/search", get(handle_search_posts)) // ?q=
+        .route(
+            "/posts/published",
+            get(get_published_posts)
+                .route_layer(from_fn_with_state(state.clone(), identify_person)),
+        )
+        .route(
+            "/posts/{post_id}",
+            get(get_published_post).route_layer(from_fn_with_state(state.clone(), identify_person)),
+        ) // ?lang=&format=html
+        .route("/posts/{post_id}/views", post(handle_record_view))
+        .route("/posts/{post_id}/related", get(get_related_posts)) // ?limit=
+        .route("/posts/{post_id}/comments", get(get_post_comments))
+        .route("/reactions/kinds", get(get_reaction_kinds))
+        .route("/media/{key}", get(get_media_file))
+        //
+        // syndication feeds, ?tag=&full=bool
+        .route("/feed.xml", get(get_rss_feed))
+        .route("/atom.xml", get(get_atom_feed))
+        .route("/feed.json", get(get_json_feed))
+        .route("/sitemap.xml", get(get_sitemap))
+        .route("/sitemaps/{page}", get(get_sitemap_page)) // {n}.xml
+        //
+        // link previews
+        .route("/posts/{post_id}/head", get(get_post_head))
+        .route("/oembed", get(get_oembed)) // ?url=&format=json
+        .route(
+            "/posts/{post_id}/reactions",
+            get(get_post_reactions).route_layer(from_fn_with_state(state.clone(), identify_person)),
+        )
         .layer(
             TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
                 // Log the matched route's path (with placeholders not filled in).
@@ -185,23 +330,110 @@ async fn init_api() -> Router {
 }
 
 async fn init_services() -> NbBlogServices {
-    let addr = get_env::<String>(NB_DB_ADDRESS);
-    let user = get_env::<String>(NB_DB_USER);
-    let pass = get_env::<String>(NB_DB_PSWD);
-    let namespace = get_env::<String>(NB_DB_NAMESPACE);
-    let db = get_env::<String>(NB_DB_NAME);
-
-    let conn = SurrealDBConnection {
-        address: addr,
-        username: user,
-        password: pass,
-        namespace: namespace,
-        database: db,
+    let conn = db_connection();
+
+    let view_salt = env::var(NB_VIEW_SALT).unwrap_or_else(|_| {
+        warn!(
+            "{} not set, visitors will be counted again after a restart",
+            NB_VIEW_SALT
+        );
+        Alphanumeric.sample_string(&mut rand::thread_rng(), 32)
+    });
+
+    let trusted_proxies = env::var(NB_TRUSTED_PROXIES)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .filter_map(|ip| match ip.parse() {
+            Ok(ip) => Some(ip),
+            Err(_) => {
+                warn!("ignoring invalid {} entry: {}", NB_TRUSTED_PROXIES, ip);
+                None
+            }
+        })
+        .collect();
+
+    let reaction_kinds = env::var(NB_REACTION_KINDS)
+        .ok()
+        .map(|kinds| {
+            kinds
+                .split(',')
+                .map(|k| k.trim().to_string())
+                .filter(|k| !k.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|kinds| !kinds.is_empty())
+        .unwrap_or_else(|| {
+            DEFAULT_REACTION_KINDS
+                .iter()
+                .map(|k| k.to_string())
+                .collect()
+        });
+
+    let site = SiteConfig {
+        url: get_env::<String>(NB_SITE_URL)
+            .trim_end_matches('/')
+            .to_string(),
+        title: env::var(NB_SITE_TITLE).unwrap_or_else(|_| "nb blog".into()),
+        description: env::var(NB_SITE_DESCRIPTION).unwrap_or_default(),
+    };
+
+    let locales = locale_config();
+
+    let media_store: Arc<dyn MediaStore> = match env::var(NB_MEDIA_STORE).as_deref() {
+        Ok("s3") => Arc::new(S3Store::new(S3Config {
+            bucket: get_env(NB_S3_BUCKET),
+            region: env::var(NB_S3_REGION).unwrap_or_else(|_| "us-east-1".into()),
+            endpoint: env::var(NB_S3_ENDPOINT).ok().filter(|e| !e.is_empty()),
+            access_key: get_env(NB_S3_ACCESS_KEY),
+            secret_key: get_env(NB_S3_SECRET_KEY),
+        })),
+        _ => Arc::new(LocalStore::new(
+            env::var(NB_MEDIA_ROOT).unwrap_or_else(|_| "media".into()),
+        )),
     };
+    let max_upload_bytes = env::var(NB_MAX_UPLOAD_BYTES)
+        .ok()
+        .and_then(|b| b.parse().ok())
+        .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES);
+    let image_widths = env::var(NB_IMAGE_WIDTHS)
+        .ok()
+        .map(|widths| {
+            widths
+                .split(',')
+                .filter_map(|w| w.trim().parse().ok())
+                .filter(|w| *w > 0)
+                .collect::<Vec<u32>>()
+        })
+        .unwrap_or_else(|| DEFAULT_IMAGE_WIDTHS.to_vec());
 
     NbBlogServices {
-        posts: PostsService::new(conn.clone()).await,
+        posts: PostsService::new(conn.clone(), locales.clone()).await,
         persons: PersonsService::new(conn.clone()).await,
+        analytics: AnalyticsService::new(conn.clone(), view_salt, trusted_proxies).await,
+        comments: CommentsService::new(conn.clone()).await,
+        reactions: ReactionsService::new(conn.clone(), reaction_kinds).await,
+        feeds: FeedsService::new(conn.clone(), site.clone(), locales.clone()).await,
+        previews: PreviewsService::new(conn.clone(), site.clone(), locales).await,
+        media: MediaService::new(
+            conn.clone(),
+            media_store,
+            env::var(NB_MEDIA_PUBLIC_URL).unwrap_or_else(|_| "/media".into()),
+            max_upload_bytes,
+            image_widths,
+        )
+        .await,
+        sitemap: SitemapService::new(
+            conn.clone(),
+            site,
+            vec![
+                Box::new(StaticPages::new(&["/"])),
+                Box::new(PublishedPosts::new()),
+                Box::new(AuthorProfiles::new()),
+            ],
+        )
+        .await,
     }
 }
 
@@ -234,7 +466,7 @@ async fn serve(app: Router, port: u16) {
         info!("listening on {}", addr);
 
         axum_server::bind_rustls(addr, tls_conf)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
             .expect("Unable to create server!");
     } else {
@@ -242,8 +474,11 @@ async fn serve(app: Router, port: u16) {
             .await
             .expect("Unable to create TCPListener.");
         info!("listening on {}", addr);
-        axum::serve(listener, app.into_make_service())
-            .await
-            .expect("Unable to create server!");
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .expect("Unable to create server!");
     }
 }
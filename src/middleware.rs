@@ -21,7 +21,11 @@ use jwt_simple::{
 };
 use nb_lib::{
     models::{custom_claims::CustomClaims, person::Person},
-    services::{s_persons::PersonsService, s_posts::PostsService},
+    services::{
+        s_analytics::AnalyticsService, s_comments::CommentsService, s_feeds::FeedsService,
+        s_media::MediaService, s_persons::PersonsService, s_posts::PostsService,
+        s_previews::PreviewsService, s_reactions::ReactionsService, s_sitemap::SitemapService,
+    },
 };
 use tower::{layer::util::Stack, ServiceBuilder};
 use tower_http::request_id::{
@@ -38,6 +42,13 @@ use crate::{
 pub struct NbBlogServices {
     pub posts: PostsService,
     pub persons: PersonsService,
+    pub analytics: AnalyticsService,
+    pub comments: CommentsService,
+    pub reactions: ReactionsService,
+    pub feeds: FeedsService,
+    pub sitemap: SitemapService,
+    pub previews: PreviewsService,
+    pub media: MediaService,
 }
 
 #[instrument(skip(req, next))]
@@ -138,6 +149,34 @@ pub async fn require_authentication(
     }
 }
 
+/// Like [`require_authentication`], but lets anonymous requests through. The current person
+/// is only inserted into the request extensions when a valid bearer token is sent.
+#[instrument(skip(services, req, next))]
+pub async fn identify_person(
+    State(services): State<NbBlogServices>,
+    mut req: Request,
+    next: Next,
+) -> impl IntoResponse {
+    let token = get_authorization_header(&req)
+        .ok()
+        .and_then(|h| h.strip_prefix("Bearer ").map(String::from));
+
+    if let Some(token) = token {
+        match verify_token(&token) {
+            Ok(claims) => {
+                if let Some(person_id) = claims.subject {
+                    if let Some(current_person) = services.persons.get_person(person_id).await {
+                        req.extensions_mut().insert(current_person);
+                    }
+                }
+            }
+            Err(e) => debug!("ignoring unverifiable token: {}", e),
+        }
+    }
+
+    next.run(req).await
+}
+
 #[instrument(skip(req))]
 fn get_authorization_header(req: &Request) -> Result<String, String> {
     let auth_header = req
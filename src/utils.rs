@@ -1,7 +1,9 @@
-use std::env;
+use axum::http::{header::ACCEPT_LANGUAGE, HeaderMap};
+use nb_lib::models::locale::{LangArgs, LocaleChoice, LocaleConfig};
 
-pub fn get_env<T: From<std::string::String>>(env_key: &str) -> T {
-    env::var(env_key)
-        .expect(format!("cannot find {}", env_key).as_str())
-        .into()
+/// The language to read published posts in, from `?lang=` or the `Accept-Language` header.
+pub fn locale_choice(locales: &LocaleConfig, args: &LangArgs, headers: &HeaderMap) -> LocaleChoice {
+    let accept_language = headers.get(ACCEPT_LANGUAGE).and_then(|h| h.to_str().ok());
+
+    locales.negotiate(args.lang.as_deref(), accept_language)
 }